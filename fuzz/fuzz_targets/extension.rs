@@ -0,0 +1,10 @@
+#![no_main]
+
+use fuzzy_rename_rs::fuzzing;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|name: &str| {
+    let (body, extension) = fuzzing::split_extension(name);
+    // Nothing is lost: either there is no extension, or body and extension rejoin to the name
+    assert!((body == name && extension.is_empty()) || format!("{body}.{extension}") == name);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use fuzzy_rename_rs::fuzzing;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|name: &str| {
+    fuzzing::normalize(name);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use fuzzy_rename_rs::fuzzing;
+use libfuzzer_sys::fuzz_target;
+
+// The first line is the template, the rest the name filled into it
+fuzz_target!(|input: &str| {
+    let (template, name) = input.split_once('\n').unwrap_or((input, ""));
+    let _ = fuzzing::render_template(template, name);
+});
@@ -0,0 +1,108 @@
+//! Text shared by (nearly) every name, like "ShowName - " before each episode title, which can be
+//! left out when matching so the distinctive part of each name decides.
+
+use std::collections::HashMap;
+
+/// Share of the names that must have an affix for it to count as common
+const COMMON_SHARE: f32 = 0.9;
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '_' | '.' | '-')
+}
+
+/// Prefixes of `name` that end where a word starts, e.g. "ShowName - " in "ShowName - Pilot"
+fn prefixes(name: &str) -> impl Iterator<Item = &str> {
+    name.char_indices()
+        .zip(name.chars().skip(1))
+        .filter(|((_, c), next)| is_separator(*c) && !is_separator(*next))
+        .map(move |((i, c), _)| &name[..i + c.len_utf8()])
+}
+
+/// Suffixes of `name` that start where a word ends, e.g. " [1080p]" in "Pilot [1080p]"
+fn suffixes(name: &str) -> impl Iterator<Item = &str> {
+    name.char_indices()
+        .zip(name.chars().skip(1))
+        .filter(|((_, c), next)| !is_separator(*c) && is_separator(*next))
+        .map(move |((i, c), _)| &name[i + c.len_utf8()..])
+}
+
+/// The longest of the `affixes` of each name that nearly all `names` share
+fn most_common<'a, I: Iterator<Item = &'a str>>(
+    names: &[&'a str],
+    affixes: impl Fn(&'a str) -> I,
+) -> Option<String> {
+    if names.len() < 2 {
+        return None;
+    }
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for name in names {
+        for affix in affixes(name) {
+            *counts.entry(affix).or_default() += 1;
+        }
+    }
+    let required = (names.len() as f32 * COMMON_SHARE).ceil() as usize;
+    counts
+        .into_iter()
+        .filter(|(affix, count)| *count >= required && !affix.trim().is_empty())
+        .max_by_key(|(affix, _)| affix.len())
+        .map(|(affix, _)| affix.to_owned())
+}
+
+/// Longest prefix ending at a word boundary that nearly all `names` start with
+pub fn common_prefix(names: &[&str]) -> Option<String> {
+    most_common(names, prefixes)
+}
+
+/// Longest suffix starting at a word boundary that nearly all `names` end with
+pub fn common_suffix(names: &[&str]) -> Option<String> {
+    most_common(names, suffixes)
+}
+
+/// `name` without `prefix` and `suffix`, each removed only where present. Never leaves it empty.
+pub fn strip<'a>(name: &'a str, prefix: &str, suffix: &str) -> &'a str {
+    let stripped = name.strip_prefix(prefix).unwrap_or(name);
+    let stripped = stripped.strip_suffix(suffix).unwrap_or(stripped);
+    if stripped.is_empty() {
+        name
+    } else {
+        stripped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_text_ends_at_word_boundaries() {
+        let names = [
+            "ShowName - S01 - Pilot [1080p]",
+            "ShowName - S01 - Second Part [1080p]",
+            "ShowName - S01 - Finale [1080p]",
+        ];
+        assert_eq!(common_prefix(&names).as_deref(), Some("ShowName - S01 - "));
+        assert_eq!(common_suffix(&names).as_deref(), Some(" [1080p]"));
+
+        assert_eq!(common_prefix(&["Alpha", "Alphabet"]), None);
+        assert_eq!(common_prefix(&["ShowName - Pilot"]), None);
+    }
+
+    #[test]
+    fn nearly_every_name_must_share_it() {
+        let mut names = vec!["Show - Episode"; 9];
+        names.push("Other");
+        assert_eq!(common_prefix(&names).as_deref(), Some("Show - "));
+        names[0] = "Other";
+        assert_eq!(common_prefix(&names), None);
+    }
+
+    #[test]
+    fn stripping_never_leaves_nothing() {
+        assert_eq!(
+            strip("Show - Pilot [1080p]", "Show - ", " [1080p]"),
+            "Pilot"
+        );
+        assert_eq!(strip("Pilot", "Show - ", " [1080p]"), "Pilot");
+        assert_eq!(strip("Show - ", "Show - ", ""), "Show - ");
+    }
+}
@@ -1,89 +1,834 @@
-use std::{fs, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::{hash_map::DefaultHasher, BinaryHeap, HashMap, HashSet, VecDeque},
+    ffi::OsString,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    mem::size_of,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
 
 use egui::*;
 use egui_extras::{Column, TableBuilder};
+use regex::Regex;
 
 use std::fs::read_dir;
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::job::{self, Job};
+use crate::{
+    affix, crc, dialog, exif,
+    history::{Edits, History, ListChange},
+    import,
+    intern::{self, Name},
+    lint::{Convention, NamingRules},
+    metrics,
+    normalize::{self, ArticleLanguage},
+    paths, rename, report,
+    session::{Session, SessionChoice, SessionSource},
+    tags, template,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{automation, copier, hooks, scan, task::Task};
+
 const CHOICE_PREVIEW_COUNT: usize = 10;
+/// Candidates kept per source in low-memory mode
+const LOW_MEMORY_PREVIEW_COUNT: usize = 3;
+/// Affected files listed in a confirmation before the full list is opened
+const CONFIRM_LISTED_COUNT: usize = 5;
+/// Statuses kept in the log panel
+const LOG_LENGTH: usize = 500;
+/// Source and choice pairs past which scoring everything gets slow, 10,000 of each
+const LARGE_PAIR_COUNT: usize = 100_000_000;
+/// Rough size of one similarity matrix row as CSV, for warning before large exports
+const MATRIX_ROW_BYTES: usize = 40;
+/// Similarity matrix exports past this size ask first
+const LARGE_EXPORT_BYTES: usize = 1 << 30;
+/// The walkthrough shown on first launch, by the menu each step is about
+const ONBOARDING_STEPS: [(&str, &str); 3] = [
+    (
+        "1. Sources",
+        "Start with the files to rename: import a folder or files from the Sources menu.",
+    ),
+    (
+        "2. Choices",
+        "Then add the names they should get from the Choices menu, as reference files or a \
+         list of names. Each source is matched to the most similar choice.",
+    ),
+    (
+        "3. Output",
+        "Check the matches in the table and pick another where one is wrong, then copy or \
+         rename the files from the Output menu.",
+    ),
+];
 const WASM_NO_FOLDERS_TOOLTIP: &str = "Cannot access folders in web build";
 
+/// Template tokens filled from the capture metadata of images, see [`exif`]
+const EXIF_TOKENS: [&str; 2] = ["{exif_date", "{camera"];
+
+/// Sources a background rescore scores between checks for being cancelled
+#[cfg(not(target_arch = "wasm32"))]
+const SCORE_ROUND: usize = 1024;
+
+/// Entries a folder import adds per frame; the rest wait for the next frames
+#[cfg(not(target_arch = "wasm32"))]
+const IMPORT_ENTRIES_PER_FRAME: usize = 2000;
+
+/// Subtitle files accepted as sources in [`MatchMode::Subtitles`]
+const SUBTITLE_EXTENSIONS: [&str; 6] = ["srt", "ass", "ssa", "sub", "idx", "vtt"];
+
+/// Extensions that span more than one dot, kept together when renaming
+const MULTI_EXTENSIONS: [&str; 5] = ["tar.gz", "tar.bz2", "tar.xz", "tar.zst", "user.js"];
+
+/// Splits a file name into body and extension, like [`Path::extension`]: a leading dot
+/// (`.gitignore`) is part of the body, and names without a dot have an empty extension.
+pub(crate) fn split_extension(s: &str) -> (&str, &str) {
+    let Some(dot) = s.rfind('.').filter(|&dot| dot > 0) else {
+        return (s, "");
+    };
+    let lower = s.to_ascii_lowercase();
+    MULTI_EXTENSIONS
+        .iter()
+        .filter(|multi| lower.len() > multi.len() + 1 && lower.ends_with(*multi))
+        .map(|multi| s.len() - multi.len())
+        .find(|&start| s.as_bytes()[start - 1] == b'.')
+        .map_or((&s[..dot], &s[dot + 1..]), |start| {
+            (&s[..start - 1], &s[start..])
+        })
+}
+
 fn remove_extension(s: &str) -> &str {
-    &s[0..s.rfind('.').unwrap_or(s.len())]
+    split_extension(s).0
 }
 
-fn pick_folder(directory: &str, title: &str) -> Option<PathBuf> {
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        return rfd::FileDialog::new()
-            .set_directory(directory)
-            .set_title(title)
-            .pick_folder();
+pub(crate) fn apply_template(
+    name_template: &str,
+    body: &str,
+    source: &str,
+    choice: &str,
+    number: Option<usize>,
+    exif: Option<&exif::Exif>,
+) -> String {
+    if name_template.is_empty() || name_template == "{name}" {
+        return body.to_owned();
     }
-    #[cfg(target_arch = "wasm32")]
-    {
-        return None;
+
+    let tags = normalize::split_language_tags(source).1;
+    let lang = if tags.is_empty() {
+        String::new()
+    } else {
+        format!(".{tags}")
+    };
+    let mut values = vec![
+        ("name", template::Value::Text(body)),
+        ("source", template::Value::Text(source)),
+        ("choice", template::Value::Text(choice)),
+        ("lang", template::Value::Text(&lang)),
+        (
+            "exif_date",
+            template::Value::Text(exif.and_then(|e| e.date.as_deref()).unwrap_or("")),
+        ),
+        (
+            "camera",
+            template::Value::Text(exif.and_then(|e| e.camera.as_deref()).unwrap_or("")),
+        ),
+    ];
+    if let Some(number) = number {
+        values.push(("n", template::Value::Number(number)));
     }
+    // Names without a year or episode keep those tokens as written, for the naming rules to flag
+    let (title, year) = match normalize::split_year(body) {
+        Some((title, year)) => (title, Some(year)),
+        None => (body, None),
+    };
+    values.push(("title", template::Value::Text(title)));
+    if let Some(year) = year {
+        values.push(("year", template::Value::Number(year as usize)));
+    }
+    let episode = normalize::episode(body);
+    values.push((
+        "show",
+        template::Value::Text(episode.as_ref().map_or(body, |e| e.show)),
+    ));
+    if let Some(episode) = episode {
+        values.push(("season", template::Value::Number(episode.season)));
+        values.push(("episode", template::Value::Number(episode.episode)));
+    }
+    template::render(name_template, &values)
 }
 
-fn pick_files(directory: &str, title: &str) -> Option<Vec<PathBuf>> {
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        return rfd::FileDialog::new()
-            .set_directory(directory)
-            .set_title(title)
-            .pick_files();
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
     }
-    #[cfg(target_arch = "wasm32")]
-    {
-        // TODO: Implement async file choice
-        return None;
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Formats a time as `YYYY-MM-DD HH:MM` in UTC
+fn format_date(time: SystemTime) -> String {
+    let Ok(elapsed) = time.duration_since(SystemTime::UNIX_EPOCH) else {
+        return "".to_owned();
+    };
+    let seconds = elapsed.as_secs();
+    let (days, minutes) = (seconds / 86400, seconds % 86400 / 60);
+
+    // Civil date from days since 1970-01-01, see Howard Hinnant's `civil_from_days`
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// Offers to copy a table cell, or its whole row, on right click
+fn copy_menu(response: Response, cell: &str, row: &str) -> Response {
+    response.context_menu(|ui| {
+        if ui.button("Copy").clicked() {
+            ui.output().copied_text = cell.to_owned();
+            ui.close_menu();
+        }
+        if ui.button("Copy row").clicked() {
+            ui.output().copied_text = row.to_owned();
+            ui.close_menu();
+        }
+    })
+}
+
+/// Shows `text` in full on hover when it doesn't fit in its table cell
+fn hover_if_clipped(ui: &Ui, response: Response, text: &str) -> Response {
+    if ui.clip_rect().contains_rect(response.rect) {
+        response
+    } else {
+        response.on_hover_text(text)
+    }
+}
+
+/// Shows the full path of `file` with its size and date, since the table only shows names and
+/// files from different folders can share one
+fn hover_file_details(response: Response, file: &FilePath) -> Response {
+    let mut details = vec![if file.path.as_os_str().is_empty() {
+        format!("{} (listed name, not a file)", file.name)
+    } else {
+        file.path.to_string_lossy().into_owned()
+    }];
+    if let Some(size) = file.size {
+        details.push(format!("Size: {}", format_size(size)));
+    }
+    if let Some(modified) = file.modified {
+        details.push(format!("Modified: {}", format_date(modified)));
+    }
+    response.on_hover_text(details.join("\n"))
+}
+
+/// Status message for an import, e.g. "Added 3 source(s), skipped 2 duplicate(s)"
+fn added_message(count: usize, skipped: usize, noun: &str) -> String {
+    if skipped > 0 {
+        format!("Added {count} {noun}, skipped {skipped} duplicate(s)")
+    } else {
+        format!("Added {count} {noun}")
+    }
+}
+
+/// Copies that don't borrow the app, for handing over to another thread
+fn owned_copies(copies: Vec<(&PathBuf, PathBuf)>) -> Vec<(PathBuf, PathBuf)> {
+    copies
+        .into_iter()
+        .map(|(origin, destination)| (origin.clone(), destination))
+        .collect()
+}
+
+/// Default group for choices imported together, the folder or list file they came from
+fn batch_name(path: &Path) -> String {
+    path.file_stem()
+        .map_or("".to_owned(), |name| name.to_string_lossy().into_owned())
+}
+
+/// Joins a body and extension back together, without a trailing dot when there is no extension
+fn with_extension(body: &str, extension: &str) -> String {
+    if extension.is_empty() {
+        body.to_owned()
+    } else {
+        format!("{body}.{extension}")
+    }
+}
+
+/// Groups extensions that usually stand in for each other, e.g. `mkv` and `mp4`
+fn extension_family(name: &str) -> &str {
+    let extension = split_extension(name).1;
+    let extension = extension.rsplit('.').next().unwrap_or(extension);
+    EXTENSION_FAMILIES
+        .iter()
+        .find(|(_, members)| members.iter().any(|m| m.eq_ignore_ascii_case(extension)))
+        .map_or(extension, |(family, _)| family)
+}
+
+const EXTENSION_FAMILIES: [(&str, &[&str]); 6] = [
+    (
+        "video",
+        &[
+            "mkv", "mp4", "avi", "mov", "wmv", "webm", "m4v", "mpg", "mpeg",
+        ],
+    ),
+    (
+        "audio",
+        &["mp3", "flac", "ogg", "opus", "wav", "m4a", "aac", "wma"],
+    ),
+    (
+        "image",
+        &["png", "jpg", "jpeg", "gif", "bmp", "webp", "tif", "tiff"],
+    ),
+    ("subtitle", &["srt", "ass", "ssa", "sub", "vtt", "idx"]),
+    ("archive", &["zip", "7z", "rar", "tar", "gz"]),
+    ("text", &["txt", "md", "nfo", "csv", "json", "xml"]),
+];
+
+fn pick_folder(directory: &str, title: &str) -> dialog::Request {
+    dialog::Request {
+        kind: dialog::Kind::Folder,
+        directory: directory.to_owned(),
+        title: title.to_owned(),
     }
 }
 
+fn pick_file(directory: &str, title: &str, filter: &str, extensions: &[&str]) -> dialog::Request {
+    dialog::Request {
+        kind: dialog::Kind::File {
+            filter: filter.to_owned(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+        },
+        directory: directory.to_owned(),
+        title: title.to_owned(),
+    }
+}
+
+fn save_file(directory: &str, title: &str, file_name: &str) -> dialog::Request {
+    dialog::Request {
+        kind: dialog::Kind::Save {
+            file_name: file_name.to_owned(),
+        },
+        directory: directory.to_owned(),
+        title: title.to_owned(),
+    }
+}
+
+fn pick_files(directory: &str, title: &str) -> dialog::Request {
+    dialog::Request {
+        kind: dialog::Kind::Files,
+        directory: directory.to_owned(),
+        title: title.to_owned(),
+    }
+}
+
+/// Runs with what was chosen once a file dialog closes
+type DialogHandler = Box<dyn FnOnce(&mut MainApp, Vec<PathBuf>, &Context)>;
+
+/// What to run once the user confirms it, see [`MainApp::confirm_overlaps`]
+type ConfirmedAction = Box<dyn FnOnce(&mut MainApp, &Context)>;
+
+/// An action that changes many files, waiting for the user to go ahead after seeing which
+struct Confirmation {
+    title: &'static str,
+    /// What happens, with how many files
+    summary: String,
+    /// The affected files, as shown in the list
+    files: Vec<String>,
+    /// Label of the button that runs `action`
+    accept: &'static str,
+    action: ConfirmedAction,
+}
+
 #[derive(Clone, Default)]
 struct FilePath {
-    name: String,
+    name: Name,
     path: PathBuf,
+    /// Resolved absolute path, for spotting the same file imported twice
+    canonical: PathBuf,
+    modified: Option<SystemTime>,
+    size: Option<u64>,
+    is_dir: bool,
+    /// CRC-32 of the contents, once read from the file or listed in a DAT
+    crc: Option<u32>,
+    /// Capture metadata of images, once read for a name template that uses it
+    exif: Option<exif::Exif>,
+}
+
+impl FilePath {
+    /// The name without its extension; folders keep their whole name, dots included
+    fn stem(&self) -> &str {
+        if self.is_dir {
+            &self.name
+        } else {
+            remove_extension(&self.name)
+        }
+    }
+
+    /// The extension without its dot, empty for folders
+    fn extension(&self) -> &str {
+        if self.is_dir {
+            ""
+        } else {
+            split_extension(&self.name).1
+        }
+    }
+
+    /// Kind of file, like `video`, or its lowercase extension when not a known kind
+    fn kind(&self) -> String {
+        if self.is_dir {
+            "folder".to_owned()
+        } else {
+            extension_family(&self.name).to_ascii_lowercase()
+        }
+    }
+
+    /// The known checksum, or else the one tagged in the name like `[A1B2C3D4]`
+    fn checksum(&self) -> Option<u32> {
+        self.crc.or_else(|| crc::from_name(&self.name))
+    }
+
+    /// Whether the checksum is still to be read from the file: it isn't known and there is one
+    #[cfg(not(target_arch = "wasm32"))]
+    fn needs_checksum(&self) -> bool {
+        self.crc.is_none() && !self.is_dir && !self.path.as_os_str().is_empty()
+    }
+
+    /// Reads the checksum of the file, unless known already or there is no file
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_checksum(&mut self) {
+        if self.needs_checksum() {
+            self.crc = crc::of_file(&self.path).ok();
+        }
+    }
 }
 
 impl TryFrom<PathBuf> for FilePath {
     type Error = ();
 
     fn try_from(value: PathBuf) -> Result<Self, Self::Error> {
-        let filename = value
-            .file_name()
-            .and_then(|f| f.to_str().map(|f| f.to_owned()));
+        // Names that aren't valid UTF-8 are shown and matched in lossy form, `path` keeps the original
+        let filename = paths::file_name(&value);
+        let metadata = fs::metadata(&value).ok();
+        let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+        let is_dir = metadata.as_ref().map_or(false, |m| m.is_dir());
+        let size = metadata.filter(|m| !m.is_dir()).map(|m| m.len());
+        let canonical = fs::canonicalize(&value).unwrap_or_else(|_| value.clone());
         filename
-            .map(|name| Self { path: value, name })
+            .map(|name| Self {
+                canonical,
+                path: value,
+                name: name.into(),
+                modified,
+                size,
+                is_dir,
+                crc: None,
+                exif: None,
+            })
             .ok_or(Default::default())
     }
 }
 
-#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq)]
-enum SearchAlgorithm {
+#[derive(Clone, Default)]
+struct ChoiceName {
+    file: FilePath,
+    /// Name given to matched files instead of the choice's own name
+    rename_to: Option<String>,
+    /// Import batch or tag, sources can be limited to one group; empty when ungrouped
+    group: String,
+    /// Left out of every source's candidates
+    excluded: bool,
+    /// Path of the only source this choice may match, see [`MainApp::reserve_choice`]
+    owner: Option<PathBuf>,
+}
+
+impl TryFrom<PathBuf> for ChoiceName {
+    type Error = ();
+
+    fn try_from(value: PathBuf) -> Result<Self, Self::Error> {
+        FilePath::try_from(value).map(|file| Self {
+            file,
+            ..Default::default()
+        })
+    }
+}
+
+impl ChoiceName {
+    /// Hash of what can be edited of the choice, to notice edits cheaply
+    fn edit_key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.file.path.hash(&mut hasher);
+        self.file.name.hash(&mut hasher);
+        self.rename_to.hash(&mut hasher);
+        self.group.hash(&mut hasher);
+        self.excluded.hash(&mut hasher);
+        self.owner.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Reads the file at `path`, matched against as `name` when given
+    fn read(path: PathBuf, name: Option<String>) -> Result<Self, Skipped> {
+        // Missing files, broken links and ones without permission. Files chosen in a browser
+        // can only be read there.
+        #[cfg(not(target_arch = "wasm32"))]
+        fs::metadata(&path).map_err(|_| Skipped::Unreadable)?;
+        let mut choice = ChoiceName::try_from(path).map_err(|_| Skipped::Unreadable)?;
+        if let Some(name) = name {
+            choice.file.name = name.into();
+        }
+        Ok(choice)
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum SearchAlgorithm {
+    #[default]
     Jaro,
     JaroWinkler,
     Levenshtein,
     DamerauLevenshtein,
+    /// Levenshtein over whole words
+    TokenLevenshtein,
+    /// Longest common subsequence ratio
+    Lcs,
+    /// Longest common substring ratio
+    LongestSubstring,
 }
 
 impl SearchAlgorithm {
+    const ALL: [SearchAlgorithm; 7] = [
+        SearchAlgorithm::Jaro,
+        SearchAlgorithm::JaroWinkler,
+        SearchAlgorithm::Levenshtein,
+        SearchAlgorithm::DamerauLevenshtein,
+        SearchAlgorithm::TokenLevenshtein,
+        SearchAlgorithm::Lcs,
+        SearchAlgorithm::LongestSubstring,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SearchAlgorithm::Jaro => "Jaro",
+            SearchAlgorithm::JaroWinkler => "Jaro Winkler",
+            SearchAlgorithm::Levenshtein => "Levenshtein",
+            SearchAlgorithm::DamerauLevenshtein => "Damerau Levenshtein",
+            SearchAlgorithm::TokenLevenshtein => "Word Levenshtein",
+            SearchAlgorithm::Lcs => "Common Subsequence",
+            SearchAlgorithm::LongestSubstring => "Common Substring",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        match self {
+            SearchAlgorithm::TokenLevenshtein => Some("Edit distance counted in whole words"),
+            SearchAlgorithm::LongestSubstring => Some("Favors names sharing a long exact core"),
+            _ => None,
+        }
+    }
+
     fn compare(&self, a: &str, b: &str) -> f64 {
         match self {
             SearchAlgorithm::Jaro => strsim::jaro(a, b),
             SearchAlgorithm::JaroWinkler => strsim::jaro_winkler(a, b),
-            SearchAlgorithm::Levenshtein => strsim::normalized_levenshtein(a, b),
+            SearchAlgorithm::Levenshtein => metrics::normalized_levenshtein(a, b),
             SearchAlgorithm::DamerauLevenshtein => strsim::normalized_damerau_levenshtein(a, b),
+            SearchAlgorithm::TokenLevenshtein => metrics::token_levenshtein(a, b),
+            SearchAlgorithm::Lcs => metrics::lcs_ratio(a, b),
+            SearchAlgorithm::LongestSubstring => metrics::longest_substring_ratio(a, b),
         }
     }
 }
 
-#[derive(Default)]
+/// What happens when both names have a year and none of them agree
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq, Default)]
+enum YearCheck {
+    #[default]
+    Ignore,
+    /// Multiply the score by `YEAR_PENALTY`
+    Penalize,
+    /// Never match them
+    Require,
+}
+
+/// Score factor for names with different years, see [`YearCheck::Penalize`]
+const YEAR_PENALTY: f64 = 0.5;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq, Default)]
+enum Tiebreaker {
+    #[default]
+    None,
+    ClosestLength,
+    SameExtensionFamily,
+    SameFolder,
+    Alphabetical,
+}
+
+impl Tiebreaker {
+    const ALL: [Tiebreaker; 5] = [
+        Tiebreaker::None,
+        Tiebreaker::ClosestLength,
+        Tiebreaker::SameExtensionFamily,
+        Tiebreaker::SameFolder,
+        Tiebreaker::Alphabetical,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Tiebreaker::None => "None",
+            Tiebreaker::ClosestLength => "Closest length",
+            Tiebreaker::SameExtensionFamily => "Same kind of file",
+            Tiebreaker::SameFolder => "Same folder",
+            Tiebreaker::Alphabetical => "Alphabetical",
+        }
+    }
+
+    /// Orders two tied choices for `source`, preferred first
+    fn compare(&self, source: &FilePath, a: &FilePath, b: &FilePath) -> std::cmp::Ordering {
+        match self {
+            Tiebreaker::None => std::cmp::Ordering::Equal,
+            Tiebreaker::ClosestLength => {
+                let length = source.stem().chars().count();
+                let distance = |c: &FilePath| c.stem().chars().count().abs_diff(length);
+                distance(a).cmp(&distance(b))
+            }
+            Tiebreaker::SameExtensionFamily => {
+                let family = extension_family(&source.name);
+                let differs = |c: &FilePath| extension_family(&c.name) != family;
+                differs(a).cmp(&differs(b))
+            }
+            Tiebreaker::SameFolder => {
+                let folder = source.path.parent();
+                let differs = |c: &FilePath| c.path.parent() != folder;
+                differs(a).cmp(&differs(b))
+            }
+            Tiebreaker::Alphabetical => a.name.cmp(&b.name),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq)]
+#[serde(default)]
+struct MatchOptions {
+    algorithm: SearchAlgorithm,
+    /// Also compare sources against initialisms of multi-word choices
+    initialisms: bool,
+    tiebreaker: Tiebreaker,
+    /// Candidates scoring within this much of the best are considered tied
+    tie_margin: f32,
+    /// Keep only the few best candidates per source, for large lists on small machines
+    low_memory: bool,
+    /// Candidates whose scores are kept per source, unless low memory keeps fewer
+    kept_candidates: usize,
+    /// Jaro Winkler bonus per shared leading character
+    prefix_scale: f64,
+    /// Leading characters counted for the Jaro Winkler bonus, 0 for no limit
+    max_prefix: usize,
+    /// Text shared by every name, left out when matching
+    strip: SharedText,
+    /// Compare names without regard to upper and lower case
+    ignore_case: bool,
+    /// Treat `_`, `.`, `-` and spaces as the same separator
+    unify_separators: bool,
+    /// Leave out leading articles like "The", and ones moved to the end like "Matrix, The"
+    ignore_articles: bool,
+    /// Languages whose articles are left out, or moved to the end of output names
+    article_languages: Vec<ArticleLanguage>,
+    /// Compare Roman numerals like "VII" as digits
+    roman_numerals: bool,
+    /// Leave out language codes and flags ending names, like ".en.forced"
+    language_tags: bool,
+    years: YearCheck,
+    /// Pair files with the same CRC-32 checksum whatever their names, see [`crc`]
+    checksums: bool,
+    /// Only choices of this kind are candidates, see [`FilePath::kind`]; empty for any
+    candidate_kind: String,
+}
+
+/// Prefixes and suffixes removed from names before they are compared, see [`affix`]
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+struct SharedText {
+    source_prefix: String,
+    source_suffix: String,
+    choice_prefix: String,
+    choice_suffix: String,
+}
+
+impl SharedText {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: SearchAlgorithm::Jaro,
+            initialisms: false,
+            tiebreaker: Tiebreaker::None,
+            tie_margin: 0.005,
+            low_memory: false,
+            kept_candidates: CHOICE_PREVIEW_COUNT,
+            prefix_scale: 0.1,
+            max_prefix: 0,
+            strip: SharedText::default(),
+            ignore_case: false,
+            unify_separators: false,
+            ignore_articles: false,
+            article_languages: vec![ArticleLanguage::English],
+            roman_numerals: false,
+            language_tags: false,
+            years: YearCheck::Ignore,
+            checksums: false,
+            candidate_kind: String::new(),
+        }
+    }
+}
+
+impl MatchOptions {
+    /// How many candidates are scored and kept per source
+    fn candidate_count(&self) -> usize {
+        if self.low_memory {
+            self.kept_candidates.clamp(1, LOW_MEMORY_PREVIEW_COUNT)
+        } else {
+            self.kept_candidates.max(1)
+        }
+    }
+
+    fn is_candidate_kind(&self, file: &FilePath) -> bool {
+        self.candidate_kind.is_empty() || file.kind() == self.candidate_kind
+    }
+
+    /// `algorithm`'s score, using the prefix settings for Jaro Winkler
+    fn score(&self, algorithm: &SearchAlgorithm, a: &str, b: &str) -> f64 {
+        match algorithm {
+            SearchAlgorithm::JaroWinkler => {
+                metrics::jaro_winkler(a, b, self.prefix_scale, self.max_prefix)
+            }
+            algorithm => algorithm.compare(a, b),
+        }
+    }
+
+    /// `text` as it is compared, following the matching options. Output names are unaffected.
+    fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let text = if self.language_tags {
+            normalize::split_language_tags(text).0
+        } else {
+            text
+        };
+        let mut text = Cow::Borrowed(text);
+        // Before case is ignored, only capitals count as numerals
+        if self.roman_numerals {
+            text = Cow::Owned(normalize::roman_to_digits(&text));
+        }
+        if self.ignore_case {
+            text = Cow::Owned(text.to_lowercase());
+        }
+        if self.unify_separators {
+            text = Cow::Owned(normalize::unify_separators(&text));
+        }
+        if self.ignore_articles {
+            text =
+                Cow::Owned(normalize::without_article(&text, &self.article_languages).to_owned());
+        }
+        text
+    }
+
+    fn compare(&self, algorithm: &SearchAlgorithm, source: &str, choice: &str) -> f64 {
+        let factor = match self.years {
+            YearCheck::Ignore => 1.0,
+            check => {
+                let source_years = normalize::years(source);
+                let choice_years = normalize::years(choice);
+                let disagree = !source_years.is_empty()
+                    && !choice_years.is_empty()
+                    && !source_years.iter().any(|year| choice_years.contains(year));
+                match (disagree, check) {
+                    (false, _) => 1.0,
+                    (true, YearCheck::Require) => return 0.0,
+                    (true, _) => YEAR_PENALTY,
+                }
+            }
+        };
+        factor * self.compare_names(algorithm, source, choice)
+    }
+
+    /// Like [`Self::compare`] with `name` searched for `source`, but same checksums score 1
+    fn compare_files(
+        &self,
+        algorithm: &SearchAlgorithm,
+        name: &str,
+        source: &FilePath,
+        choice: &FilePath,
+    ) -> f64 {
+        if self.checksums {
+            if let (Some(a), Some(b)) = (source.checksum(), choice.checksum()) {
+                if a == b {
+                    return 1.0;
+                }
+            }
+        }
+        self.compare(algorithm, name, choice.stem())
+    }
+
+    fn compare_names(&self, algorithm: &SearchAlgorithm, source: &str, choice: &str) -> f64 {
+        let strip = &self.strip;
+        let source = affix::strip(source, &strip.source_prefix, &strip.source_suffix);
+        let choice = affix::strip(choice, &strip.choice_prefix, &strip.choice_suffix);
+        let score = self.score(algorithm, &self.normalize(source), &self.normalize(choice));
+        if !self.initialisms {
+            return score;
+        }
+
+        let source = source.to_lowercase();
+        metrics::initialisms(choice)
+            .iter()
+            .map(|initialism| self.score(algorithm, &source, initialism))
+            .fold(score, f64::max)
+    }
+}
+
+#[derive(Clone, Default)]
 struct SourceName {
     file: FilePath,
     choice_map: Vec<(usize, f32)>,
     manual_choice: Option<Option<usize>>,
+    /// Replaces the search algorithm for this source only
+    algorithm: Option<SearchAlgorithm>,
+    /// Number of candidates tied for the best score
+    tied_count: usize,
+    /// Output folder for this source, replacing the one picked when copying
+    destination: Option<PathBuf>,
+    /// Only match against choices in this group
+    choice_group: Option<String>,
+    /// Kept at the top of the table to come back to later
+    pinned: bool,
+    /// Text matched instead of the file name, e.g. with a junk prefix removed
+    query: Option<String>,
+    /// Keeps the current candidates and choice through later option changes and imports
+    locked: bool,
 }
 
 impl TryFrom<PathBuf> for SourceName {
@@ -98,6 +843,43 @@ impl TryFrom<PathBuf> for SourceName {
 }
 
 impl SourceName {
+    /// Hash of what can be edited of the source, to notice edits cheaply
+    fn edit_key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.file.path.hash(&mut hasher);
+        self.manual_choice.hash(&mut hasher);
+        self.algorithm.map(|a| a as u8).hash(&mut hasher);
+        self.destination.hash(&mut hasher);
+        self.choice_group.hash(&mut hasher);
+        self.pinned.hash(&mut hasher);
+        self.query.hash(&mut hasher);
+        self.locked.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Reads the file at `path`, matched and renamed as `name` when given, and scores it
+    fn read(
+        path: PathBuf,
+        name: Option<String>,
+        choice_names: &Vec<ChoiceName>,
+        choice_index: &ChoiceIndex,
+        options: &MatchOptions,
+    ) -> Result<Self, Skipped> {
+        #[cfg(not(target_arch = "wasm32"))]
+        fs::metadata(&path).map_err(|_| Skipped::Unreadable)?;
+        let mut source = SourceName::try_from(path).map_err(|_| Skipped::Unreadable)?;
+        if let Some(name) = name {
+            source.file.name = name.into();
+        }
+        source.update_choices(choice_names, choice_index, options);
+        Ok(source)
+    }
+
+    /// What choices are scored against: the edited query, or the name without extension
+    fn search_text(&self) -> &str {
+        self.query.as_deref().unwrap_or_else(|| self.file.stem())
+    }
+
     #[inline]
     fn reset_choice(&mut self) {
         self.manual_choice = None;
@@ -123,41 +905,240 @@ impl SourceName {
         }
     }
 
-    fn update_choices(&mut self, choice_names: &Vec<FilePath>, algorithm: &SearchAlgorithm) {
-        let name = remove_extension(&self.file.name);
-        let mut scores: [(usize, f32); CHOICE_PREVIEW_COUNT] = [(0, -1.0); 10];
+    /// How far the best candidate is ahead of the runner-up
+    fn score_gap(&self) -> f32 {
+        match self.choice_map.as_slice() {
+            [] => 0.0,
+            [(_, best)] => *best,
+            [(_, best), (_, second), ..] => best - second,
+        }
+    }
 
-        for (index, choice) in choice_names.iter().enumerate() {
-            let score = algorithm.compare(name, remove_extension(&choice.name)) as f32;
+    fn accepts_choice(&self, choice: &ChoiceName) -> bool {
+        !choice.excluded
+            && choice
+                .owner
+                .as_ref()
+                .map_or(true, |owner| *owner == self.file.path)
+            && self
+                .choice_group
+                .as_ref()
+                .map_or(true, |group| *group == choice.group)
+    }
 
-            let mut lowest: f32 = 2.0; // f32::INFINITY
-            let mut replace: usize = 0;
-            for i in 0usize..CHOICE_PREVIEW_COUNT {
-                let (_, i_score) = scores[i];
-                if i_score < score && i_score < lowest {
-                    lowest = i_score;
-                    replace = i;
+    fn update_choices(
+        &mut self,
+        choice_names: &Vec<ChoiceName>,
+        choice_index: &ChoiceIndex,
+        options: &MatchOptions,
+    ) {
+        if self.locked {
+            // Unless the choice it was locked to is gone
+            if self
+                .current_choice()
+                .map_or(true, |c| c < choice_names.len())
+            {
+                // A source locked without a match keeps its lock, but not candidates that
+                // were removed
+                if self
+                    .choice_map
+                    .iter()
+                    .any(|(c, _)| *c >= choice_names.len())
+                {
+                    self.choice_map.clear();
                 }
+                return;
             }
-            if lowest != 2.0 {
-                scores[replace] = (index, score);
+            self.locked = false;
+        }
+        let name = self.search_text();
+        let algorithm = self.algorithm.as_ref().unwrap_or(&options.algorithm);
+        let listed = choice_index.candidates(&options.candidate_kind, self.choice_group.as_deref());
+        let indices: Box<dyn Iterator<Item = usize>> = match &listed {
+            Some(listed) => Box::new(listed.iter().copied()),
+            None => Box::new(0..choice_names.len()),
+        };
+        let candidates = indices
+            .map(|index| (index, &choice_names[index]))
+            .filter(|(_, choice)| self.accepts_choice(choice))
+            .map(|(index, choice)| Candidate {
+                index,
+                score: options.compare_files(algorithm, name, &self.file, &choice.file) as f32,
+                file: &choice.file,
+            });
+        self.choice_map = top_k(candidates, options.candidate_count())
+            .into_iter()
+            .map(|candidate| (candidate.index, candidate.score))
+            .collect();
+
+        let best = self.choice_map.first().map_or(0.0, |(_, s)| *s);
+        self.tied_count = self
+            .choice_map
+            .iter()
+            .take_while(|(_, s)| best - *s <= options.tie_margin)
+            .count();
+        if self.tied_count > 1 {
+            let file = &self.file;
+            self.choice_map[..self.tied_count].sort_by(|(a, _), (b, _)| {
+                options
+                    .tiebreaker
+                    .compare(file, &choice_names[*a].file, &choice_names[*b].file)
+            });
+        }
+    }
+}
+
+/// Scores `sources` against the choices, split over up to `workers` threads
+fn score_sources(
+    sources: &mut [SourceName],
+    choice_names: &Vec<ChoiceName>,
+    index: &ChoiceIndex,
+    options: &MatchOptions,
+    workers: usize,
+) {
+    let workers = workers.min(sources.len()).max(1);
+    if workers > 1 {
+        let chunk_size = (sources.len() + workers - 1) / workers;
+        std::thread::scope(|scope| {
+            for chunk in sources.chunks_mut(chunk_size) {
+                scope.spawn(move || {
+                    for source in chunk {
+                        source.update_choices(choice_names, index, options);
+                    }
+                });
             }
+        });
+    } else {
+        for source in sources.iter_mut() {
+            source.update_choices(choice_names, index, options);
+        }
+    }
+}
+
+/// Choices by kind of file and by group, so sources limited to either only score those
+#[derive(Default)]
+struct ChoiceIndex {
+    by_kind: HashMap<String, Vec<usize>>,
+    by_group: HashMap<String, Vec<usize>>,
+}
+
+impl ChoiceIndex {
+    fn new(choice_names: &[ChoiceName]) -> Self {
+        let mut index = Self::default();
+        for (i, choice) in choice_names.iter().enumerate() {
+            index.by_kind.entry(choice.file.kind()).or_default().push(i);
+            index
+                .by_group
+                .entry(choice.group.clone())
+                .or_default()
+                .push(i);
+        }
+        index
+    }
+
+    /// Indices of the choices of `kind` in `group`, in order; None when neither limits them
+    fn candidates(&self, kind: &str, group: Option<&str>) -> Option<Cow<'_, [usize]>> {
+        let kind =
+            (!kind.is_empty()).then(|| self.by_kind.get(kind).map_or(&[][..], Vec::as_slice));
+        let group = group.map(|group| self.by_group.get(group).map_or(&[][..], Vec::as_slice));
+        match (kind, group) {
+            (None, None) => None,
+            (Some(list), None) | (None, Some(list)) => Some(Cow::Borrowed(list)),
+            (Some(kind), Some(group)) => Some(Cow::Owned(
+                kind.iter()
+                    .copied()
+                    .filter(|i| group.binary_search(i).is_ok())
+                    .collect(),
+            )),
         }
+    }
+}
+
+/// A choice scored for a source
+struct Candidate<'a> {
+    index: usize,
+    score: f32,
+    file: &'a FilePath,
+}
 
-        self.choice_map =
-            Vec::from(&scores[0..scores.iter().position(|(_, s)| -1.0 == *s).unwrap_or(10)]);
-        self.choice_map.sort_by(|a, b| b.1.total_cmp(&a.1));
+/// Best score first, then by name and path, so equal scores come out the same whatever order
+/// the files were listed in
+impl Ord for Candidate<'_> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other
+            .score
+            .total_cmp(&self.score)
+            .then_with(|| self.file.name.cmp(&other.file.name))
+            .then_with(|| self.file.path.cmp(&other.file.path))
+            .then_with(|| self.index.cmp(&other.index))
     }
 }
 
+impl PartialOrd for Candidate<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Candidate<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Candidate<'_> {}
+
+/// The first `k` of `items` in their order, sorted. Only `k` are kept at a time, in a heap with
+/// the last of them on top, so long lists are never sorted whole.
+fn top_k<T: Ord>(items: impl IntoIterator<Item = T>, k: usize) -> Vec<T> {
+    let mut heap = BinaryHeap::with_capacity(k);
+    for item in items {
+        if heap.len() < k {
+            heap.push(item);
+        } else if let Some(mut last) = heap.peek_mut() {
+            if item < *last {
+                *last = item;
+            }
+        }
+    }
+    heap.into_sorted_vec()
+}
+
+/// Finds the choice previously confirmed for a source with the same name
+fn recall_choice(
+    remembered: &HashMap<String, String>,
+    choice_names: &[ChoiceName],
+    source: &SourceName,
+) -> Option<usize> {
+    let choice = remembered.get(source.file.stem())?;
+    choice_names.iter().position(|c| c.file.stem() == choice)
+}
+
 #[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 struct FuzzySearch {
     #[serde(skip)]
     source_names: Vec<SourceName>,
     #[serde(skip)]
-    choice_names: Vec<FilePath>,
+    choice_names: Vec<ChoiceName>,
+    /// Sources taken out of the table, most recent last, until restored or forgotten
+    #[serde(skip)]
+    removed_sources: Vec<SourceName>,
 
-    algorithm: SearchAlgorithm,
+    options: MatchOptions,
+
+    /// Confirmed matches from previous sessions, as source name -> choice name (without extensions)
+    remembered: HashMap<String, String>,
+    remember_matches: bool,
+    /// Threads used for scoring, 0 for one per CPU core
+    workers: usize,
+    /// The algorithm as saved before it moved into `options`, only read to carry it over
+    #[serde(rename = "algorithm", skip_serializing)]
+    saved_algorithm: Option<SearchAlgorithm>,
+    /// Counts changes of the lists and scores, so what is worked out from them can be kept
+    /// until the next one
+    #[serde(skip)]
+    revision: u64,
 }
 
 impl Default for FuzzySearch {
@@ -165,680 +1146,7955 @@ impl Default for FuzzySearch {
         Self {
             source_names: vec![],
             choice_names: vec![],
-            algorithm: SearchAlgorithm::Jaro,
+            removed_sources: vec![],
+            options: MatchOptions::default(),
+            remembered: HashMap::new(),
+            remember_matches: true,
+            workers: 0,
+            saved_algorithm: None,
+            revision: 0,
         }
     }
 }
 
 impl FuzzySearch {
-    fn add_source(&mut self, path: PathBuf) {
-        if let Ok(mut source) = SourceName::try_from(path) {
-            source.update_choices(&self.choice_names, &self.algorithm);
-            self.source_names.push(source);
-        }
+    /// Canonical paths of the imported sources, see [`Self::add_source`]
+    fn source_paths(&self) -> HashSet<PathBuf> {
+        self.source_names
+            .iter()
+            .map(|s| s.file.canonical.clone())
+            .collect()
     }
 
-    fn add_choice(&mut self, path: PathBuf) {
-        if let Ok(choice) = FilePath::try_from(path) {
-            self.choice_names.push(choice);
-        }
+    fn choice_paths(&self) -> HashSet<PathBuf> {
+        self.choice_names
+            .iter()
+            .filter(|c| !c.file.canonical.as_os_str().is_empty())
+            .map(|c| c.file.canonical.clone())
+            .collect()
     }
 
-    fn update_all(&mut self) {
-        self.source_names
-            .sort_unstable_by_key(|v| v.file.name.clone());
-        for source in self.source_names.iter_mut() {
-            source.update_choices(&self.choice_names, &self.algorithm);
+    /// Adds a source unless `known` already has its path, or tells why it was left out. `index`
+    /// is of the current choices, built once for a whole batch of sources.
+    fn add_source(
+        &mut self,
+        path: PathBuf,
+        index: &ChoiceIndex,
+        known: &mut HashSet<PathBuf>,
+    ) -> Result<(), Skipped> {
+        let source = SourceName::read(path, None, &self.choice_names, index, &self.options)?;
+        self.push_source(source, known)
+    }
+
+    /// Adds a source read already, unless `known` has its path
+    fn push_source(
+        &mut self,
+        mut source: SourceName,
+        known: &mut HashSet<PathBuf>,
+    ) -> Result<(), Skipped> {
+        self.revision += 1;
+        if !known.insert(source.file.canonical.clone()) {
+            return Err(Skipped::Duplicate);
+        }
+        if self.remember_matches {
+            if let Some(choice) = recall_choice(&self.remembered, &self.choice_names, &source) {
+                source.set_choice(Some(choice));
+            }
         }
+        self.source_names.push(source);
+        Ok(())
     }
 
-    fn remove_source(&mut self, index: usize) {
-        self.source_names.remove(index);
+    /// Adds a choice unless `known` already has its path, or tells why it was left out
+    fn add_choice(&mut self, path: PathBuf, known: &mut HashSet<PathBuf>) -> Result<(), Skipped> {
+        self.push_choice(ChoiceName::read(path, None)?, known)
     }
 
-    // fn remove_choice(&mut self, index: usize) {
-    //     self.choice_names.swap_remove(index);
-    // }
-}
-
-#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq)]
-enum WindowTheme {
-    Dark,
-    Light,
-}
-
-#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq)]
-enum SideToUse {
-    Choices,
-    Sources,
-}
+    /// Adds a choice read already, unless `known` has its path
+    fn push_choice(
+        &mut self,
+        choice: ChoiceName,
+        known: &mut HashSet<PathBuf>,
+    ) -> Result<(), Skipped> {
+        self.revision += 1;
+        if !known.insert(choice.file.canonical.clone()) {
+            return Err(Skipped::Duplicate);
+        }
+        self.choice_names.push(choice);
+        Ok(())
+    }
 
-enum AppStatus {
-    None,
-    Info(String),
-    Notice(String),
-    // Progress(String, f32),
-}
+    /// Adds a folder entry read by an [`EntryReader`], unless `known` has its path
+    fn push_entry(
+        &mut self,
+        entry: ReadEntry,
+        known: &mut HashSet<PathBuf>,
+    ) -> Result<(), Skipped> {
+        match entry {
+            ReadEntry::Source(source) => self.push_source(source, known),
+            ReadEntry::Choice(choice) => self.push_choice(choice, known),
+        }
+    }
 
-#[derive(serde::Deserialize, serde::Serialize)]
-#[serde(default)]
-pub struct MainApp {
-    sources_path: String,
-    choices_path: String,
-    renames_path: String,
+    /// Adds a choice that only exists as a name in a list, not as a file
+    fn add_choice_name(&mut self, name: String) {
+        self.add_listed_choice(import::Listed::new(name, None));
+    }
 
-    keep_extension: bool,
-    side_to_copy: SideToUse,
-    copy_failed_sources: bool,
+    /// Like [`Self::add_choice_name`], with what the list gives for it
+    fn add_listed_choice(&mut self, listed: import::Listed) {
+        self.revision += 1;
+        self.choice_names.push(ChoiceName {
+            file: FilePath {
+                name: listed.name.into(),
+                crc: listed.crc,
+                ..Default::default()
+            },
+            rename_to: listed.rename_to,
+            ..Default::default()
+        });
+    }
 
-    window_theme: WindowTheme,
+    /// Puts every choice added since `first` into `group`
+    fn group_choices_from(&mut self, first: usize, group: &str) {
+        self.revision += 1;
+        for choice in self.choice_names.iter_mut().skip(first) {
+            choice.group = group.to_owned();
+        }
+    }
 
-    threshold: f32,
+    /// Kinds of all choices, sorted, see [`FilePath::kind`]
+    fn choice_kinds(&self) -> Vec<String> {
+        let mut kinds: Vec<String> = self.choice_names.iter().map(|c| c.file.kind()).collect();
+        kinds.sort();
+        kinds.dedup();
+        kinds
+    }
 
-    search: FuzzySearch,
+    /// Names of all choice groups, sorted
+    fn choice_groups(&self) -> Vec<String> {
+        let mut groups: Vec<String> = self
+            .choice_names
+            .iter()
+            .filter(|c| !c.group.is_empty())
+            .map(|c| c.group.clone())
+            .collect();
+        groups.sort();
+        groups.dedup();
+        groups
+    }
 
-    #[serde(skip)]
-    status: AppStatus,
-}
+    /// The lists and picked matches, for undo
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            source_names: self.source_names.clone(),
+            choice_names: self.choice_names.clone(),
+            removed_sources: self.removed_sources.clone(),
+            remembered: self.remembered.clone(),
+        }
+    }
 
-impl Default for MainApp {
-    fn default() -> Self {
-        Self {
-            sources_path: "".to_owned(),
-            choices_path: "".to_owned(),
-            renames_path: "".to_owned(),
-            keep_extension: false,
-            side_to_copy: SideToUse::Choices,
-            copy_failed_sources: true,
-            window_theme: WindowTheme::Light,
-            threshold: 0.7,
-            search: FuzzySearch::default(),
-            status: AppStatus::None,
+    /// How the lists changed since `tracked`, as the step that undoes it. `tracked` is brought
+    /// up to date, copying only what changed.
+    fn track_changes(&self, tracked: &mut Snapshot) -> UndoStep {
+        let remembered = (tracked.remembered != self.remembered)
+            .then(|| std::mem::replace(&mut tracked.remembered, self.remembered.clone()));
+        UndoStep {
+            source_names: ListChange::track(
+                &mut tracked.source_names,
+                &self.source_names,
+                SourceName::edit_key,
+            ),
+            choice_names: ListChange::track(
+                &mut tracked.choice_names,
+                &self.choice_names,
+                ChoiceName::edit_key,
+            ),
+            removed_sources: ListChange::track(
+                &mut tracked.removed_sources,
+                &self.removed_sources,
+                SourceName::edit_key,
+            ),
+            remembered,
         }
     }
-}
 
-impl MainApp {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let main_app: Self = if let Some(storage) = cc.storage {
-            // Loads the previous state
-            eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
-        } else {
-            // Loads with default values
-            Default::default()
+    /// Puts back what `step` holds, returning the step that reverts it
+    fn apply_step(&mut self, step: UndoStep) -> UndoStep {
+        let reverted = UndoStep {
+            source_names: step.source_names.apply(&mut self.source_names),
+            choice_names: step.choice_names.apply(&mut self.choice_names),
+            removed_sources: step.removed_sources.apply(&mut self.removed_sources),
+            remembered: step
+                .remembered
+                .map(|remembered| std::mem::replace(&mut self.remembered, remembered)),
         };
+        // Options may have changed since, and they aren't undone
+        self.update_all();
+        reverted
+    }
 
-        Self::set_window_theme(&cc.egui_ctx, &main_app.window_theme);
+    /// Hash of everything undo restores that can be edited, to notice edits cheaply
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for source in &self.source_names {
+            source.edit_key().hash(&mut hasher);
+        }
+        self.removed_sources.len().hash(&mut hasher);
+        for choice in &self.choice_names {
+            choice.edit_key().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
 
-        main_app
+    fn to_session(&self) -> Session {
+        Session {
+            sources: self
+                .source_names
+                .iter()
+                .map(|source| SessionSource {
+                    path: source.file.path.clone(),
+                    manual_choice: source.manual_choice,
+                    destination: source.destination.clone(),
+                    choice_group: source.choice_group.clone(),
+                    pinned: source.pinned,
+                    query: source.query.clone(),
+                    locked: source.locked,
+                })
+                .collect(),
+            choices: self
+                .choice_names
+                .iter()
+                .map(|choice| SessionChoice {
+                    name: choice.file.name.to_string(),
+                    path: choice.file.path.clone(),
+                    rename_to: choice.rename_to.clone(),
+                    group: choice.group.clone(),
+                    excluded: choice.excluded,
+                    owner: choice.owner.clone(),
+                    crc: choice.file.crc,
+                })
+                .collect(),
+        }
     }
 
-    fn set_window_theme(ctx: &Context, theme: &WindowTheme) {
-        ctx.set_visuals(match theme {
-            WindowTheme::Dark => Visuals::dark(),
-            WindowTheme::Light => Visuals::light(),
-        });
+    /// Replaces all sources and choices with the ones saved in `session`
+    fn load_session(&mut self, session: Session) {
+        self.revision += 1;
+        self.source_names.clear();
+        self.choice_names.clear();
+        self.removed_sources.clear();
+
+        for choice in session.choices {
+            if choice.path.as_os_str().is_empty() {
+                self.choice_names.push(ChoiceName {
+                    file: FilePath {
+                        name: choice.name.into(),
+                        crc: choice.crc,
+                        ..Default::default()
+                    },
+                    rename_to: choice.rename_to,
+                    group: choice.group,
+                    excluded: choice.excluded,
+                    owner: choice.owner,
+                });
+            } else if let Ok(mut loaded) = ChoiceName::try_from(choice.path) {
+                loaded.rename_to = choice.rename_to;
+                loaded.group = choice.group;
+                loaded.excluded = choice.excluded;
+                loaded.owner = choice.owner;
+                self.choice_names.push(loaded);
+            }
+        }
+
+        let choice_count = self.choice_names.len();
+        for saved in session.sources {
+            if let Ok(mut source) = SourceName::try_from(saved.path) {
+                source.manual_choice = saved
+                    .manual_choice
+                    .filter(|c| c.map_or(true, |c| c < choice_count));
+                source.destination = saved.destination;
+                source.choice_group = saved.choice_group;
+                source.pinned = saved.pinned;
+                source.query = saved.query;
+                source.locked = saved.locked;
+                self.source_names.push(source);
+            }
+        }
+        self.update_all();
     }
 
-    // Rename choice
-    //   A_game.zip
-    // > A.png <
-    // > A_game.zip.png <
+    /// Counts how many hand-picked matches `options` would have ranked first on its own
+    fn count_corrections_ranked_first(&self, options: &MatchOptions) -> usize {
+        self.source_names
+            .iter()
+            .filter(|source| {
+                let Some(Some(picked)) = source.manual_choice else {
+                    return false;
+                };
+                if picked >= self.choice_names.len() {
+                    return false;
+                }
+                let algorithm = source.algorithm.as_ref().unwrap_or(&options.algorithm);
+                let name = source.search_text();
+                let score =
+                    |c: &ChoiceName| options.compare_files(algorithm, name, &source.file, &c.file);
+                let picked_score = score(&self.choice_names[picked]);
+                self.choice_names
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| source.accepts_choice(c))
+                    .all(|(i, c)| i == picked || score(c) < picked_score)
+            })
+            .count()
+    }
 
-    // Rename source
-    // > B.png <
-    //   B_game.zip
-    // > B_game.zip.png <
+    /// Finds text nearly all sources (or all choices) share, to leave out when matching
+    fn detect_shared_text(&self) -> SharedText {
+        let sources: Vec<&str> = self.source_names.iter().map(|s| s.search_text()).collect();
+        let choices: Vec<&str> = self.choice_names.iter().map(|c| c.file.stem()).collect();
+        SharedText {
+            source_prefix: affix::common_prefix(&sources).unwrap_or_default(),
+            source_suffix: affix::common_suffix(&sources).unwrap_or_default(),
+            choice_prefix: affix::common_prefix(&choices).unwrap_or_default(),
+            choice_suffix: affix::common_suffix(&choices).unwrap_or_default(),
+        }
+    }
 
-    fn iter_renames(&self, include_failed: bool) -> Vec<(&PathBuf, String)> {
-        return self
-            .search
+    /// Tries each algorithm and initialism setting against the matches picked by hand, then
+    /// flips each normalization in turn, keeping the ones that rank more picks first
+    fn learn_from_corrections(&self) -> CorrectionReport {
+        let total = self
             .source_names
             .iter()
-            .filter_map(|source| {
-                let current_choice = source.current_choice();
-                let below_threshold = source.current_score().map_or(false, |s| s < self.threshold);
+            .filter(|s| matches!(s.manual_choice, Some(Some(_))))
+            .count();
+        let current = self.count_corrections_ranked_first(&self.options);
 
-                let choice = current_choice.and_then(|c| self.search.choice_names.get(c));
-                if let Some(choice) = choice.filter(|_| !below_threshold) {
-                    let rename = self.rename(&source.file.name, &choice.name);
-                    let path = match self.side_to_copy {
-                        SideToUse::Choices => &choice.path,
-                        SideToUse::Sources => &source.file.path,
-                    };
-                    Some((path, rename))
-                } else if include_failed && self.side_to_copy == SideToUse::Sources {
-                    Some((&source.file.path, source.file.name.clone()))
-                } else {
-                    None
+        let mut best = self.options.clone();
+        let mut best_count = current;
+        for algorithm in SearchAlgorithm::ALL {
+            for initialisms in [false, true] {
+                let candidate = MatchOptions {
+                    algorithm,
+                    initialisms,
+                    ..self.options.clone()
+                };
+                let count = self.count_corrections_ranked_first(&candidate);
+                if count > best_count {
+                    best = candidate;
+                    best_count = count;
                 }
-            })
-            .collect();
+            }
+        }
+        for (_, toggle) in NORMALIZATIONS {
+            let mut candidate = best.clone();
+            let enabled = toggle(&mut candidate);
+            *enabled = !*enabled;
+            let count = self.count_corrections_ranked_first(&candidate);
+            if count > best_count {
+                best = candidate;
+                best_count = count;
+            }
+        }
+
+        let mut changes = vec![];
+        if best.algorithm != self.options.algorithm {
+            changes.push(best.algorithm.label().to_owned());
+        }
+        if best.initialisms != self.options.initialisms {
+            changes.push(on_or_off("initialisms", best.initialisms));
+        }
+        let (mut before, mut after) = (self.options.clone(), best.clone());
+        for (label, toggle) in NORMALIZATIONS {
+            if *toggle(&mut before) != *toggle(&mut after) {
+                changes.push(on_or_off(label, *toggle(&mut after)));
+            }
+        }
+        CorrectionReport {
+            total,
+            current,
+            best,
+            best_count,
+            changes,
+        }
     }
 
-    fn rename(&self, source: &str, choice: &str) -> String {
-        let (original, reference) = match self.side_to_copy {
-            SideToUse::Choices => (choice, source),
-            SideToUse::Sources => (source, choice),
-        };
+    /// Moves settings saved by older versions to where they are kept now
+    fn upgrade_settings(&mut self) {
+        if let Some(algorithm) = self.saved_algorithm.take() {
+            self.options.algorithm = algorithm;
+        }
+    }
 
-        let extension = original.rsplit_once('.').map_or("", |(_, s)| s);
-        let body = if self.keep_extension {
-            reference
-        } else {
-            remove_extension(reference)
-        };
-        format!("{body}.{extension}")
+    fn worker_count(&self) -> usize {
+        if cfg!(target_arch = "wasm32") {
+            return 1;
+        }
+        match self.workers {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            workers => workers,
+        }
     }
-}
 
-impl eframe::App for MainApp {
-    /// Called by the frame work to save state before shutdown.
-    fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+    fn update_all(&mut self) {
+        self.revision += 1;
+        intern::release_unused();
+        self.sort_sources();
+        let index = ChoiceIndex::new(&self.choice_names);
+        score_sources(
+            &mut self.source_names,
+            &self.choice_names,
+            &index,
+            &self.options,
+            self.worker_count(),
+        );
+        self.recall_remembered();
     }
 
-    /// Called each time the UI needs repainting, which may be many times per second.
-    /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
-    fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        // Menu bar
+    /// By path after name, so sources with the same name keep one order
+    fn sort_sources(&mut self) {
+        self.revision += 1;
+        self.source_names
+            .sort_by(|a, b| (&a.file.name, &a.file.path).cmp(&(&b.file.name, &b.file.path)));
+    }
 
-        TopBottomPanel::top("top_panel").show(ctx, |ui| {
+    /// Picks the remembered match of sources that have no pick of their own
+    fn recall_remembered(&mut self) {
+        for source in self.source_names.iter_mut() {
+            if self.remember_matches && source.manual_choice.is_none() && !source.locked {
+                if let Some(choice) = recall_choice(&self.remembered, &self.choice_names, source) {
+                    source.set_choice(Some(choice));
+                }
+            }
+        }
+    }
 
-            // Files bar
+    /// Takes in the scores of a background rescore, see [`MainApp::rescore`]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_scores(&mut self, scored: Vec<SourceName>) {
+        self.revision += 1;
+        for (source, scored) in self.source_names.iter_mut().zip(scored) {
+            source.choice_map = scored.choice_map;
+            source.tied_count = scored.tied_count;
+            source.locked = scored.locked;
+        }
+        self.recall_remembered();
+    }
 
-            menu::bar(ui, |ui| {
+    /// Rescores one source, for edits that can't change any other row
+    fn update_source(&mut self, index: usize, choices: &ChoiceIndex) {
+        self.revision += 1;
+        let source = &mut self.source_names[index];
+        source.update_choices(&self.choice_names, choices, &self.options);
+        if self.remember_matches && source.manual_choice.is_none() && !source.locked {
+            if let Some(choice) = recall_choice(&self.remembered, &self.choice_names, source) {
+                source.set_choice(Some(choice));
+            }
+        }
+    }
 
-                // Sources
+    /// Rescores only the sources that had `choice` among their candidates, after it was
+    /// excluded or reserved for another source
+    fn withdraw_choice(&mut self, choice: usize) {
+        let choices = ChoiceIndex::new(&self.choice_names);
+        for index in 0..self.source_names.len() {
+            if self.source_names[index]
+                .choice_map
+                .iter()
+                .any(|(c, _)| *c == choice)
+            {
+                self.update_source(index, &choices);
+            }
+        }
+    }
 
-                ui.menu_button("Sources", |ui| {
-                    ui.weak("Base names to match to");
-                    ui.weak("All files will be used");
-                    ui.separator();
+    /// Rescores only the sources `choice` would now rank among, after it was allowed again or
+    /// released. The others keep their candidates, it would score below all of them.
+    fn offer_choice(&mut self, choice: usize) {
+        self.revision += 1;
+        if !self
+            .options
+            .is_candidate_kind(&self.choice_names[choice].file)
+        {
+            return;
+        }
+        let kept = self.options.candidate_count();
+        let choices = ChoiceIndex::new(&self.choice_names);
+        for index in 0..self.source_names.len() {
+            let source = &self.source_names[index];
+            let offered = &self.choice_names[choice];
+            if !source.accepts_choice(offered) {
+                continue;
+            }
+            let ranks = source.choice_map.len() < kept || {
+                let algorithm = source.algorithm.as_ref().unwrap_or(&self.options.algorithm);
+                let score = self.options.compare_files(
+                    algorithm,
+                    source.search_text(),
+                    &source.file,
+                    &offered.file,
+                ) as f32;
+                let worst = source
+                    .choice_map
+                    .iter()
+                    .map(|(_, s)| *s)
+                    .fold(f32::INFINITY, f32::min);
+                score >= worst
+            };
+            if ranks {
+                self.update_source(index, &choices);
+            }
+        }
+    }
 
-                    ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")), |ui| {
-                        if ui.button("Import folder").on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
-                            let folder = pick_folder(&self.sources_path, "Choose a folder with source files");
+    /// Rough bytes held by the loaded files, their names and kept scores
+    fn memory_used(&self) -> usize {
+        let file = |file: &FilePath| file.name.len() + file.path.as_os_str().len() * 2;
+        let sources: usize = self
+            .source_names
+            .iter()
+            .chain(&self.removed_sources)
+            .map(|s| {
+                size_of::<SourceName>()
+                    + file(&s.file)
+                    + s.choice_map.capacity() * size_of::<(usize, f32)>()
+            })
+            .sum();
+        let choices: usize = self
+            .choice_names
+            .iter()
+            .map(|c| size_of::<ChoiceName>() + file(&c.file))
+            .sum();
+        sources + choices
+    }
 
-                            if let Some(folder) = folder {
-                                self.sources_path = folder.to_str().unwrap().to_owned();
+    /// Why matching may be slow or use much memory, when the lists are that large
+    fn size_warning(&self) -> Option<String> {
+        let pairs = self
+            .source_names
+            .len()
+            .saturating_mul(self.choice_names.len());
+        if pairs < LARGE_PAIR_COUNT {
+            return None;
+        }
+        let mut warning = format!(
+            "{} sources and {} choices make {pairs} pairs to score, matching will be slow",
+            self.source_names.len(),
+            self.choice_names.len()
+        );
+        if !self.options.low_memory {
+            warning.push_str(". Low memory under Performance keeps fewer candidates per source");
+        }
+        Some(warning)
+    }
 
-                                if let Ok(read_dir) = read_dir(folder) {
-                                    let mut count = 0usize;
-                                    for item in read_dir.filter_map(|i| i.ok()) {
-                                        if item.file_type().map_or(false, |f| f.is_file()) {
-                                            self.search.add_source(item.path());
-                                            count += 1;
-                                        }
-                                    }
-                                    self.status = AppStatus::Info(format!("Added {count} source(s)"));
-                                }
-                            }
-                        }
-                    });
+    /// Reads the checksums of imported files not known yet and waits for them, for runs without
+    /// a window; the window reads them in the background, see [`MainApp::read_checksums`]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_checksums(&mut self) {
+        self.revision += 1;
+        if !self.options.checksums {
+            return;
+        }
+        for source in self.source_names.iter_mut() {
+            source.file.read_checksum();
+        }
+        for choice in self.choice_names.iter_mut() {
+            choice.file.read_checksum();
+        }
+        self.update_all();
+    }
 
-                    if ui.button("Import files").clicked() {
-                        let files = pick_files(&self.sources_path, "Choose source files");
+    /// Files whose checksum is still to be read, leaving out the `unreadable` ones
+    #[cfg(not(target_arch = "wasm32"))]
+    fn unread_checksums(&self, unreadable: &HashSet<PathBuf>) -> Vec<PathBuf> {
+        let sources = self.source_names.iter().map(|s| &s.file);
+        let files = sources.chain(self.choice_names.iter().map(|c| &c.file));
+        let paths: HashSet<&PathBuf> = files
+            .filter(|file| file.needs_checksum() && !unreadable.contains(&file.path))
+            .map(|file| &file.path)
+            .collect();
+        paths.into_iter().cloned().collect()
+    }
 
-                        if let Some(files) = files {
-                            if let Some(file) = files.first() {
-                                self.sources_path =
-                                    file.parent().unwrap().to_str().unwrap().to_owned();
-                            }
+    /// Fills in checksums read from the files, to be rescored with
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_checksums(&mut self, crcs: &HashMap<PathBuf, u32>) {
+        self.revision += 1;
+        let sources = self.source_names.iter_mut().map(|s| &mut s.file);
+        for file in sources.chain(self.choice_names.iter_mut().map(|c| &mut c.file)) {
+            if let Some(crc) = crcs.get(&file.path) {
+                file.crc = Some(*crc);
+            }
+        }
+    }
 
-                            let count = files.len();
-                            for item in files {
-                                self.search.add_source(item);
-                            }
-                            self.status = AppStatus::Info(format!("Added {count} source(s)"));
+    /// Takes out every source or choice, for an import that replaces them
+    fn take_for_replace(&mut self, side: SideToUse) -> Replaced {
+        self.revision += 1;
+        let mut replaced = Replaced {
+            side,
+            sources: vec![],
+            choices: vec![],
+            picks: HashMap::new(),
+        };
+        match side {
+            SideToUse::Sources => replaced.sources = std::mem::take(&mut self.source_names),
+            SideToUse::Choices => {
+                let sources = self.source_names.iter_mut();
+                for source in sources.chain(self.removed_sources.iter_mut()) {
+                    let choice = if source.locked {
+                        source.current_choice()
+                    } else {
+                        source.manual_choice.flatten()
+                    };
+                    replaced.picks.insert(
+                        source.file.canonical.clone(),
+                        Pick {
+                            manual_choice: source.manual_choice,
+                            locked: source.locked,
+                            choice,
+                        },
+                    );
+                    // The indices are of the choices taken out
+                    if matches!(source.manual_choice, Some(Some(_))) {
+                        source.reset_choice();
+                    }
+                    source.locked = false;
+                    source.choice_map.clear();
+                }
+                replaced.choices = std::mem::take(&mut self.choice_names);
+            }
+        }
+        replaced
+    }
+
+    /// Finishes an import that replaced a list. Once it completed, what was set on the files of
+    /// `replaced` carries over to the same files imported again. With `put_back`, for an import
+    /// that failed or was cancelled, the files it added are dropped and the old list returns.
+    fn restore_replaced(&mut self, replaced: Replaced, put_back: bool) {
+        self.revision += 1;
+        if put_back {
+            match replaced.side {
+                SideToUse::Sources => self.source_names = replaced.sources,
+                SideToUse::Choices => {
+                    self.choice_names = replaced.choices;
+                    let sources = self.source_names.iter_mut();
+                    for source in sources.chain(self.removed_sources.iter_mut()) {
+                        if let Some(pick) = replaced.picks.get(&source.file.canonical) {
+                            source.manual_choice = pick.manual_choice;
+                            source.locked = pick.locked;
                         }
                     }
+                }
+            }
+            self.update_all();
+            return;
+        }
 
-                    ui.separator();
+        let previous: HashMap<&PathBuf, &SourceName> = replaced
+            .sources
+            .iter()
+            .map(|s| (&s.file.canonical, s))
+            .collect();
+        for source in &mut self.source_names {
+            if let Some(old) = previous.get(&source.file.canonical) {
+                source.manual_choice = old.manual_choice;
+                source.choice_map = old.choice_map.clone();
+                source.algorithm = old.algorithm;
+                source.destination = old.destination.clone();
+                source.choice_group = old.choice_group.clone();
+                source.pinned = old.pinned;
+                source.query = old.query.clone();
+                source.locked = old.locked;
+            }
+        }
 
-                    ui.menu_button("Clear all sources", |ui| {
-                        ui.label("Are you sure?");
-                        if ui.button("Yes").clicked() {
-                            self.search.source_names.clear();
-                            self.status = AppStatus::Info("Cleared all sources".to_owned());
-                        }
-                    })
-                });
+        let previous: HashMap<&PathBuf, &ChoiceName> = replaced
+            .choices
+            .iter()
+            .filter(|c| !c.file.path.as_os_str().is_empty())
+            .map(|c| (&c.file.canonical, c))
+            .collect();
+        let mut indices = HashMap::new();
+        for (index, choice) in self.choice_names.iter_mut().enumerate() {
+            if let Some(old) = previous.get(&choice.file.canonical) {
+                choice.rename_to = old.rename_to.clone();
+                choice.excluded = old.excluded;
+                choice.owner = old.owner.clone();
+            }
+            indices.insert(choice.file.canonical.clone(), index);
+        }
+        let sources = self.source_names.iter_mut();
+        for source in sources.chain(self.removed_sources.iter_mut()) {
+            let Some(pick) = replaced.picks.get(&source.file.canonical) else {
+                continue;
+            };
+            let choice = pick
+                .choice
+                .and_then(|c| replaced.choices.get(c))
+                .filter(|c| !c.file.path.as_os_str().is_empty())
+                .and_then(|c| indices.get(&c.file.canonical));
+            if let Some(&index) = choice {
+                source.set_choice(Some(index));
+                source.locked = pick.locked;
+            }
+        }
+        self.update_all();
+    }
 
-                // Choices
+    /// Moves a source to the removed list, see [`FuzzySearch::restore_source`]
+    fn remove_source(&mut self, index: usize) {
+        self.revision += 1;
+        let source = self.source_names.remove(index);
+        self.removed_sources.push(source);
+    }
 
-                ui.menu_button("Choices", |ui| {
-                    ui.weak("References to match with");
-                    ui.weak("Matched files will be used");
-                    ui.separator();
+    /// Puts a removed source back in its place in the table
+    fn restore_source(&mut self, removed_index: usize) {
+        self.revision += 1;
+        let mut source = self.removed_sources.remove(removed_index);
+        source.update_choices(
+            &self.choice_names,
+            &ChoiceIndex::new(&self.choice_names),
+            &self.options,
+        );
+        let index = self.source_names.partition_point(|s| {
+            (&s.file.name, &s.file.path) < (&source.file.name, &source.file.path)
+        });
+        self.source_names.insert(index, source);
+    }
 
-                    ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")), |ui| {
-                        if ui.button("Import folder").on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
-                            let folder = pick_folder(&self.choices_path, "Choose a folder with reference files");
+    /// Score of every source against every choice it may match, as (source, choice, score)
+    /// indices, best first per source. `top_k` keeps only the best few per source, 0 keeps all.
+    fn similarity_matrix(&self, top_k: usize) -> Vec<(usize, usize, f32)> {
+        let mut matrix = vec![];
+        for (source_index, source) in self.source_names.iter().enumerate() {
+            let name = source.search_text();
+            let algorithm = source.algorithm.as_ref().unwrap_or(&self.options.algorithm);
+            let mut scores: Vec<(usize, usize, f32)> = self
+                .choice_names
+                .iter()
+                .enumerate()
+                .filter(|(_, choice)| {
+                    source.accepts_choice(choice) && self.options.is_candidate_kind(&choice.file)
+                })
+                .map(|(choice_index, choice)| {
+                    let score =
+                        self.options
+                            .compare_files(algorithm, name, &source.file, &choice.file);
+                    (source_index, choice_index, score as f32)
+                })
+                .collect();
+            scores.sort_by(|a, b| b.2.total_cmp(&a.2));
+            if top_k > 0 {
+                scores.truncate(top_k);
+            }
+            matrix.append(&mut scores);
+        }
+        matrix
+    }
 
-                            if let Some(folder) = folder {
-                                self.choices_path = folder.to_str().unwrap().to_owned();
+    /// Scores each of `sources` against the choices as it is reached, without adding it to the
+    /// table, so lists too large to keep can be matched one file at a time. Outside the app this
+    /// is reached through [`MainApp::stream_matches`].
+    fn match_iter<'a>(
+        &'a self,
+        sources: impl IntoIterator<Item = PathBuf> + 'a,
+    ) -> impl Iterator<Item = SourceName> + 'a {
+        let choices = ChoiceIndex::new(&self.choice_names);
+        sources.into_iter().filter_map(move |path| {
+            let mut source = SourceName::try_from(path).ok()?;
+            source.update_choices(&self.choice_names, &choices, &self.options);
+            Some(source)
+        })
+    }
 
-                                if let Ok(read_dir) = read_dir(folder) {
-                                    let mut count = 0usize;
-                                    for item in read_dir.filter_map(|i| i.ok()) {
-                                        if item.file_type().map_or(false, |f| f.is_file()) {
-                                            self.search.add_choice(item.path());
-                                            count += 1;
-                                        }
-                                    }
-                                    if count > 0 {
-                                        self.search.update_all();
-                                    }
-                                    self.status = AppStatus::Info(format!("Added {count} reference(s)"));
-                                }
+    /// Times each algorithm scoring a sample of the sources against every choice
+    fn benchmark(&self) -> Vec<BenchResult> {
+        let sources: Vec<&str> = self
+            .source_names
+            .iter()
+            .take(BENCHMARK_SAMPLE)
+            .map(|s| s.search_text())
+            .collect();
+        let choices: Vec<&str> = self.choice_names.iter().map(|c| c.file.stem()).collect();
+
+        SearchAlgorithm::ALL
+            .into_iter()
+            .map(|algorithm| {
+                let start = Instant::now();
+                let mut total = 0.0;
+                for source in &sources {
+                    for choice in &choices {
+                        total += self.options.compare(&algorithm, source, choice);
+                    }
+                }
+                let pairs = sources.len() * choices.len();
+                BenchResult {
+                    algorithm,
+                    pairs,
+                    elapsed: start.elapsed(),
+                    mean_score: total / pairs.max(1) as f64,
+                }
+            })
+            .collect()
+    }
+
+    // fn remove_choice(&mut self, index: usize) {
+    //     self.choice_names.swap_remove(index);
+    // }
+}
+
+/// The lists as of the last recorded edit, see [`FuzzySearch::track_changes`]
+struct Snapshot {
+    source_names: Vec<SourceName>,
+    choice_names: Vec<ChoiceName>,
+    removed_sources: Vec<SourceName>,
+    remembered: HashMap<String, String>,
+}
+
+/// What an edit changed of the lists, holding the state to go back to
+struct UndoStep {
+    source_names: ListChange<SourceName>,
+    choice_names: ListChange<ChoiceName>,
+    removed_sources: ListChange<SourceName>,
+    remembered: Option<HashMap<String, String>>,
+}
+
+impl UndoStep {
+    fn is_empty(&self) -> bool {
+        self.source_names.is_unchanged()
+            && self.choice_names.is_unchanged()
+            && self.removed_sources.is_unchanged()
+            && self.remembered.is_none()
+    }
+}
+
+/// Sources scored per algorithm by the benchmark, so it stays quick on large lists
+const BENCHMARK_SAMPLE: usize = 200;
+
+/// Timing of one algorithm, see [`FuzzySearch::benchmark`]
+struct BenchResult {
+    algorithm: SearchAlgorithm,
+    pairs: usize,
+    elapsed: Duration,
+    mean_score: f64,
+}
+
+impl BenchResult {
+    fn pairs_per_second(&self) -> f64 {
+        self.pairs as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Benchmark results as plain text, one algorithm per line
+fn format_benchmark(results: &[BenchResult]) -> String {
+    results
+        .iter()
+        .map(|result| {
+            format!(
+                "{:<24} {:>10} pairs {:>10.1} ms {:>14.0} pairs/s  mean {:.3}",
+                result.algorithm.label(),
+                result.pairs,
+                result.elapsed.as_secs_f64() * 1000.0,
+                result.pairs_per_second(),
+                result.mean_score
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Result of checking other options against the matches picked by hand
+struct CorrectionReport {
+    total: usize,
+    /// Picks the current options already rank first
+    current: usize,
+    best: MatchOptions,
+    best_count: usize,
+    /// What `best` changes from the current options, like "Roman numerals on"
+    changes: Vec<String>,
+}
+
+/// One of the on or off settings of [`MatchOptions`]
+type Toggle = fn(&mut MatchOptions) -> &mut bool;
+
+/// The normalizations [`FuzzySearch::learn_from_corrections`] tries flipping, by their labels
+/// in the Normalize names menu
+const NORMALIZATIONS: [(&str, Toggle); 5] = [
+    ("Upper and lower case", |options| &mut options.ignore_case),
+    ("Separators", |options| &mut options.unify_separators),
+    ("Leading articles", |options| &mut options.ignore_articles),
+    ("Roman numerals", |options| &mut options.roman_numerals),
+    ("Language tags", |options| &mut options.language_tags),
+];
+
+fn on_or_off(setting: &str, enabled: bool) -> String {
+    format!("{setting} {}", if enabled { "on" } else { "off" })
+}
+
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+enum WindowTheme {
+    Dark,
+    Light,
+}
+
+/// Colors of the score and status coding
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq, Default)]
+enum Palette {
+    #[default]
+    Standard,
+    /// Okabe-Ito colors, told apart with the common kinds of color blindness. Errors are blue,
+    /// since red and orange look alike to many.
+    ColorBlind,
+}
+
+impl Palette {
+    const ALL: [Palette; 2] = [Palette::Standard, Palette::ColorBlind];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Palette::Standard => "Standard",
+            Palette::ColorBlind => "Color-blind friendly",
+        }
+    }
+
+    /// Ties, ambiguous scores, collisions and other things worth a second look
+    fn warning(&self) -> Color32 {
+        match self {
+            Palette::Standard => Color32::from_rgb(200, 140, 0),
+            Palette::ColorBlind => Color32::from_rgb(230, 159, 0),
+        }
+    }
+
+    /// Settings that can't be used as they are
+    fn error(&self) -> Color32 {
+        match self {
+            Palette::Standard => Color32::RED,
+            Palette::ColorBlind => Color32::from_rgb(0, 114, 178),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq)]
+enum MatchMode {
+    Files,
+    /// Sources are `.zip` archives, choices come from a DAT name list
+    RomSet,
+    /// Choices come from a CSV of current names and the names to rename them to
+    CsvMapping,
+    /// Subtitle sources are named after videos, keeping their language tags
+    Subtitles,
+}
+
+impl MatchMode {
+    fn accepts_source(&self, path: &Path) -> bool {
+        match self {
+            MatchMode::Files | MatchMode::CsvMapping => true,
+            MatchMode::RomSet => path
+                .extension()
+                .map_or(false, |e| e.eq_ignore_ascii_case("zip")),
+            MatchMode::Subtitles => path.extension().map_or(false, |e| {
+                SUBTITLE_EXTENSIONS
+                    .iter()
+                    .any(|subtitle| e.eq_ignore_ascii_case(subtitle))
+            }),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+enum TableView {
+    Sources,
+    /// Each choice with the sources that picked it
+    Choices,
+}
+
+/// Counters always shown in the status bar
+struct StatusCounts {
+    sources: usize,
+    choices: usize,
+    /// Sources with a trusted or picked match
+    matched: usize,
+    /// Sources whose automatic match needs review
+    pending: usize,
+}
+
+/// Panels shown around the table; egui keeps their sizes
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+struct PanelLayout {
+    /// Choices no source is matched to
+    unmatched: bool,
+    /// What every matched file will be named
+    preview: bool,
+    log: bool,
+}
+
+/// What the table and panels show of every source's output, worked out again only once
+/// something it depends on changed, see [`MainApp::output_key`]. Parts that aren't shown are
+/// left empty.
+#[derive(Default)]
+struct OutputCache {
+    key: Option<u64>,
+    /// Sequence numbers for the `{n}` template token, see [`MainApp::numbering`]
+    numbers: Vec<Option<usize>>,
+    collisions: Vec<bool>,
+    /// See [`MainApp::match_usage`]
+    usage: Vec<usize>,
+    /// Each file with its new name and destination folder
+    renames: Vec<(PathBuf, OsString, Option<PathBuf>)>,
+    unmatched: Vec<usize>,
+}
+
+/// Optional columns of the source table
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+struct TableColumns {
+    path: bool,
+    /// File or folder
+    kind: bool,
+    extension: bool,
+    size: bool,
+    modified: bool,
+    /// Whether the match was picked by hand or automatically
+    origin: bool,
+    /// Whether another source is renamed to the same output
+    collision: bool,
+    /// How many sources share the match
+    usage: bool,
+    /// Widths the columns were last shown at, by title
+    widths: HashMap<String, f32>,
+}
+
+impl TableColumns {
+    /// `column`, or one as wide as the column titled `title` was last shown
+    fn sized(&self, title: &str, column: Column) -> Column {
+        self.widths
+            .get(title)
+            .map_or(column, |width| Column::initial(*width))
+    }
+
+    fn remember_width(&mut self, title: &str, width: f32) {
+        if self.widths.get(title) != Some(&width) {
+            self.widths.insert(title.to_owned(), width);
+        }
+    }
+}
+
+/// Find and replace applied to generated names, not used for matching
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+struct ReplaceRule {
+    find: String,
+    replace: String,
+    regex: bool,
+    #[serde(skip)]
+    compiled: Option<Regex>,
+}
+
+impl ReplaceRule {
+    fn compile(&mut self) {
+        self.compiled = if self.regex && !self.find.is_empty() {
+            Regex::new(&self.find).ok()
+        } else {
+            None
+        };
+    }
+
+    fn is_invalid(&self) -> bool {
+        self.regex && !self.find.is_empty() && self.compiled.is_none()
+    }
+
+    fn apply(&self, name: &str) -> String {
+        if self.find.is_empty() {
+            return name.to_owned();
+        }
+        match &self.compiled {
+            Some(regex) => regex.replace_all(name, self.replace.as_str()).into_owned(),
+            None if self.regex => name.to_owned(),
+            None => name.replace(&self.find, &self.replace),
+        }
+    }
+}
+
+/// Output name template and naming convention of a media server's library layout
+struct OutputPreset {
+    label: &'static str,
+    /// How the matched names are expected to look, shown as a hint
+    example: &'static str,
+    name_template: &'static str,
+    convention: Convention,
+}
+
+const OUTPUT_PRESETS: [OutputPreset; 7] = [
+    OutputPreset {
+        label: "Plex movies",
+        example: "Choices named with their year, like \"The Matrix 1999\"",
+        name_template: "{title} ({year})",
+        convention: Convention::Plex,
+    },
+    OutputPreset {
+        label: "Plex TV episodes",
+        example: "Choices named with their season and episode, like \"Show s02e05\"",
+        name_template: "{show} - S{season:02}E{episode:02}",
+        convention: Convention::Plex,
+    },
+    OutputPreset {
+        label: "Jellyfin movies",
+        example: "Choices named with their year, like \"The Matrix 1999\"",
+        name_template: "{title} ({year})",
+        convention: Convention::Jellyfin,
+    },
+    OutputPreset {
+        label: "Jellyfin TV episodes",
+        example: "Choices named with their season and episode, like \"Show s02e05\"",
+        name_template: "{show} S{season:02}E{episode:02}",
+        convention: Convention::Jellyfin,
+    },
+    OutputPreset {
+        label: "Kodi movies",
+        example: "Choices named with their year, like \"The Matrix 1999\"",
+        name_template: "{title} ({year})",
+        convention: Convention::Kodi,
+    },
+    OutputPreset {
+        label: "Kodi TV episodes",
+        example: "Choices named with their season and episode, like \"Show s02e05\"",
+        name_template: "{show} S{season:02}E{episode:02}",
+        convention: Convention::Kodi,
+    },
+    OutputPreset {
+        label: "Music tracks",
+        example: "Choices named after the tracks, numbered like \"01 - Title\"",
+        name_template: "{n:02} - {name}",
+        convention: Convention::Music,
+    },
+];
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq)]
+enum OutputAction {
+    CopyToFolder,
+    /// Copies each file into the folder of its match, e.g. subtitles beside videos
+    CopyBesideMatch,
+    ExportReport,
+    /// CSV of sources, matches, and new names
+    ExportMapping,
+    /// Appends the executed renames to a text file
+    WriteLog,
+}
+
+impl OutputAction {
+    const ALL: [OutputAction; 5] = [
+        OutputAction::CopyToFolder,
+        OutputAction::CopyBesideMatch,
+        OutputAction::ExportReport,
+        OutputAction::ExportMapping,
+        OutputAction::WriteLog,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            OutputAction::CopyToFolder => "Copy to folder",
+            OutputAction::CopyBesideMatch => "Copy next to matches",
+            OutputAction::ExportReport => "HTML report",
+            OutputAction::ExportMapping => "Mapping CSV",
+            OutputAction::WriteLog => "Log file",
+        }
+    }
+
+    fn default_file_name(&self) -> &'static str {
+        match self {
+            OutputAction::CopyToFolder | OutputAction::CopyBesideMatch => "",
+            OutputAction::ExportReport => "rename_report.html",
+            OutputAction::ExportMapping => "rename_mapping.csv",
+            OutputAction::WriteLog => "rename_log.txt",
+        }
+    }
+}
+
+/// One step of the "Execute all" output profile
+#[derive(serde::Deserialize, serde::Serialize)]
+struct OutputTarget {
+    action: OutputAction,
+    path: String,
+    enabled: bool,
+}
+
+/// What folder imports do with symbolic links and junctions
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq)]
+enum SymlinkHandling {
+    Skip,
+    /// Import links to files under the link's own name
+    Follow,
+    /// Import links to files under the name of the file they point to
+    TargetName,
+}
+
+/// How similarity is shown in the table
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq)]
+enum ScoreFormat {
+    /// Whole percent, e.g. " 87%"
+    Percent,
+    /// Percent with two decimals, e.g. "87.35%"
+    PercentDecimals,
+    /// Score from 0 to 1, e.g. "0.874"
+    Raw,
+    /// Position of the match among the source's candidates, e.g. "#1"
+    Rank,
+}
+
+impl ScoreFormat {
+    const ALL: [ScoreFormat; 4] = [
+        ScoreFormat::Percent,
+        ScoreFormat::PercentDecimals,
+        ScoreFormat::Raw,
+        ScoreFormat::Rank,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            ScoreFormat::Percent => "Percent",
+            ScoreFormat::PercentDecimals => "Percent with decimals",
+            ScoreFormat::Raw => "Score (0-1)",
+            ScoreFormat::Rank => "Rank",
+        }
+    }
+
+    /// `rank` counts from 0; either may be missing, e.g. for a hand-picked match
+    fn format(&self, score: Option<f32>, rank: Option<usize>) -> String {
+        match (self, score, rank) {
+            (ScoreFormat::Rank, _, Some(rank)) => format!("#{}", rank + 1),
+            (ScoreFormat::Percent, Some(score), _) => format!("{:>3.0}%", 100.0 * score),
+            (ScoreFormat::PercentDecimals, Some(score), _) => format!("{:.2}%", 100.0 * score),
+            (ScoreFormat::Raw, Some(score), _) => format!("{score:.3}"),
+            _ => "N/A".to_owned(),
+        }
+    }
+}
+
+/// What folder imports add from the folder picked
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq)]
+enum ImportEntries {
+    Files,
+    /// Subfolders, e.g. album or game folders, which are renamed whole
+    Folders,
+    Both,
+}
+
+impl ImportEntries {
+    fn includes(&self, is_dir: bool) -> bool {
+        match self {
+            ImportEntries::Files => !is_dir,
+            ImportEntries::Folders => is_dir,
+            ImportEntries::Both => true,
+        }
+    }
+}
+
+/// Storage key of the session kept between runs, see [`MainApp::keep_session`]
+const SESSION_KEY: &str = "session";
+
+/// Which rows the table shows, by how confident their match is
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq)]
+enum RowFilter {
+    All,
+    Trusted,
+    NeedsReview,
+    Unmatched,
+    Manual,
+}
+
+impl RowFilter {
+    const ALL: [RowFilter; 5] = [
+        RowFilter::All,
+        RowFilter::Trusted,
+        RowFilter::NeedsReview,
+        RowFilter::Unmatched,
+        RowFilter::Manual,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            RowFilter::All => "All rows",
+            RowFilter::Trusted => "Confident matches",
+            RowFilter::NeedsReview => "Needs review",
+            RowFilter::Unmatched => "Unmatched",
+            RowFilter::Manual => "Picked by hand",
+        }
+    }
+
+    fn shows(&self, confidence: report::Confidence) -> bool {
+        match self {
+            RowFilter::All => true,
+            RowFilter::Trusted => confidence == report::Confidence::Trusted,
+            RowFilter::NeedsReview => confidence == report::Confidence::NeedsReview,
+            RowFilter::Unmatched => confidence == report::Confidence::Unmatched,
+            RowFilter::Manual => confidence == report::Confidence::Manual,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+enum NumberOrder {
+    /// Order of the matched choices, for ordered lists like episode guides
+    Match,
+    Name,
+    Date,
+}
+
+/// How sources are split up for per-group settings
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq)]
+enum SourceGrouping {
+    None,
+    Folder,
+    Extension,
+}
+
+/// Settings for one group of sources, replacing the general ones when set
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+struct GroupRule {
+    group: String,
+    threshold: Option<f32>,
+    name_template: Option<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SideToUse {
+    Choices,
+    Sources,
+}
+
+/// What importing into a list that already has files does
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq, Default)]
+enum ImportMode {
+    #[default]
+    Append,
+    /// Start the list over, keeping what was set on files that are imported again
+    Replace,
+}
+
+/// Sources or choices taken out by an import that replaces them, see
+/// [`FuzzySearch::restore_replaced`]
+struct Replaced {
+    side: SideToUse,
+    sources: Vec<SourceName>,
+    choices: Vec<ChoiceName>,
+    /// What each source and removed source had picked among the replaced choices, by its
+    /// canonical path
+    picks: HashMap<PathBuf, Pick>,
+}
+
+/// A source's pick among choices that are being replaced
+struct Pick {
+    manual_choice: Option<Option<usize>>,
+    locked: bool,
+    /// The choice it was picked or locked to
+    choice: Option<usize>,
+}
+
+/// Ctrl+F search that jumps between matching rows, keeping the others in view
+#[derive(Default)]
+struct FindBar {
+    query: String,
+    /// Source row currently highlighted
+    row: Option<usize>,
+    /// Scroll to `row` on the next frame
+    scroll: bool,
+    focus: bool,
+}
+
+/// Why an entry of an imported folder was left out
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Skipped {
+    /// By the import, symlink or source filter settings
+    Filtered,
+    /// Imported already
+    Duplicate,
+    /// Its details couldn't be read, like a broken link or one without permission
+    Unreadable,
+}
+
+impl Skipped {
+    const ALL: [Skipped; 3] = [Skipped::Filtered, Skipped::Duplicate, Skipped::Unreadable];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Skipped::Filtered => "Left out by import settings",
+            Skipped::Duplicate => "Already imported",
+            Skipped::Unreadable => "Could not be read",
+        }
+    }
+}
+
+/// What a folder import did with each entry, shown once it ends when anything was left out
+#[derive(Default)]
+struct ImportSummary {
+    folder: PathBuf,
+    added: usize,
+    /// Added entries whose names aren't valid UTF-8, shown and matched in lossy form
+    lossy: Vec<PathBuf>,
+    skipped: Vec<(PathBuf, Skipped)>,
+    /// Entries the folder listing itself failed on, so not even their names are known
+    unlisted: usize,
+}
+
+impl ImportSummary {
+    fn new(folder: &Path) -> Self {
+        Self {
+            folder: folder.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn record(&mut self, path: PathBuf, outcome: Result<(), Skipped>) {
+        match outcome {
+            Ok(()) => {
+                self.added += 1;
+                if path
+                    .file_name()
+                    .map_or(false, |name| name.to_str().is_none())
+                {
+                    self.lossy.push(path);
+                }
+            }
+            Err(reason) => self.skipped.push((path, reason)),
+        }
+    }
+
+    fn skipped(&self, reason: Skipped) -> impl Iterator<Item = &PathBuf> {
+        self.skipped
+            .iter()
+            .filter(move |(_, r)| *r == reason)
+            .map(|(path, _)| path)
+    }
+
+    fn duplicates(&self) -> usize {
+        self.skipped(Skipped::Duplicate).count()
+    }
+
+    /// Status line for the import, which never counts unreadable paths as added
+    fn message(&self, noun: &str) -> String {
+        let added = added_message(self.added, self.duplicates(), noun);
+        match self.skipped(Skipped::Unreadable).count() {
+            0 => added,
+            unreadable => format!("{added}, {unreadable} could not be read"),
+        }
+    }
+
+    /// Whether anything happened the status line doesn't tell
+    fn has_details(&self) -> bool {
+        !self.lossy.is_empty()
+            || self.unlisted > 0
+            || self.skipped.iter().any(|(_, r)| *r != Skipped::Duplicate)
+    }
+}
+
+/// A folder entry read by an [`EntryReader`], ready to be added
+enum ReadEntry {
+    Source(SourceName),
+    Choice(ChoiceName),
+}
+
+/// What reading the entries of an imported folder needs from the app, copied so the scan thread
+/// can check, read and score them on its own
+struct EntryReader {
+    side: SideToUse,
+    mode: MatchMode,
+    import_entries: ImportEntries,
+    symlinks: SymlinkHandling,
+    /// What sources are scored against, as it was when the import started; empty for choices
+    choice_names: Vec<ChoiceName>,
+    choice_index: ChoiceIndex,
+    options: MatchOptions,
+}
+
+impl EntryReader {
+    /// Reads a file or subfolder found in an imported folder, following the import and symlink
+    /// settings, or tells why it was left out
+    fn read(&self, path: PathBuf) -> Result<ReadEntry, Skipped> {
+        // Links are judged by what they point to
+        let is_dir = fs::metadata(&path)
+            .map_err(|_| Skipped::Unreadable)?
+            .is_dir();
+        if !self.import_entries.includes(is_dir) {
+            return Err(Skipped::Filtered);
+        }
+        let is_symlink = fs::symlink_metadata(&path).map_or(false, |m| m.file_type().is_symlink());
+        // Links keep their own path, so renaming one renames the link and not its target
+        let name = if is_symlink {
+            match self.symlinks {
+                SymlinkHandling::Skip => return Err(Skipped::Filtered),
+                SymlinkHandling::Follow => None,
+                SymlinkHandling::TargetName => fs::canonicalize(&path).ok().and_then(|target| {
+                    target.file_name().map(|n| n.to_string_lossy().into_owned())
+                }),
+            }
+        } else {
+            None
+        };
+
+        match self.side {
+            SideToUse::Sources if !self.mode.accepts_source(&path) => Err(Skipped::Filtered),
+            SideToUse::Sources => SourceName::read(
+                path,
+                name,
+                &self.choice_names,
+                &self.choice_index,
+                &self.options,
+            )
+            .map(ReadEntry::Source),
+            SideToUse::Choices => ChoiceName::read(path, name).map(ReadEntry::Choice),
+        }
+    }
+}
+
+/// A folder entry and what reading it gave
+#[cfg(not(target_arch = "wasm32"))]
+type FoundEntry = (PathBuf, Result<ReadEntry, Skipped>);
+
+/// A folder being read in the background, see [`scan`]
+#[cfg(not(target_arch = "wasm32"))]
+struct PendingImport {
+    scan: scan::FolderScan<FoundEntry>,
+    /// Entries read but not added yet, see [`IMPORT_ENTRIES_PER_FRAME`]
+    queue: VecDeque<FoundEntry>,
+    /// How the scan ended, once it did
+    result: Option<io::Result<bool>>,
+    /// What the scan thread scores sources with, rescored if it changed before the end
+    options: MatchOptions,
+    side: SideToUse,
+    summary: ImportSummary,
+    known: HashSet<PathBuf>,
+    /// What a replacing import took out of the list
+    replaced: Option<Replaced>,
+    /// First choice added by this import, for grouping
+    first_choice: usize,
+}
+
+/// Files being renamed in place in the background, see [`rename::rename_all`]
+#[cfg(not(target_arch = "wasm32"))]
+struct PendingRename {
+    task: Task<(Result<rename::Outcome, rename::Failure>, TagTally)>,
+    renames: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Sources being rescored in the background after a settings change, see [`MainApp::rescore`]
+#[cfg(not(target_arch = "wasm32"))]
+struct PendingScore {
+    task: Task<Vec<SourceName>>,
+    /// The lists and options scored; the scores are thrown away if either changed since
+    fingerprint: u64,
+    options: MatchOptions,
+}
+
+/// The post-run command running in the background, with the summary of the batch it follows
+#[cfg(not(target_arch = "wasm32"))]
+struct PendingHook {
+    task: Task<io::Result<std::process::ExitStatus>>,
+    summary: String,
+}
+
+/// How the post-run command went
+#[cfg(not(target_arch = "wasm32"))]
+fn hook_result(result: io::Result<std::process::ExitStatus>) -> StepResult {
+    match result {
+        Ok(status) if status.success() => StepResult::done("Post-run command: Done"),
+        Ok(status) => StepResult {
+            summary: format!("Post-run command failed: {status}"),
+            ok: false,
+        },
+        Err(error) => StepResult {
+            summary: format!("Post-run command failed: {error}"),
+            ok: false,
+        },
+    }
+}
+
+/// Files being copied in the background, see [`copier`]
+#[cfg(not(target_arch = "wasm32"))]
+struct PendingCopy {
+    copy: copier::BackgroundCopy,
+    tally: CopyTally,
+    /// The file being copied, with how much of it is done
+    index: usize,
+    copied: u64,
+    size: u64,
+    /// Folder given to the post-run command when everything was copied
+    output: Option<PathBuf>,
+    tag_tally: TagTally,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PendingCopy {
+    fn fraction(&self) -> f32 {
+        let within = if self.size > 0 {
+            self.copied as f32 / self.size as f32
+        } else {
+            0.0
+        };
+        (self.index as f32 + within) / self.copy.copies.len().max(1) as f32
+    }
+}
+
+/// Counts of a batch of copies, for its summary
+#[derive(Default)]
+struct CopyTally {
+    copied: usize,
+    replaced: usize,
+    failed: usize,
+    first_error: Option<String>,
+}
+
+impl CopyTally {
+    /// Counts a copy of `origin`, `Ok(true)` when it replaced a file
+    fn add(&mut self, origin: &Path, destination: &Path, result: io::Result<bool>) {
+        match result {
+            Ok(true) => {
+                self.replaced += 1;
+                self.copied += 1;
+            }
+            Ok(false) => {
+                self.copied += 1;
+            }
+            Err(error) => {
+                eprintln!(
+                    "Could not copy file: {} ({:?} -> {:?})",
+                    error,
+                    origin,
+                    destination.to_str()
+                );
+                self.first_error
+                    .get_or_insert_with(|| format!("{}: {error}", origin.display()));
+                self.failed += 1;
+            }
+        }
+    }
+
+    fn summary(&self) -> String {
+        let mut results: Vec<String> = Vec::with_capacity(3);
+        if self.copied > 0 {
+            results.push(format!("{} Copied", self.copied));
+        }
+        if self.replaced > 0 {
+            results.push(format!("{} Replaced", self.replaced));
+        }
+        if self.failed > 0 {
+            results.push(format!("{} Failed", self.failed));
+        }
+        results.extend(self.first_error.clone());
+        results.join(" | ")
+    }
+}
+
+/// How an output step went, see [`MainApp::execute_outputs`]
+struct StepResult {
+    summary: String,
+    /// Nothing in the step failed, a partial copy like "2 Copied | 1 Failed" didn't
+    ok: bool,
+}
+
+impl StepResult {
+    fn done(summary: &str) -> Self {
+        Self {
+            summary: summary.to_owned(),
+            ok: true,
+        }
+    }
+}
+
+/// Renames a batch in place, then tags the renamed files that `titles` has a title for
+fn rename_and_tag(
+    renames: &[(PathBuf, PathBuf)],
+    titles: &HashMap<PathBuf, String>,
+) -> (Result<rename::Outcome, rename::Failure>, TagTally) {
+    let result = rename::rename_all(renames);
+    let mut tag_tally = TagTally::default();
+    if result.is_ok() {
+        for (from, to) in renames {
+            tag_tally.tag(titles, from, to);
+        }
+    }
+    (result, tag_tally)
+}
+
+/// Title tags written after a batch, see [`tags`]
+#[derive(Default)]
+struct TagTally {
+    tagged: usize,
+    failed: usize,
+    first_error: Option<String>,
+}
+
+impl TagTally {
+    /// Tags `written`, the new file of `origin`, if `titles` has a title for it
+    fn tag(&mut self, titles: &HashMap<PathBuf, String>, origin: &Path, written: &Path) {
+        if let Some(title) = titles.get(origin) {
+            self.record(written, tags::write_title(written, title));
+        }
+    }
+
+    /// Counts how tagging `written` went
+    fn record(&mut self, written: &Path, result: io::Result<bool>) {
+        match result {
+            Ok(true) => self.tagged += 1,
+            Ok(false) => {}
+            Err(error) => {
+                eprintln!("Could not tag file: {error} ({written:?})");
+                self.first_error
+                    .get_or_insert_with(|| format!("{}: {error}", written.display()));
+                self.failed += 1;
+            }
+        }
+    }
+
+    /// Appended to the summary of the batch, `None` when nothing was tagged
+    fn summary(&self) -> Option<String> {
+        let mut results: Vec<String> = Vec::with_capacity(3);
+        if self.tagged > 0 {
+            results.push(format!("{} Tagged", self.tagged));
+        }
+        if self.failed > 0 {
+            results.push(format!("{} Not tagged", self.failed));
+        }
+        results.extend(self.first_error.clone());
+        (!results.is_empty()).then(|| results.join(" | "))
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum AppStatus {
+    None,
+    Info(String),
+    Notice(String),
+    // Progress(String, f32),
+}
+
+/// A change to the sources and choices, applied by [`MainApp::execute`] the same way whether it
+/// comes from the window, automation or a test
+enum Command {
+    ClearSources,
+    ClearChoices,
+    RemoveSource(usize),
+    /// Stop matching the choice at this index
+    ExcludeChoice(usize),
+    /// Match the source at `source` to `choice`, and keep other sources from taking it
+    ReserveChoice {
+        source: usize,
+        choice: usize,
+    },
+    ReleaseChoice(usize),
+    /// Names from a list, with what it gives for them, added as choices under `group`
+    AddListedChoices {
+        names: Vec<import::Listed>,
+        group: String,
+    },
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct MainApp {
+    sources_path: String,
+    choices_path: String,
+    renames_path: String,
+    session_path: String,
+
+    mode: MatchMode,
+    /// Store the sources, choices and picked matches with the settings, so they survive a restart
+    /// (or a page reload on the web, where sessions can't be saved to files)
+    keep_session: bool,
+    csv_has_header: bool,
+    /// Choices per source in the exported similarity matrix, 0 for all of them
+    matrix_top_k: usize,
+    symlinks: SymlinkHandling,
+    import_entries: ImportEntries,
+
+    keep_extension: bool,
+    /// Move leading articles of output names to the end, "The Matrix" becomes "Matrix, The"
+    relocate_articles: bool,
+    /// Output name without extension; `{name}` is the matched name
+    name_template: String,
+    number_start: usize,
+    number_order: NumberOrder,
+    source_grouping: SourceGrouping,
+    group_rules: Vec<GroupRule>,
+    name_prefix: String,
+    name_suffix: String,
+    replace_rules: Vec<ReplaceRule>,
+    /// Convention output names are checked against, see [`lint`]
+    naming: NamingRules,
+    output_targets: Vec<OutputTarget>,
+    /// Write the title tag of MP3 and FLAC files that are copied or renamed
+    tag_titles: bool,
+    /// Title written by `tag_titles`; `{name}` is the matched name
+    tag_template: String,
+    /// Shell command run after a batch of copies or renames succeeds, with `{output}` and
+    /// `{report}` replaced by the output folder and report file
+    post_run_command: String,
+    /// Shell command given the planned renames as JSON on stdin before a batch, which is
+    /// cancelled if it exits with a failure
+    validation_command: String,
+    side_to_copy: SideToUse,
+    copy_failed_sources: bool,
+
+    import_mode: ImportMode,
+    /// The first-launch walkthrough was finished or skipped
+    onboarded: bool,
+    window_theme: WindowTheme,
+    palette: Palette,
+    /// Selection color instead of the theme's, as RGB
+    accent: Option<[u8; 3]>,
+    table_view: TableView,
+    panels: PanelLayout,
+    columns: TableColumns,
+    /// Least confident matches first, instead of by name
+    review_order: bool,
+    row_filter: RowFilter,
+    score_format: ScoreFormat,
+
+    threshold: f32,
+    /// Automatic matches below this (but above `threshold`) need review
+    trust_threshold: f32,
+    /// Leave out matches that need review until they are confirmed
+    exclude_unreviewed: bool,
+    /// Automatic matches less than this far ahead of the runner-up are flagged
+    ambiguity_margin: f32,
+
+    search: FuzzySearch,
+
+    #[serde(skip)]
+    status: AppStatus,
+    /// Earlier statuses, oldest first
+    #[serde(skip)]
+    log: Vec<AppStatus>,
+    /// The status last added to the log
+    #[serde(skip)]
+    logged_status: AppStatus,
+    #[serde(skip)]
+    output_cache: OutputCache,
+    #[serde(skip)]
+    correction_report: Option<CorrectionReport>,
+    #[serde(skip)]
+    find: Option<FindBar>,
+    /// Diagnostics window, opened with Ctrl+Shift+B
+    #[serde(skip)]
+    show_benchmark: bool,
+    #[serde(skip)]
+    benchmark: Vec<BenchResult>,
+    #[serde(skip)]
+    history: History<UndoStep>,
+    /// Edit count of the last recorded edit, and the lists at that point
+    #[serde(skip)]
+    last_edit: Option<(u64, Snapshot)>,
+    /// Imports and manual choices, and whether they were made since the last export
+    #[serde(skip)]
+    edits: Edits,
+    #[serde(skip)]
+    confirm_close: bool,
+    /// Walkthrough step shown while not `onboarded`
+    #[serde(skip)]
+    onboarding_step: usize,
+    /// File of a job that was loaded and is waiting for confirmation to run its outputs, with
+    /// its summary and the changes since its last run
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    confirm_job: Option<(PathBuf, String, Vec<String>)>,
+    /// Why copying or renaming may overwrite imported files, with what to run if the user goes
+    /// ahead anyway
+    #[serde(skip)]
+    confirm_overlap: Option<(Vec<String>, ConfirmedAction)>,
+    #[serde(skip)]
+    confirmation: Option<Confirmation>,
+    #[serde(skip)]
+    allow_close: bool,
+    /// The open file dialog, with what to do once something is chosen
+    #[serde(skip)]
+    dialog: Option<(dialog::PendingDialog, DialogHandler)>,
+    /// Contents of the file chosen in the last dialog, see [`MainApp::read_chosen`]
+    #[cfg(target_arch = "wasm32")]
+    #[serde(skip)]
+    chosen_contents: HashMap<PathBuf, Vec<u8>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pending_import: Option<PendingImport>,
+    /// The last folder import that left something out, until its window is closed
+    #[serde(skip)]
+    import_summary: Option<ImportSummary>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pending_copy: Option<PendingCopy>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pending_rename: Option<PendingRename>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pending_hook: Option<PendingHook>,
+    /// Checksums being read from the files, see [`MainApp::read_checksums`]
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pending_checksums: Option<Task<Vec<(PathBuf, Option<u32>)>>>,
+    /// A settings change asked for every source to be rescored, see [`MainApp::rescore`]
+    #[serde(skip)]
+    rescore_requested: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pending_score: Option<PendingScore>,
+    /// Capture metadata being read from images, see [`MainApp::read_exif`]
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pending_exif: Option<Task<Vec<(PathBuf, exif::Exif)>>>,
+    /// Files whose checksum couldn't be read, not tried again
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    unreadable_checksums: HashSet<PathBuf>,
+    /// Remembered dialog folders found missing, see [`MainApp::validate_folders`]
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    folder_check: Option<Task<Vec<(&'static str, String)>>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    automation: Option<std::sync::mpsc::Receiver<Result<automation::Request, String>>>,
+    /// Windows opened from this one, see [`MainApp::open_window`]
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    windows_opened: usize,
+}
+
+impl Default for MainApp {
+    fn default() -> Self {
+        Self {
+            sources_path: "".to_owned(),
+            choices_path: "".to_owned(),
+            renames_path: "".to_owned(),
+            session_path: "".to_owned(),
+            mode: MatchMode::Files,
+            keep_session: cfg!(target_arch = "wasm32"),
+            csv_has_header: true,
+            matrix_top_k: 0,
+            symlinks: SymlinkHandling::Skip,
+            import_entries: ImportEntries::Files,
+            keep_extension: false,
+            relocate_articles: false,
+            name_template: "{name}".to_owned(),
+            number_start: 1,
+            number_order: NumberOrder::Match,
+            source_grouping: SourceGrouping::None,
+            group_rules: vec![],
+            name_prefix: "".to_owned(),
+            name_suffix: "".to_owned(),
+            replace_rules: vec![],
+            naming: NamingRules::default(),
+            output_targets: vec![],
+            tag_titles: false,
+            tag_template: "{name}".to_owned(),
+            post_run_command: "".to_owned(),
+            validation_command: "".to_owned(),
+            side_to_copy: SideToUse::Choices,
+            copy_failed_sources: true,
+            import_mode: ImportMode::Append,
+            onboarded: false,
+            window_theme: WindowTheme::Light,
+            palette: Palette::Standard,
+            accent: None,
+            table_view: TableView::Sources,
+            panels: Default::default(),
+            columns: Default::default(),
+            review_order: false,
+            row_filter: RowFilter::All,
+            score_format: ScoreFormat::Percent,
+            threshold: 0.7,
+            trust_threshold: 0.9,
+            exclude_unreviewed: false,
+            ambiguity_margin: 0.02,
+            search: FuzzySearch::default(),
+            status: AppStatus::None,
+            log: vec![],
+            logged_status: AppStatus::None,
+            output_cache: OutputCache::default(),
+            correction_report: None,
+            find: None,
+            show_benchmark: false,
+            benchmark: vec![],
+            history: Default::default(),
+            last_edit: None,
+            edits: Edits::default(),
+            confirm_close: false,
+            onboarding_step: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            confirm_job: None,
+            confirm_overlap: None,
+            confirmation: None,
+            allow_close: false,
+            dialog: None,
+            #[cfg(target_arch = "wasm32")]
+            chosen_contents: HashMap::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_import: None,
+            import_summary: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_copy: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_rename: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_hook: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_checksums: None,
+            rescore_requested: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_score: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_exif: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            unreadable_checksums: HashSet::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            folder_check: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            automation: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            windows_opened: 0,
+        }
+    }
+}
+
+impl MainApp {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut main_app: Self = if let Some(storage) = cc.storage {
+            // Loads the previous state
+            eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
+        } else {
+            // Loads with default values
+            Default::default()
+        };
+
+        main_app.search.upgrade_settings();
+        Self::set_window_theme(&cc.egui_ctx, &main_app.window_theme, main_app.accent);
+        for rule in main_app.replace_rules.iter_mut() {
+            rule.compile();
+        }
+        main_app.naming.compile();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        main_app.validate_folders(&cc.egui_ctx);
+
+        if main_app.keep_session {
+            let saved = cc
+                .storage
+                .and_then(|storage| storage.get_string(SESSION_KEY));
+            if let Some(Ok(session)) = saved.map(|json| Session::from_json(&json)) {
+                main_app.search.load_session(session);
+            }
+        }
+
+        main_app
+    }
+
+    /// The folder holding every path in `paths`, if they all share one
+    #[cfg(not(target_arch = "wasm32"))]
+    fn shared_folder<'a>(mut paths: impl Iterator<Item = &'a PathBuf>) -> Option<PathBuf> {
+        let folder = paths::parent_folder(paths.next()?)?;
+        paths
+            .all(|path| paths::parent_folder(path) == Some(folder))
+            .then(|| folder.to_owned())
+    }
+
+    /// Saves the current settings with the folders the sources and choices came from
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_job_dialog(&mut self, ctx: &Context) {
+        let request = save_file(&self.session_path, "Save job template", "job.json");
+        self.ask_path(ctx, request, |app, file, _| app.save_job(&file));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_job(&mut self, file: &Path) {
+        let sources = Self::shared_folder(self.search.source_names.iter().map(|s| &s.file.path))
+            .unwrap_or_else(|| PathBuf::from(&self.sources_path));
+        let choices = Self::shared_folder(self.search.choice_names.iter().map(|c| &c.file.path))
+            .unwrap_or_else(|| PathBuf::from(&self.choices_path));
+        self.session_path = paths::dialog_folder(file);
+
+        // Only the settings are stored, the folders are scanned again when the job runs
+        let job = Job {
+            sources,
+            choices,
+            settings: &*self,
+        };
+        self.status = match job.save(file) {
+            Ok(()) => AppStatus::Info("Saved job template".to_owned()),
+            Err(error) => AppStatus::Notice(format!("Could not save job template: {error}")),
+        };
+    }
+
+    /// Applies a job's settings and scans its folders again, returning what was found. Nothing
+    /// changes if a folder can't be read.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_job(&mut self, job: Job<MainApp>) -> io::Result<job::Summary> {
+        let Job {
+            sources,
+            choices,
+            settings: mut loaded,
+        } = job;
+
+        for rule in loaded.replace_rules.iter_mut() {
+            rule.compile();
+        }
+        loaded.naming.compile();
+        loaded.search.source_names.clear();
+        loaded.search.choice_names.clear();
+        loaded.search.removed_sources.clear();
+        let choice_count = loaded.import_choice_folder(&choices)?.added;
+        let source_count = loaded.import_source_folder(&sources)?.added;
+        loaded.search.update_all();
+        loaded.sources_path = sources.to_string_lossy().into_owned();
+        loaded.choices_path = choices.to_string_lossy().into_owned();
+
+        let previous = std::mem::replace(self, loaded);
+        // State that isn't part of the settings carries over
+        self.window_theme = previous.window_theme;
+        self.history = previous.history;
+        self.last_edit = previous.last_edit;
+        // The job's lists are one undo step, with nothing to save
+        self.edits = previous.edits;
+        self.edits.mark();
+        self.edits.save();
+        self.automation = previous.automation;
+        self.dialog = previous.dialog;
+        self.pending_copy = previous.pending_copy;
+        self.pending_rename = previous.pending_rename;
+        self.pending_hook = previous.pending_hook;
+        self.folder_check = previous.folder_check;
+        if let Some(pending) = previous.pending_import {
+            pending.scan.cancel();
+        }
+
+        let matched = self
+            .search
+            .source_names
+            .iter()
+            .filter(|source| self.accepted_choice(source).is_some())
+            .count();
+        Ok(job::Summary {
+            ok: true,
+            sources: source_count,
+            choices: choice_count,
+            matched,
+            ..Default::default()
+        })
+    }
+
+    /// Each source with the name of its accepted choice, as kept between runs of a job
+    #[cfg(not(target_arch = "wasm32"))]
+    fn job_mapping(&self) -> Vec<job::Mapped> {
+        self.search
+            .source_names
+            .iter()
+            .map(|source| job::Mapped {
+                source: source.file.name.to_string(),
+                choice: self
+                    .accepted_choice(source)
+                    .map(|c| self.search.choice_names[c].file.name.to_string()),
+            })
+            .collect()
+    }
+
+    /// Compares the mapping with the last run of the job in `file`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn compare_with_last_run(&self, file: &Path) -> Option<job::Changes> {
+        let previous = job::load_last_run(file).unwrap_or_else(|error| {
+            eprintln!("Could not read the last run of the job: {error}");
+            None
+        });
+        previous.map(|previous| job::Changes::between(previous, &self.job_mapping()))
+    }
+
+    /// Keeps the mapping as the last run of the job in `file`, once its outputs succeeded
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_last_run(&self, file: &Path) {
+        if let Err(error) = job::save_last_run(file, &self.job_mapping()) {
+            eprintln!("Could not save the run of the job: {error}");
+        }
+    }
+
+    /// Opens the job in `file`, first asking to discard unsaved changes to the lists
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_job(&mut self, file: &Path) {
+        if !self.edits.unsaved() {
+            self.prepare_job(file);
+            return;
+        }
+        let file = file.to_owned();
+        self.confirmation = Some(Confirmation {
+            title: "Run job",
+            summary: format!(
+                "The job replaces the lists with the folders in {}, unsaved changes will be lost.",
+                file.display()
+            ),
+            files: vec![],
+            accept: "Discard changes",
+            action: Box::new(move |app, _| app.prepare_job(&file)),
+        });
+    }
+
+    /// Loads the job in `file` and asks for confirmation before running its outputs
+    #[cfg(not(target_arch = "wasm32"))]
+    fn prepare_job(&mut self, file: &Path) {
+        let summary = match Job::load(file).and_then(|job| self.load_job(job)) {
+            Ok(summary) => summary,
+            Err(error) => {
+                self.status = AppStatus::Notice(format!("Could not run job: {error}"));
+                return;
+            }
+        };
+        let steps: Vec<&str> = self
+            .output_targets
+            .iter()
+            .filter(|t| t.enabled)
+            .map(|t| t.action.label())
+            .collect();
+        let changes = self.compare_with_last_run(file);
+        let text = format!(
+            "Matched {} of {} source(s) against {} choice(s).\nSince the last run: {}\nOutput steps: {}",
+            summary.matched,
+            summary.sources,
+            summary.choices,
+            changes
+                .as_ref()
+                .map_or("this is the first run".to_owned(), |c| c.describe()),
+            if steps.is_empty() {
+                "none, add some to the output profile".to_owned()
+            } else {
+                steps.join(", ")
+            }
+        );
+        let lines = changes.map(|c| c.lines()).unwrap_or_default();
+        self.confirm_job = Some((file.to_owned(), text, lines));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run_job_dialog(&mut self, ctx: &Context) {
+        let request = pick_file(&self.session_path, "Run job", "Job template", &["json"]);
+        self.ask_path(ctx, request, |app, file, _| {
+            app.session_path = paths::dialog_folder(&file);
+            app.open_job(&file);
+        });
+    }
+
+    /// Runs the job in `file` and all of its output steps without a window, for
+    /// `--job <file> --yes`. Returns a JSON summary and whether everything succeeded.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn run_job_unattended(file: &Path) -> (String, bool) {
+        let mut app = MainApp::default();
+        let summary = match Job::load(file).and_then(|job| app.load_job(job)) {
+            Ok(mut summary) => {
+                app.search.read_checksums();
+                app.read_exif_now();
+                summary.changes = app.compare_with_last_run(file);
+                match app.execute_outputs(false) {
+                    Ok(results) if results.is_empty() => {
+                        summary.ok = false;
+                        summary.error = Some("The job has no output steps enabled".to_owned());
+                    }
+                    Ok(mut results) => {
+                        if results.iter().all(|result| result.ok) {
+                            app.save_last_run(file);
+                            results.extend(app.run_post_command(&app.post_command_output()));
+                        }
+                        summary.ok = results.iter().all(|result| result.ok);
+                        summary.outputs = results.into_iter().map(|r| r.summary).collect();
+                    }
+                    Err(reason) => {
+                        summary.ok = false;
+                        summary.error = Some(reason);
+                    }
+                }
+                summary
+            }
+            Err(error) => job::Summary {
+                error: Some(error.to_string()),
+                ..Default::default()
+            },
+        };
+        let json = serde_json::to_string(&summary).unwrap_or_default();
+        (json, summary.ok)
+    }
+
+    /// Starts another instance of the app with its own state, see `--new-window` in `main.rs`.
+    /// The main window numbers the windows it opens from 2, so each keeps its own settings.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_window(&mut self) -> io::Result<()> {
+        let number = self.windows_opened + 2;
+        std::process::Command::new(std::env::current_exe()?)
+            .args(["--new-window", &number.to_string()])
+            .spawn()?;
+        self.windows_opened += 1;
+        Ok(())
+    }
+
+    /// Whether this is a window opened from the main one, which numbers them
+    #[cfg(not(target_arch = "wasm32"))]
+    fn is_extra_window() -> bool {
+        std::env::args().any(|arg| arg == "--new-window")
+    }
+
+    /// The folders dialogs start in, remembered between runs
+    #[cfg(not(target_arch = "wasm32"))]
+    fn remembered_folders(&mut self) -> [(&'static str, &mut String); 4] {
+        [
+            ("sources", &mut self.sources_path),
+            ("choices", &mut self.choices_path),
+            ("output", &mut self.renames_path),
+            ("sessions", &mut self.session_path),
+        ]
+    }
+
+    /// Checks in the background whether the remembered dialog folders still exist, see
+    /// [`Self::poll_folder_check`]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn validate_folders(&mut self, ctx: &Context) {
+        let folders: Vec<(&'static str, String)> = self
+            .remembered_folders()
+            .into_iter()
+            .filter(|(_, folder)| !folder.is_empty())
+            .map(|(label, folder)| (label, folder.clone()))
+            .collect();
+        self.folder_check = Some(Task::spawn(ctx.clone(), move |reporter| {
+            let mut exists = paths::ExistsCheck::default();
+            let missing = folders
+                .into_iter()
+                .filter(|(_, folder)| !exists.try_exists(Path::new(folder)).unwrap_or(false))
+                .collect();
+            reporter.send(missing);
+        }));
+    }
+
+    /// Rescores every source on the next frame, in the background where there are threads, for
+    /// settings changed in the window
+    fn rescore(&mut self) {
+        self.rescore_requested = true;
+    }
+
+    /// Starts a rescore asked for by [`Self::rescore`], cancelling one still running as its scores
+    /// would be out of date, and takes in the scores once done
+    fn poll_rescore(&mut self, ctx: &Context) {
+        #[cfg(target_arch = "wasm32")]
+        if std::mem::take(&mut self.rescore_requested) {
+            let _ = ctx;
+            self.search.update_all();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if std::mem::take(&mut self.rescore_requested) {
+                if let Some(pending) = self.pending_score.take() {
+                    pending.task.cancel();
+                }
+                intern::release_unused();
+                self.search.sort_sources();
+                let mut sources = self.search.source_names.clone();
+                let choice_names = self.search.choice_names.clone();
+                let options = self.search.options.clone();
+                let workers = self.search.worker_count();
+                let task = Task::spawn(ctx.clone(), move |reporter| {
+                    let index = ChoiceIndex::new(&choice_names);
+                    for round in sources.chunks_mut(SCORE_ROUND) {
+                        if reporter.is_cancelled() {
+                            return;
+                        }
+                        score_sources(round, &choice_names, &index, &options, workers);
+                    }
+                    reporter.send(sources);
+                });
+                self.pending_score = Some(PendingScore {
+                    task,
+                    fingerprint: self.search.fingerprint(),
+                    options: self.search.options.clone(),
+                });
+            }
+
+            let Some(pending) = self.pending_score.take() else {
+                return;
+            };
+            let running = pending.task.is_running();
+            match pending.task.poll().into_iter().next() {
+                Some(scored) => {
+                    if self.search.fingerprint() == pending.fingerprint
+                        && self.search.options == pending.options
+                    {
+                        self.search.set_scores(scored);
+                    } else {
+                        // The lists or settings changed while scoring
+                        self.rescore_requested = true;
+                        ctx.request_repaint();
+                    }
+                }
+                None if !running => {
+                    self.status = AppStatus::Notice(
+                        "Scoring cancelled, matches are from before the last settings change"
+                            .to_owned(),
+                    );
+                }
+                None => self.pending_score = Some(pending),
+            }
+        }
+    }
+
+    /// Reads the checksums of imported files not known yet in the background, when matching by
+    /// checksum, and rescores once they are in
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_checksums(&mut self, ctx: &Context) {
+        if let Some(task) = &self.pending_checksums {
+            if !self.search.options.checksums {
+                task.cancel();
+                self.pending_checksums = None;
+                return;
+            }
+            let running = task.is_running();
+            let Some(read) = task.poll().into_iter().next() else {
+                if !running {
+                    self.pending_checksums = None;
+                }
+                return;
+            };
+            self.pending_checksums = None;
+            let mut crcs = HashMap::new();
+            for (path, crc) in read {
+                match crc {
+                    Some(crc) => {
+                        crcs.insert(path, crc);
+                    }
+                    None => {
+                        self.unreadable_checksums.insert(path);
+                    }
+                }
+            }
+            self.search.set_checksums(&crcs);
+            self.rescore();
+            return;
+        }
+        if !self.search.options.checksums {
+            return;
+        }
+        let unread = self.search.unread_checksums(&self.unreadable_checksums);
+        if unread.is_empty() {
+            return;
+        }
+        self.pending_checksums = Some(Task::spawn(ctx.clone(), move |reporter| {
+            let mut read = Vec::with_capacity(unread.len());
+            for path in unread {
+                if reporter.is_cancelled() {
+                    return;
+                }
+                let crc = crc::of_file(&path).ok();
+                read.push((path, crc));
+            }
+            reporter.send(read);
+        }));
+    }
+
+    /// Replaces remembered dialog folders found missing with the home folder, unless they were
+    /// changed since, noting which ones vanished in the status
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_folder_check(&mut self) {
+        let Some(task) = &self.folder_check else {
+            return;
+        };
+        let running = task.is_running();
+        let Some(missing) = task.poll().into_iter().next() else {
+            if !running {
+                self.folder_check = None;
+            }
+            return;
+        };
+        self.folder_check = None;
+
+        let home = paths::home_folder().map_or("".to_owned(), |h| h.to_string_lossy().into_owned());
+        let mut vanished = vec![];
+        for (label, folder) in self.remembered_folders() {
+            if missing.contains(&(label, folder.clone())) {
+                vanished.push(format!("{label} ({folder})"));
+                *folder = home.clone();
+            }
+        }
+        if !vanished.is_empty() {
+            self.status = AppStatus::Notice(format!(
+                "Previous folder for {} no longer exists, using the home folder instead",
+                vanished.join(", ")
+            ));
+        }
+    }
+
+    /// Applies `command` and rescores whatever it affects, without touching the UI. Returns what
+    /// to show in the status bar.
+    fn execute(&mut self, command: Command) -> AppStatus {
+        match command {
+            Command::ClearSources => {
+                self.search.source_names.clear();
+                self.edits.mark();
+                AppStatus::Info("Cleared all sources".to_owned())
+            }
+            Command::ClearChoices => {
+                self.search.choice_names.clear();
+                self.search.update_all();
+                self.edits.mark();
+                AppStatus::Info("Cleared all references".to_owned())
+            }
+            Command::RemoveSource(index) => {
+                self.search.remove_source(index);
+                if let Some(find) = &mut self.find {
+                    find.row = None;
+                }
+                self.edits.mark();
+                AppStatus::Info("Removed 1 source".to_owned())
+            }
+            Command::ExcludeChoice(choice) => {
+                self.search.choice_names[choice].excluded = true;
+                self.search.withdraw_choice(choice);
+                self.edits.mark();
+                AppStatus::Info(format!(
+                    "No longer matching {}",
+                    self.search.choice_names[choice].file.name
+                ))
+            }
+            Command::ReserveChoice { source, choice } => {
+                self.reserve_choice(source, choice);
+                self.edits.mark();
+                AppStatus::None
+            }
+            Command::ReleaseChoice(choice) => {
+                self.search.choice_names[choice].owner = None;
+                self.search.offer_choice(choice);
+                self.edits.mark();
+                AppStatus::None
+            }
+            Command::AddListedChoices { names, group } => {
+                let count = names.len();
+                let first = self.search.choice_names.len();
+                for listed in names {
+                    self.search.add_listed_choice(listed);
+                }
+                self.search.group_choices_from(first, &group);
+                if count == 0 {
+                    return AppStatus::Notice("No names found in the list".to_owned());
+                }
+                self.search.update_all();
+                self.edits.mark();
+                AppStatus::Info(format!("Added {count} listed name(s)"))
+            }
+        }
+    }
+
+    /// Runs `command` from the window, showing its summary
+    fn run(&mut self, command: Command) {
+        match self.execute(command) {
+            AppStatus::None => {}
+            status => self.status = status,
+        }
+    }
+
+    /// Matches `choice` to the source at `index` and takes it out of every other source's
+    /// candidates, so they fall back to their next best match. Other sources it was picked for
+    /// by hand go back to their best match too.
+    fn reserve_choice(&mut self, index: usize, choice: usize) {
+        for (other, source) in self.search.source_names.iter_mut().enumerate() {
+            if other != index && source.manual_choice == Some(Some(choice)) {
+                source.reset_choice();
+            }
+        }
+        let source = &mut self.search.source_names[index];
+        source.set_choice(Some(choice));
+        let owner = source.file.path.clone();
+        self.search.choice_names[choice].owner = Some(owner);
+        self.search.withdraw_choice(choice);
+    }
+
+    /// Records an undo step if the lists or picked matches were edited since the last call
+    fn track_edits(&mut self) {
+        // A folder import becomes one step once it has finished
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.pending_import.is_some() {
+            return;
+        }
+        let count = self.edits.count();
+        let Some((recorded, tracked)) = &mut self.last_edit else {
+            self.last_edit = Some((count, self.search.snapshot()));
+            return;
+        };
+        if *recorded == count {
+            return;
+        }
+        *recorded = count;
+        let step = self.search.track_changes(tracked);
+        if !step.is_empty() {
+            self.history.record(step);
+        }
+    }
+
+    fn undo(&mut self) {
+        // Edits of this frame get their own step first
+        self.track_edits();
+        let search = &mut self.search;
+        if self.history.undo(|step| search.apply_step(step)) {
+            self.edits.mark();
+            self.last_edit = Some((self.edits.count(), self.search.snapshot()));
+        }
+    }
+
+    fn redo(&mut self) {
+        // An edit since the undo drops what could be redone
+        self.track_edits();
+        let search = &mut self.search;
+        if self.history.redo(|step| search.apply_step(step)) {
+            self.edits.mark();
+            self.last_edit = Some((self.edits.count(), self.search.snapshot()));
+        }
+    }
+
+    fn accepts_source(&self, path: &Path) -> bool {
+        self.mode.accepts_source(path)
+    }
+
+    /// Opens a file dialog without waiting for it; `handler` runs on the frame after something is
+    /// chosen, and not at all if it is cancelled
+    fn ask_paths(
+        &mut self,
+        ctx: &Context,
+        request: dialog::Request,
+        handler: impl FnOnce(&mut MainApp, Vec<PathBuf>, &Context) + 'static,
+    ) {
+        if let Some((pending, _)) = &self.dialog {
+            self.status =
+                AppStatus::Notice(format!("Close the \"{}\" dialog first", pending.title));
+            return;
+        }
+        let pending = dialog::PendingDialog::open(request, ctx.clone());
+        self.dialog = Some((pending, Box::new(handler)));
+    }
+
+    /// Like [`Self::ask_paths`], for dialogs that choose a single folder or file
+    fn ask_path(
+        &mut self,
+        ctx: &Context,
+        request: dialog::Request,
+        handler: impl FnOnce(&mut MainApp, PathBuf, &Context) + 'static,
+    ) {
+        self.ask_paths(ctx, request, |app, paths, ctx| {
+            if let Some(path) = paths.into_iter().next() {
+                handler(app, path, ctx);
+            }
+        });
+    }
+
+    /// Hands the choice of a file dialog that closed since the last frame to its handler
+    fn poll_dialog(&mut self, ctx: &Context) {
+        let Some((pending, handler)) = self.dialog.take() else {
+            return;
+        };
+        match pending.poll() {
+            Some(chosen) if chosen.paths.is_empty() => {}
+            Some(chosen) => {
+                #[cfg(target_arch = "wasm32")]
+                {
+                    self.chosen_contents = chosen.contents;
+                }
+                handler(self, chosen.paths, ctx);
+            }
+            None => self.dialog = Some((pending, handler)),
+        }
+    }
+
+    /// The text of `file`, chosen in the last dialog. Browsers hand out the contents of a
+    /// chosen file rather than its path, the dialog read it there.
+    fn read_chosen(&mut self, file: &Path) -> io::Result<String> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let contents = self.chosen_contents.remove(file).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "the file was not chosen")
+            })?;
+            String::from_utf8(contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        fs::read_to_string(file)
+    }
+
+    /// Adds the names in the list chosen as `file` as choices, grouped under its name
+    fn import_list(&mut self, file: &Path, kind: import::ListKind) {
+        self.choices_path = paths::dialog_folder(file);
+        match self.read_chosen(file) {
+            Ok(text) => {
+                let extension = file
+                    .extension()
+                    .map(|e| e.to_string_lossy())
+                    .unwrap_or_default();
+                let names = import::parse_list(&text, kind, &extension);
+                self.run(Command::AddListedChoices {
+                    names,
+                    group: batch_name(file),
+                });
+            }
+            Err(error) => {
+                self.status = AppStatus::Notice(format!("Could not read list: {error}"));
+            }
+        }
+    }
+
+    /// Takes out the files of `side` when imports replace them and there are any
+    fn replace_for_import(&mut self, side: SideToUse) -> Option<Replaced> {
+        let empty = match side {
+            SideToUse::Sources => self.search.source_names.is_empty(),
+            SideToUse::Choices => self.search.choice_names.is_empty(),
+        };
+        (self.import_mode == ImportMode::Replace && !empty)
+            .then(|| self.search.take_for_replace(side))
+    }
+
+    /// Reads `folder` in the background, adding its files as they are found
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_folder_import(&mut self, folder: PathBuf, side: SideToUse, ctx: &Context) {
+        if let Some(pending) = self.pending_import.take() {
+            pending.scan.cancel();
+            if let Some(replaced) = pending.replaced {
+                self.search.restore_replaced(replaced, true);
+            }
+        }
+        let replaced = self.replace_for_import(side);
+        let known = match side {
+            SideToUse::Sources => self.search.source_paths(),
+            SideToUse::Choices => self.search.choice_paths(),
+        };
+        let reader = self.entry_reader(side);
+        self.pending_import = Some(PendingImport {
+            summary: ImportSummary::new(&folder),
+            scan: scan::FolderScan::start(folder, ctx.clone(), move |path| {
+                let entry = reader.read(path.clone());
+                (path, entry)
+            }),
+            queue: VecDeque::new(),
+            result: None,
+            options: self.search.options.clone(),
+            side,
+            known,
+            replaced,
+            first_choice: self.search.choice_names.len(),
+        });
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn start_folder_import(&mut self, folder: PathBuf, side: SideToUse, _ctx: &Context) {
+        let replaced = self.replace_for_import(side);
+        let result = match side {
+            SideToUse::Sources => self.import_source_folder(&folder),
+            SideToUse::Choices => self.import_choice_folder(&folder),
+        };
+        if let Some(replaced) = replaced {
+            self.search.restore_replaced(replaced, result.is_err());
+        }
+        if let Ok(summary) = result {
+            self.edits.mark();
+            self.status = AppStatus::Info(summary.message("file(s)"));
+            self.show_import_summary(summary);
+        }
+    }
+
+    /// Adds what the background import read since the last frame, up to
+    /// [`IMPORT_ENTRIES_PER_FRAME`] entries
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_folder_import(&mut self, ctx: &Context) {
+        let Some(mut pending) = self.pending_import.take() else {
+            return;
+        };
+
+        for progress in pending.scan.poll() {
+            match progress {
+                scan::Progress::Batch(entries) => pending.queue.extend(entries),
+                scan::Progress::Unlisted(count) => pending.summary.unlisted += count,
+                scan::Progress::Done(result) => pending.result = Some(result),
+            }
+        }
+        let count = pending.queue.len().min(IMPORT_ENTRIES_PER_FRAME);
+        for (path, entry) in pending.queue.drain(..count) {
+            let outcome = entry.and_then(|entry| self.search.push_entry(entry, &mut pending.known));
+            pending.summary.record(path, outcome);
+        }
+
+        let result = match pending.result.take() {
+            Some(result) if pending.queue.is_empty() => result,
+            result => {
+                pending.result = result;
+                if !pending.queue.is_empty() {
+                    ctx.request_repaint();
+                }
+                self.pending_import = Some(pending);
+                return;
+            }
+        };
+        let noun = match pending.side {
+            SideToUse::Sources => "source(s)",
+            SideToUse::Choices => "reference(s)",
+        };
+        let summary = pending.summary;
+        match pending.side {
+            SideToUse::Choices => {
+                self.search
+                    .group_choices_from(pending.first_choice, &batch_name(&pending.scan.folder));
+                if summary.added > 0 {
+                    self.search.update_all();
+                }
+            }
+            // Sources were scored against the choices and options of when the import started
+            SideToUse::Sources => {
+                if summary.added > 0
+                    && (self.search.choice_names.len() != pending.first_choice
+                        || self.search.options != pending.options)
+                {
+                    self.search.update_all();
+                }
+            }
+        }
+        let kept_list = pending.replaced.is_some() && !matches!(result, Ok(false));
+        if let Some(replaced) = pending.replaced {
+            self.search.restore_replaced(replaced, kept_list);
+        }
+        if summary.added > 0 && !kept_list {
+            self.edits.mark();
+        }
+        let added = if kept_list {
+            "Kept the previous list".to_owned()
+        } else {
+            summary.message(noun)
+        };
+        self.status = match result {
+            Ok(false) => AppStatus::Info(added),
+            Ok(true) => AppStatus::Notice(format!("Import cancelled, {}", added.to_lowercase())),
+            Err(error) if kept_list => AppStatus::Notice(format!(
+                "Could not read folder: {error}, {}",
+                added.to_lowercase()
+            )),
+            Err(error) => AppStatus::Notice(format!("Could not read folder: {error}")),
+        };
+        if !kept_list {
+            self.show_import_summary(summary);
+        }
+    }
+
+    /// Opens the import summary window if the import left out anything besides duplicates
+    fn show_import_summary(&mut self, summary: ImportSummary) {
+        if summary.has_details() {
+            self.import_summary = Some(summary);
+        }
+    }
+
+    /// Reads folder entries for `side` with the current import settings, see [`EntryReader`]
+    fn entry_reader(&self, side: SideToUse) -> EntryReader {
+        let choice_names = match side {
+            SideToUse::Sources => self.search.choice_names.clone(),
+            SideToUse::Choices => vec![],
+        };
+        EntryReader {
+            side,
+            mode: self.mode,
+            import_entries: self.import_entries,
+            symlinks: self.symlinks,
+            choice_index: ChoiceIndex::new(&choice_names),
+            choice_names,
+            options: self.search.options.clone(),
+        }
+    }
+
+    /// Adds the files (or subfolders) in `folder` as sources
+    fn import_source_folder(&mut self, folder: &Path) -> io::Result<ImportSummary> {
+        let mut known = self.search.source_paths();
+        let mut summary = ImportSummary::new(folder);
+        let reader = self.entry_reader(SideToUse::Sources);
+        for item in read_dir(folder)? {
+            let Ok(item) = item else {
+                summary.unlisted += 1;
+                continue;
+            };
+            let path = item.path();
+            let outcome = reader
+                .read(path.clone())
+                .and_then(|entry| self.search.push_entry(entry, &mut known));
+            summary.record(path, outcome);
+        }
+        Ok(summary)
+    }
+
+    /// Adds the files (or subfolders) in `folder` as choices
+    fn import_choice_folder(&mut self, folder: &Path) -> io::Result<ImportSummary> {
+        let first = self.search.choice_names.len();
+        let mut known = self.search.choice_paths();
+        let mut summary = ImportSummary::new(folder);
+        let reader = self.entry_reader(SideToUse::Choices);
+        for item in read_dir(folder)? {
+            let Ok(item) = item else {
+                summary.unlisted += 1;
+                continue;
+            };
+            let path = item.path();
+            let outcome = reader
+                .read(path.clone())
+                .and_then(|entry| self.search.push_entry(entry, &mut known));
+            summary.record(path, outcome);
+        }
+        self.search.group_choices_from(first, &batch_name(folder));
+        if summary.added > 0 {
+            self.search.update_all();
+        }
+        Ok(summary)
+    }
+
+    /// Each matched source with its new path in its own folder, or the first that has no folder
+    fn in_place_renames(&self) -> Result<Vec<(PathBuf, PathBuf)>, &PathBuf> {
+        self.iter_renames(false)
+            .into_iter()
+            .map(|(file_origin, new_name, _)| {
+                let folder = paths::parent_folder(file_origin).ok_or(file_origin)?;
+                Ok((file_origin.clone(), folder.join(new_name)))
+            })
+            .collect()
+    }
+
+    /// Renames the matched sources in their own folders, all or nothing, in the background
+    /// where there are threads. The post-run command follows if anything was renamed.
+    fn rename_in_place(&mut self, ctx: &Context) {
+        let renames = match self.in_place_renames() {
+            Ok(renames) => renames,
+            Err(file_origin) => {
+                eprintln!("Could not rename file: Malformed parent in filepath ({file_origin:?})");
+                self.status = AppStatus::Notice(format!(
+                    "Nothing renamed, {}: no parent folder",
+                    file_origin.display()
+                ));
+                return;
+            }
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if self.pending_rename.is_some() {
+                self.status = AppStatus::Notice("Still renaming, wait for it to finish".to_owned());
+                return;
+            }
+            let thread_renames = renames.clone();
+            let titles = self.audio_titles();
+            let task = Task::spawn(ctx.clone(), move |reporter| {
+                reporter.send(rename_and_tag(&thread_renames, &titles));
+            });
+            self.pending_rename = Some(PendingRename { task, renames });
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let (result, tag_tally) = rename_and_tag(&renames, &self.audio_titles());
+            self.finish_rename(result, tag_tally, ctx);
+        }
+    }
+
+    /// Shows how the batch renamed in place went once it finished
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_rename(&mut self, ctx: &Context) {
+        let Some(pending) = self.pending_rename.take() else {
+            return;
+        };
+        let running = pending.task.is_running();
+        match pending.task.poll().into_iter().next() {
+            Some((result, tag_tally)) => self.finish_rename(result, tag_tally, ctx),
+            None if !running => {
+                self.status = AppStatus::Notice(
+                    "Renaming stopped unexpectedly, check the source folders".to_owned(),
+                );
+            }
+            None => self.pending_rename = Some(pending),
+        }
+    }
+
+    /// Shows a summary of a batch renamed in place and starts the post-run command if anything
+    /// was renamed
+    fn finish_rename(
+        &mut self,
+        result: Result<rename::Outcome, rename::Failure>,
+        tag_tally: TagTally,
+        ctx: &Context,
+    ) {
+        let (summary, renamed) = match result {
+            Ok(outcome) => {
+                let mut results: Vec<String> = Vec::with_capacity(3);
+                if outcome.renamed > 0 {
+                    results.push(format!("{} Renamed", outcome.renamed));
+                }
+                if outcome.replaced > 0 {
+                    results.push(format!("{} Replaced", outcome.replaced));
+                }
+                results.extend(tag_tally.summary());
+                (results.join(" | "), outcome.renamed > 0)
+            }
+            Err(failure) => {
+                eprintln!(
+                    "Could not rename file: {} ({:?})",
+                    failure.error, failure.path
+                );
+                let mut summary = format!(
+                    "Nothing renamed, {}: {}",
+                    failure.path.display(),
+                    failure.error
+                );
+                if let Some(first) = failure.stranded.first() {
+                    summary = format!(
+                        "Rename failed and {} file(s) could not be put back, like {} | {}: {}",
+                        failure.stranded.len(),
+                        first.display(),
+                        failure.path.display(),
+                        failure.error
+                    );
+                }
+                (summary, false)
+            }
+        };
+        if renamed {
+            let output = PathBuf::from(&self.sources_path);
+            self.start_post_command(&output, summary, ctx);
+        } else {
+            self.status = AppStatus::Notice(summary);
+        }
+    }
+
+    /// Each renamed file with where it is copied to in `folder`
+    fn copies_to(&self, folder: &Path) -> Vec<(&PathBuf, PathBuf)> {
+        self.iter_renames(self.copy_failed_sources)
+            .into_iter()
+            .map(|(origin, new_name, destination)| {
+                (origin, destination.unwrap_or(folder).join(new_name))
+            })
+            .collect()
+    }
+
+    /// Copies the renamed files into `folder`, returning a summary of the results
+    fn copy_results(&self, folder: &Path) -> StepResult {
+        self.copy_files(self.copies_to(folder))
+    }
+
+    /// Copies each renamed file into the folder of the file it was matched to
+    fn copy_results_beside_matches(&self) -> StepResult {
+        self.copy_files(self.renames_beside_matches())
+    }
+
+    fn copy_files<'a>(
+        &self,
+        copies: impl IntoIterator<Item = (&'a PathBuf, PathBuf)>,
+    ) -> StepResult {
+        let titles = self.audio_titles();
+        let mut tally = CopyTally::default();
+        let mut tag_tally = TagTally::default();
+        let mut exists = paths::ExistsCheck::default();
+        for (file_origin, destination) in copies {
+            let result = exists
+                .try_exists(&destination)
+                .and_then(|overwrite| paths::copy(file_origin, &destination).map(|_| overwrite));
+            if result.is_ok() {
+                tag_tally.tag(&titles, file_origin, &destination);
+            }
+            tally.add(file_origin, &destination, result);
+        }
+        let summary = match tag_tally.summary() {
+            Some(tagged) => format!("{} | {tagged}", tally.summary()),
+            None => tally.summary(),
+        };
+        StepResult {
+            summary,
+            ok: tally.failed == 0,
+        }
+    }
+
+    /// The title tag for each file that is copied or renamed, by its path, when tagging is on
+    fn audio_titles(&self) -> HashMap<PathBuf, String> {
+        if !self.tag_titles {
+            return HashMap::new();
+        }
+        let numbers = self.numbering();
+        self.search
+            .source_names
+            .iter()
+            .zip(numbers)
+            .filter_map(|(source, number)| {
+                let choice = &self.search.choice_names[self.accepted_choice(source)?];
+                let path = match self.side_to_copy {
+                    SideToUse::Choices => &choice.file.path,
+                    SideToUse::Sources => &source.file.path,
+                };
+                let title = apply_template(
+                    &self.tag_template,
+                    choice.file.stem(),
+                    source.file.stem(),
+                    choice.file.stem(),
+                    number,
+                    source.file.exif.as_ref(),
+                );
+                Some((path.clone(), title))
+            })
+            .collect()
+    }
+
+    /// Copies the files from the window, in the background on native so the status bar can
+    /// show the progress. `output` is given to the post-run command once they are done.
+    fn start_copy(
+        &mut self,
+        copies: Vec<(PathBuf, PathBuf)>,
+        output: Option<PathBuf>,
+        ctx: &Context,
+    ) {
+        if copies.is_empty() {
+            self.status = AppStatus::Notice("Nothing to copy".to_owned());
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if self.pending_copy.is_some() {
+                self.status = AppStatus::Notice(
+                    "Still copying, cancel it or wait for it to finish".to_owned(),
+                );
+                return;
+            }
+            self.pending_copy = Some(PendingCopy {
+                copy: copier::BackgroundCopy::start(copies, self.audio_titles(), ctx.clone()),
+                tally: CopyTally::default(),
+                index: 0,
+                copied: 0,
+                size: 0,
+                output,
+                tag_tally: TagTally::default(),
+            });
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = (output, ctx);
+            let result = self.copy_files(copies.iter().map(|(origin, to)| (origin, to.clone())));
+            self.status = AppStatus::Notice(result.summary);
+        }
+        self.edits.save();
+    }
+
+    /// Counts the copies that finished since the last frame
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_copy(&mut self, ctx: &Context) {
+        let Some(mut pending) = self.pending_copy.take() else {
+            return;
+        };
+
+        let mut finished = false;
+        for progress in pending.copy.poll() {
+            match progress {
+                copier::Progress::File {
+                    index,
+                    copied,
+                    size,
+                } => {
+                    pending.index = index;
+                    pending.copied = copied;
+                    pending.size = size;
+                }
+                copier::Progress::Copied(index, result) => {
+                    let (origin, destination) = &pending.copy.copies[index];
+                    pending.tally.add(origin, destination, result);
+                    pending.index = index + 1;
+                    pending.copied = 0;
+                    pending.size = 0;
+                }
+                copier::Progress::Tagged(index, result) => {
+                    let (_, destination) = &pending.copy.copies[index];
+                    pending.tag_tally.record(destination, result);
+                }
+                copier::Progress::Done => finished = true,
+            }
+        }
+
+        if !finished {
+            self.pending_copy = Some(pending);
+            return;
+        }
+        let mut summary = pending.tally.summary();
+        if let Some(tagged) = pending.tag_tally.summary() {
+            summary = format!("{summary} | {tagged}");
+        }
+        let remaining = pending.copy.copies.len() - pending.index.min(pending.copy.copies.len());
+        if remaining > 0 {
+            summary = format!("Copy cancelled, {remaining} not copied | {summary}");
+        } else if let Some(output) = pending.output.filter(|_| pending.tally.failed == 0) {
+            self.start_post_command(&output, summary, ctx);
+            return;
+        }
+        self.status = AppStatus::Notice(summary);
+    }
+
+    /// Asks where to save the session, then quits if `close` and it was saved
+    fn save_session_dialog(&mut self, ctx: &Context, close: bool) {
+        let request = save_file(&self.session_path, "Save session", "session.json");
+        self.ask_path(ctx, request, move |app, file, _| {
+            if app.save_session(&file) && close {
+                app.allow_close = true;
+            }
+        });
+    }
+
+    /// Returns whether the session was saved
+    fn save_session(&mut self, file: &Path) -> bool {
+        if let Some(parent) = file.parent().map(|p| p.to_string_lossy().into_owned()) {
+            self.session_path = parent;
+        }
+
+        match self.search.to_session().save(file) {
+            Ok(()) => {
+                self.edits.save();
+                self.status = AppStatus::Info("Saved session".to_owned());
+                true
+            }
+            Err(error) => {
+                self.status = AppStatus::Notice(format!("Could not save session: {error}"));
+                false
+            }
+        }
+    }
+
+    fn open_session_dialog(&mut self, ctx: &Context) {
+        let request = pick_file(&self.session_path, "Open session", "Session", &["json"]);
+        self.ask_path(ctx, request, |app, file, _| app.open_session(&file));
+    }
+
+    fn open_session(&mut self, file: &Path) {
+        if let Some(parent) = file.parent().map(|p| p.to_string_lossy().into_owned()) {
+            self.session_path = parent;
+        }
+
+        match Session::load(file) {
+            Ok(session) => {
+                self.search.load_session(session);
+                // Undoable, but nothing to save
+                self.edits.mark();
+                self.edits.save();
+                self.status = AppStatus::Info(format!(
+                    "Opened session with {} source(s) and {} reference(s)",
+                    self.search.source_names.len(),
+                    self.search.choice_names.len()
+                ));
+            }
+            Err(error) => {
+                self.status = AppStatus::Notice(format!("Could not open session: {error}"));
+            }
+        }
+    }
+
+    fn set_mode(&mut self, mode: MatchMode) {
+        if mode != MatchMode::Files {
+            // Sources are renamed after the listed names, never the other way around
+            self.side_to_copy = SideToUse::Sources;
+            self.keep_extension = false;
+        }
+        if mode == MatchMode::Subtitles {
+            if self.name_template.is_empty() || self.name_template == "{name}" {
+                self.name_template = "{name}{lang}".to_owned();
+            }
+            let beside_matches = self
+                .output_targets
+                .iter()
+                .any(|t| t.action == OutputAction::CopyBesideMatch);
+            if !beside_matches {
+                self.output_targets.push(OutputTarget {
+                    action: OutputAction::CopyBesideMatch,
+                    path: String::new(),
+                    enabled: true,
+                });
+            }
+            self.search.options.language_tags = true;
+            self.search.update_all();
+        }
+        self.mode = mode;
+    }
+
+    /// Switches the output names to a media server's layout
+    fn apply_preset(&mut self, preset: &OutputPreset) {
+        self.name_template = preset.name_template.to_owned();
+        self.naming.set_convention(preset.convention);
+        // Servers sort leading articles on their own, and read the year and episode from names
+        self.relocate_articles = false;
+        self.keep_extension = false;
+        self.status = AppStatus::Info(format!("Using the {} preset", preset.label));
+    }
+
+    fn set_window_theme(ctx: &Context, theme: &WindowTheme, accent: Option<[u8; 3]>) {
+        let mut visuals = match theme {
+            WindowTheme::Dark => Visuals::dark(),
+            WindowTheme::Light => Visuals::light(),
+        };
+        if let Some([r, g, b]) = accent {
+            visuals.selection.bg_fill = Color32::from_rgb(r, g, b);
+        }
+        ctx.set_visuals(visuals);
+    }
+
+    // Rename choice
+    //   A_game.zip
+    // > A.png <
+    // > A_game.zip.png <
+
+    // Rename source
+    // > B.png <
+    //   B_game.zip
+    // > B_game.zip.png <
+
+    /// The group a source falls in with the current grouping, empty when ungrouped
+    fn source_group(&self, source: &SourceName) -> String {
+        match self.source_grouping {
+            SourceGrouping::None => "".to_owned(),
+            SourceGrouping::Folder => source.file.path.parent().map_or("".to_owned(), batch_name),
+            SourceGrouping::Extension => source.file.extension().to_lowercase(),
+        }
+    }
+
+    /// Names of all source groups, sorted
+    fn source_groups(&self) -> Vec<String> {
+        let mut groups: Vec<String> = self
+            .search
+            .source_names
+            .iter()
+            .map(|s| self.source_group(s))
+            .collect();
+        groups.sort();
+        groups.dedup();
+        groups
+    }
+
+    fn group_rule(&self, source: &SourceName) -> Option<&GroupRule> {
+        if self.source_grouping == SourceGrouping::None {
+            return None;
+        }
+        let group = self.source_group(source);
+        self.group_rules.iter().find(|rule| rule.group == group)
+    }
+
+    fn threshold_for(&self, source: &SourceName) -> f32 {
+        self.group_rule(source)
+            .and_then(|rule| rule.threshold)
+            .unwrap_or(self.threshold)
+    }
+
+    /// Sources whose capture metadata is still to be read, once a name template uses it
+    fn unread_exif(&self) -> Vec<PathBuf> {
+        let uses_exif = |template: &str| EXIF_TOKENS.iter().any(|token| template.contains(token));
+        let needed = uses_exif(&self.name_template)
+            || self
+                .group_rules
+                .iter()
+                .filter_map(|rule| rule.name_template.as_deref())
+                .any(uses_exif);
+        if !needed {
+            return vec![];
+        }
+        self.search
+            .source_names
+            .iter()
+            .filter(|source| source.file.exif.is_none() && !source.file.is_dir)
+            .map(|source| source.file.path.clone())
+            .collect()
+    }
+
+    /// Fills in capture metadata read for these paths; files without any get an empty one, so
+    /// they aren't read again
+    fn set_exif(&mut self, read: Vec<(PathBuf, exif::Exif)>) {
+        self.search.revision += 1;
+        let mut read: HashMap<PathBuf, exif::Exif> = read.into_iter().collect();
+        for source in self.search.source_names.iter_mut() {
+            if source.file.exif.is_none() {
+                if let Some(exif) = read.remove(&source.file.path) {
+                    source.file.exif = Some(exif);
+                }
+            }
+        }
+    }
+
+    /// Reads the capture metadata of the sources not read yet and waits for it, where there is
+    /// no window to keep responsive or no threads to read it on
+    fn read_exif_now(&mut self) {
+        let read = self
+            .unread_exif()
+            .into_iter()
+            .map(|path| {
+                let exif = exif::read(&path).unwrap_or_default();
+                (path, exif)
+            })
+            .collect();
+        self.set_exif(read);
+    }
+
+    /// Reads the capture metadata of the sources not read yet in the background, once a name
+    /// template uses it
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_exif(&mut self, ctx: &Context) {
+        if let Some(task) = &self.pending_exif {
+            let running = task.is_running();
+            let Some(read) = task.poll().into_iter().next() else {
+                if !running {
+                    self.pending_exif = None;
+                }
+                return;
+            };
+            self.pending_exif = None;
+            self.set_exif(read);
+            return;
+        }
+        let unread = self.unread_exif();
+        if unread.is_empty() {
+            return;
+        }
+        self.pending_exif = Some(Task::spawn(ctx.clone(), move |reporter| {
+            let mut read = Vec::with_capacity(unread.len());
+            for path in unread {
+                if reporter.is_cancelled() {
+                    return;
+                }
+                let exif = exif::read(&path).unwrap_or_default();
+                read.push((path, exif));
+            }
+            reporter.send(read);
+        }));
+    }
+
+    fn template_for(&self, source: &SourceName) -> &str {
+        self.group_rule(source)
+            .and_then(|rule| rule.name_template.as_deref())
+            .unwrap_or(&self.name_template)
+    }
+
+    /// The choice a source will be renamed with, if any
+    fn accepted_choice(&self, source: &SourceName) -> Option<usize> {
+        let below_threshold = source
+            .current_score()
+            .map_or(false, |s| s < self.threshold_for(source));
+        let excluded = self.exclude_unreviewed && self.needs_review(source);
+        source
+            .current_choice()
+            .filter(|c| !below_threshold && !excluded && *c < self.search.choice_names.len())
+    }
+
+    /// Automatic matches between the two thresholds are shown, but not trusted
+    fn needs_review(&self, source: &SourceName) -> bool {
+        let threshold = self.threshold_for(source);
+        source
+            .current_score()
+            .map_or(false, |s| s >= threshold && s < self.trust_threshold)
+    }
+
+    fn confidence(&self, source: &SourceName) -> report::Confidence {
+        let score = source.current_score();
+        let choice = source
+            .current_choice()
+            .and_then(|c| self.search.choice_names.get(c));
+        if source.manual_choice.is_some() {
+            report::Confidence::Manual
+        } else if choice.is_none() || score.map_or(true, |s| s < self.threshold_for(source)) {
+            report::Confidence::Unmatched
+        } else if self.needs_review(source) {
+            report::Confidence::NeedsReview
+        } else {
+            report::Confidence::Trusted
+        }
+    }
+
+    /// Source indices in the order the table shows them, pinned sources first, leaving out
+    /// the ones hidden by the row filter
+    fn row_order(&self) -> Vec<usize> {
+        let sources = &self.search.source_names;
+        let mut order: Vec<usize> = (0..sources.len())
+            .filter(|i| self.row_filter.shows(self.confidence(&sources[*i])))
+            .collect();
+        if self.review_order {
+            // Lowest best score first, then the closest runner-up; hand-picked matches last
+            let best = |s: &SourceName| s.choice_map.first().map_or(0.0, |(_, score)| *score);
+            order.sort_by(|a, b| {
+                let (a, b) = (&sources[*a], &sources[*b]);
+                a.manual_choice
+                    .is_some()
+                    .cmp(&b.manual_choice.is_some())
+                    .then_with(|| best(a).total_cmp(&best(b)))
+                    .then_with(|| a.score_gap().total_cmp(&b.score_gap()))
+            });
+        }
+        order.sort_by_key(|i| !sources[*i].pinned);
+        order
+    }
+
+    /// Turns the current match of every row the table shows into a manual choice, so later
+    /// option changes leave them alone. Rows below the threshold are left unmatched. Returns how
+    /// many rows were confirmed.
+    fn confirm_visible_rows(&mut self) -> usize {
+        let mut confirmed = 0;
+        for index in self.row_order() {
+            let shown = matches!(
+                self.confidence(&self.search.source_names[index]),
+                report::Confidence::Trusted | report::Confidence::NeedsReview
+            );
+            let source = &mut self.search.source_names[index];
+            if !shown || source.locked {
+                continue;
+            }
+            let Some(choice) = source.current_choice() else {
+                continue;
+            };
+            source.set_choice(Some(choice));
+            if self.search.remember_matches {
+                self.search.remembered.insert(
+                    source.file.stem().to_owned(),
+                    self.search.choice_names[choice].file.stem().to_owned(),
+                );
+            }
+            confirmed += 1;
+        }
+        confirmed
+    }
+
+    /// Shown sources whose name or closest match contains `query`, ignoring case
+    fn find_matches(&self, query: &str) -> Vec<usize> {
+        let query = query.to_lowercase();
+        if query.is_empty() {
+            return vec![];
+        }
+        self.search
+            .source_names
+            .iter()
+            .enumerate()
+            .filter(|(_, source)| self.row_filter.shows(self.confidence(source)))
+            .filter(|(_, source)| {
+                source.file.name.to_lowercase().contains(&query)
+                    || source
+                        .current_choice()
+                        .and_then(|c| self.search.choice_names.get(c))
+                        .map_or(false, |c| c.file.name.to_lowercase().contains(&query))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Highlights the next (or previous) row matching the find query, wrapping around
+    fn find_next(&mut self, backwards: bool) {
+        let Some(find) = &self.find else {
+            return;
+        };
+        let matches = self.find_matches(&find.query);
+        let current = find.row;
+        let next = match (current, backwards) {
+            (None, false) => matches.first(),
+            (None, true) => matches.last(),
+            (Some(row), false) => matches.iter().find(|m| **m > row).or(matches.first()),
+            (Some(row), true) => matches.iter().rev().find(|m| **m < row).or(matches.last()),
+        }
+        .copied();
+
+        if let Some(find) = &mut self.find {
+            find.row = next;
+            find.scroll = next.is_some();
+        }
+    }
+
+    /// A source's table row as tab-separated text: name, similarity, match and renamed file
+    fn row_text(&self, source: &SourceName, number: Option<usize>) -> String {
+        let score = source.current_score();
+        let choice = source
+            .current_choice()
+            .filter(|_| score.map_or(true, |s| s >= self.threshold_for(source)))
+            .and_then(|c| self.search.choice_names.get(c));
+        let rename = self.accepted_choice(source).map_or("".to_owned(), |c| {
+            self.rename(source, &self.search.choice_names[c], number)
+        });
+        [
+            source.file.name.to_string(),
+            score.map_or("N/A".to_owned(), |s| format!("{:.1}%", 100.0 * s)),
+            choice.map_or("".to_owned(), |c| c.file.name.to_string()),
+            rename,
+        ]
+        .join("\t")
+    }
+
+    /// Whether each source's renamed file would overwrite another source's output
+    fn output_collisions(&self) -> Vec<bool> {
+        let numbers = self.numbering();
+        let outputs: Vec<Option<(Option<&Path>, String)>> = self
+            .search
+            .source_names
+            .iter()
+            .zip(numbers)
+            .map(|(source, number)| {
+                let choice = &self.search.choice_names[self.accepted_choice(source)?];
+                let name = self.rename(source, choice, number).to_lowercase();
+                Some((source.destination.as_deref(), name))
+            })
+            .collect();
+
+        let mut counts: HashMap<&(Option<&Path>, String), usize> = HashMap::new();
+        for output in outputs.iter().flatten() {
+            *counts.entry(output).or_default() += 1;
+        }
+        outputs
+            .iter()
+            .map(|output| output.as_ref().map_or(false, |o| counts[o] > 1))
+            .collect()
+    }
+
+    /// Indices of the sources using each choice
+    fn choice_usage(&self) -> Vec<Vec<usize>> {
+        let mut usage = vec![vec![]; self.search.choice_names.len()];
+        for (index, source) in self.search.source_names.iter().enumerate() {
+            if let Some(choice) = self.accepted_choice(source) {
+                usage[choice].push(index);
+            }
+        }
+        usage
+    }
+
+    /// How many sources use the match of each source, 0 for sources without one
+    fn match_usage(&self) -> Vec<usize> {
+        let usage = self.choice_usage();
+        self.search
+            .source_names
+            .iter()
+            .map(|source| self.accepted_choice(source).map_or(0, |c| usage[c].len()))
+            .collect()
+    }
+
+    /// Sequence numbers for the `{n}` template token, per source
+    fn numbering(&self) -> Vec<Option<usize>> {
+        let mut numbers = vec![None; self.search.source_names.len()];
+        let numbered = self.name_template.contains("{n")
+            || self.group_rules.iter().any(|rule| {
+                rule.name_template
+                    .as_ref()
+                    .map_or(false, |t| t.contains("{n"))
+            });
+        if !numbered {
+            return numbers;
+        }
+
+        let sources = &self.search.source_names;
+        let mut accepted: Vec<(usize, usize)> = sources
+            .iter()
+            .enumerate()
+            .filter_map(|(i, source)| self.accepted_choice(source).map(|c| (i, c)))
+            .collect();
+        match self.number_order {
+            NumberOrder::Match => accepted.sort_by(|(a_source, a_choice), (b_source, b_choice)| {
+                a_choice.cmp(b_choice).then_with(|| {
+                    sources[*a_source]
+                        .file
+                        .name
+                        .cmp(&sources[*b_source].file.name)
+                })
+            }),
+            NumberOrder::Name => {
+                accepted.sort_by(|(a, _), (b, _)| sources[*a].file.name.cmp(&sources[*b].file.name))
+            }
+            NumberOrder::Date => accepted.sort_by(|(a, _), (b, _)| {
+                sources[*a]
+                    .file
+                    .modified
+                    .cmp(&sources[*b].file.modified)
+                    .then_with(|| sources[*a].file.name.cmp(&sources[*b].file.name))
+            }),
+        }
+
+        for (n, (source, _)) in accepted.into_iter().enumerate() {
+            numbers[source] = Some(self.number_start + n);
+        }
+        numbers
+    }
+
+    /// Renamed files paired with a destination beside the file they matched
+    fn renames_beside_matches(&self) -> Vec<(&PathBuf, PathBuf)> {
+        let numbers = self.numbering();
+        self.search
+            .source_names
+            .iter()
+            .zip(numbers)
+            .filter_map(|(source, number)| {
+                let choice = &self.search.choice_names[self.accepted_choice(source)?];
+                let (origin, counterpart) = match self.side_to_copy {
+                    SideToUse::Choices => (&choice.file.path, &source.file.path),
+                    SideToUse::Sources => (&source.file.path, &choice.file.path),
+                };
+                // Name-only choices have no folder to go to
+                let folder = match &source.destination {
+                    Some(destination) => destination.as_path(),
+                    None => counterpart.parent().filter(|p| !p.as_os_str().is_empty())?,
+                };
+                Some((origin, folder.join(self.rename(source, choice, number))))
+            })
+            .collect()
+    }
+
+    /// Each file to rename with its new name, and the source's own output folder if it has one
+    fn iter_renames(&self, include_failed: bool) -> Vec<(&PathBuf, OsString, Option<&Path>)> {
+        let numbers = self.numbering();
+        return self
+            .search
+            .source_names
+            .iter()
+            .zip(numbers)
+            .filter_map(|(source, number)| {
+                let choice = self
+                    .accepted_choice(source)
+                    .map(|c| &self.search.choice_names[c]);
+                if let Some(choice) = choice {
+                    let rename = self.rename(source, choice, number);
+                    let path = match self.side_to_copy {
+                        SideToUse::Choices => &choice.file.path,
+                        SideToUse::Sources => &source.file.path,
+                    };
+                    Some((path, rename.into(), source.destination.as_deref()))
+                } else if include_failed && self.side_to_copy == SideToUse::Sources {
+                    // Unmatched files keep their exact name, even when it isn't valid UTF-8
+                    Some((
+                        &source.file.path,
+                        source.file.path.file_name()?.to_owned(),
+                        source.destination.as_deref(),
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+    }
+
+    fn rename(&self, source: &SourceName, choice: &ChoiceName, number: Option<usize>) -> String {
+        let template = self.template_for(source);
+        let (body, extension) = if let Some(target) = choice
+            .rename_to
+            .as_ref()
+            .filter(|_| self.side_to_copy == SideToUse::Sources)
+        {
+            // Mapped names are used verbatim, only the extension is filled in
+            let extension = source.file.extension();
+            let (target_body, target_extension) = split_extension(target);
+            let body = if target_extension.eq_ignore_ascii_case(extension) {
+                target_body
+            } else {
+                target.as_str()
+            };
+            (body, extension)
+        } else {
+            let (original, reference) = match self.side_to_copy {
+                SideToUse::Choices => (&choice.file, &source.file),
+                SideToUse::Sources => (&source.file, &choice.file),
+            };
+
+            let extension = original.extension();
+            let body = if self.keep_extension {
+                reference.name.as_str()
+            } else {
+                reference.stem()
+            };
+            (body, extension)
+        };
+
+        let body = apply_template(
+            template,
+            body,
+            source.file.stem(),
+            choice.file.stem(),
+            number,
+            source.file.exif.as_ref(),
+        );
+        let body = self.replace_rules.iter().fold(
+            format!("{}{body}{}", self.name_prefix, self.name_suffix),
+            |name, rule| rule.apply(&name),
+        );
+        let body = if self.relocate_articles {
+            normalize::relocate_article(&body, &self.search.options.article_languages)
+        } else {
+            body
+        };
+        paths::usable_name(&with_extension(&body, extension))
+    }
+
+    /// Scores the files of two folders with each algorithm, for `--bench` on the command line
+    pub fn benchmark_folders(sources: &Path, choices: &Path) -> io::Result<String> {
+        let mut search = FuzzySearch::default();
+        for item in read_dir(sources)?.filter_map(|i| i.ok()) {
+            if let Ok(source) = SourceName::try_from(item.path()) {
+                search.source_names.push(source);
+            }
+        }
+        for item in read_dir(choices)?.filter_map(|i| i.ok()) {
+            if let Ok(choice) = ChoiceName::try_from(item.path()) {
+                search.choice_names.push(choice);
+            }
+        }
+        Ok(format_benchmark(&search.benchmark()))
+    }
+
+    /// Matches the files of `sources` against the files of `choices`, writing each as a
+    /// tab-separated `source, best choice, score` line as soon as it is scored, for `--match` on
+    /// the command line. Only the choices are kept in memory, sources are read and written one at
+    /// a time. Returns how many sources were written.
+    pub fn stream_matches(
+        sources: &Path,
+        choices: &Path,
+        out: &mut impl io::Write,
+    ) -> io::Result<usize> {
+        let mut search = FuzzySearch::default();
+        // Only the best candidate is written
+        search.options.kept_candidates = 1;
+        for item in read_dir(choices)?.filter_map(|i| i.ok()) {
+            if let Ok(choice) = ChoiceName::try_from(item.path()) {
+                search.choice_names.push(choice);
+            }
+        }
+
+        let paths = read_dir(sources)?.filter_map(|i| i.ok()).map(|i| i.path());
+        let mut count = 0;
+        for source in search.match_iter(paths) {
+            let (choice, score) = source.choice_map.first().map_or(("", 0.0), |(c, s)| {
+                (search.choice_names[*c].file.name.as_str(), *s)
+            });
+            writeln!(out, "{}\t{choice}\t{score:.3}", source.file.name)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Reads requests from stdin, see [`automation`]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start_automation(&mut self, ctx: &Context) {
+        self.automation = Some(automation::listen(ctx.clone()));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn handle_automation(&mut self) {
+        let Some(receiver) = &self.automation else {
+            return;
+        };
+        let requests: Vec<_> = receiver.try_iter().collect();
+        for request in requests {
+            let before = self.search.fingerprint();
+            let response = match request {
+                Ok(request) => self.handle_request(request),
+                Err(error) => automation::Response::failure(error),
+            };
+            // Requests that changed the lists are edits like any other
+            if self.search.fingerprint() != before {
+                self.edits.mark();
+            }
+            automation::respond(&response);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn handle_request(&mut self, request: automation::Request) -> automation::Response {
+        use automation::{Request, Response};
+
+        match request {
+            Request::ImportSources { path } => {
+                if path.is_dir() {
+                    match self.import_source_folder(&path) {
+                        Ok(summary) => Response::success(summary.message("source(s)")),
+                        Err(error) => Response::failure(error.to_string()),
+                    }
+                } else if path.is_file() && self.accepts_source(&path) {
+                    let mut known = self.search.source_paths();
+                    let index = ChoiceIndex::new(&self.search.choice_names);
+                    match self.search.add_source(path.clone(), &index, &mut known) {
+                        Ok(()) => Response::success("Added 1 source(s)"),
+                        Err(Skipped::Unreadable) => {
+                            Response::failure(format!("Could not read {path:?}"))
+                        }
+                        Err(_) => Response::success(added_message(0, 1, "source(s)")),
+                    }
+                } else {
+                    Response::failure(format!("Not a usable file or folder: {path:?}"))
+                }
+            }
+            Request::ImportChoices { path } => {
+                if path.is_dir() {
+                    match self.import_choice_folder(&path) {
+                        Ok(summary) => Response::success(summary.message("reference(s)")),
+                        Err(error) => Response::failure(error.to_string()),
+                    }
+                } else if path.is_file() {
+                    let mut known = self.search.choice_paths();
+                    match self.search.add_choice(path.clone(), &mut known) {
+                        Ok(()) => {
+                            self.search.update_all();
+                            Response::success("Added 1 reference(s)")
+                        }
+                        Err(Skipped::Unreadable) => {
+                            Response::failure(format!("Could not read {path:?}"))
+                        }
+                        Err(_) => Response::success(added_message(0, 1, "reference(s)")),
+                    }
+                } else {
+                    Response::failure(format!("Not a usable file or folder: {path:?}"))
+                }
+            }
+            Request::ClearSources => {
+                self.execute(Command::ClearSources);
+                Response::success("Cleared all sources")
+            }
+            Request::ClearChoices => {
+                self.execute(Command::ClearChoices);
+                Response::success("Cleared all references")
+            }
+            Request::SetOptions {
+                algorithm,
+                threshold,
+                keep_extension,
+                side_to_copy,
+                name_template,
+            } => {
+                if let Some(algorithm) = algorithm {
+                    self.search.options.algorithm = algorithm;
+                    self.search.update_all();
+                }
+                if let Some(threshold) = threshold {
+                    self.threshold = threshold.clamp(0.0, 1.0);
+                }
+                if let Some(keep_extension) = keep_extension {
+                    self.keep_extension = keep_extension;
+                }
+                if let Some(side_to_copy) = side_to_copy.filter(|_| self.mode == MatchMode::Files) {
+                    self.side_to_copy = side_to_copy;
+                }
+                if let Some(name_template) = name_template {
+                    self.name_template = name_template;
+                }
+                Response::success("Updated options")
+            }
+            Request::GetMapping => {
+                let numbers = self.numbering();
+                let mapping = self
+                    .search
+                    .source_names
+                    .iter()
+                    .zip(numbers)
+                    .map(|(source, number)| {
+                        let choice = self
+                            .accepted_choice(source)
+                            .map(|c| &self.search.choice_names[c]);
+                        automation::MappingEntry {
+                            source: source.file.name.to_string(),
+                            choice: choice.map(|c| c.file.name.to_string()),
+                            score: source.current_score(),
+                            rename: choice.map(|c| self.rename(source, c, number)),
+                        }
+                    })
+                    .collect();
+                Response {
+                    ok: true,
+                    mapping: Some(mapping),
+                    ..Default::default()
+                }
+            }
+            Request::CopyResults { folder } => {
+                if folder.is_dir() {
+                    let result = self.copy_results(&folder);
+                    if result.ok {
+                        Response::success(result.summary)
+                    } else {
+                        Response::failure(result.summary)
+                    }
+                } else {
+                    Response::failure(format!("Not a folder: {folder:?}"))
+                }
+            }
+            Request::ExportReport { path } => match fs::write(&path, self.report_html()) {
+                Ok(()) => Response::success("Exported report"),
+                Err(error) => Response::failure(error.to_string()),
+            },
+        }
+    }
+
+    fn report_html(&self) -> String {
+        report::render_html(
+            "Fuzzy Rename Report",
+            &self.report_settings(),
+            &self.report_rows(),
+        )
+    }
+
+    fn report_settings(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "Mode",
+                match self.mode {
+                    MatchMode::Files => "Files",
+                    MatchMode::RomSet => "ROM set",
+                    MatchMode::CsvMapping => "CSV mapping",
+                    MatchMode::Subtitles => "Subtitles",
+                }
+                .to_owned(),
+            ),
+            (
+                "Search algorithm",
+                self.search.options.algorithm.label().to_owned(),
+            ),
+            (
+                "Similarity threshold",
+                format!("{:.0}%", 100.0 * self.threshold),
+            ),
+            (
+                "Trusted threshold",
+                format!("{:.0}%", 100.0 * self.trust_threshold),
+            ),
+            (
+                "Files to copy",
+                match self.side_to_copy {
+                    SideToUse::Choices => "Choices",
+                    SideToUse::Sources => "Sources",
+                }
+                .to_owned(),
+            ),
+            ("Keep extensions", self.keep_extension.to_string()),
+            ("Name template", self.name_template.clone()),
+            ("Sources", self.search.source_names.len().to_string()),
+            ("Choices", self.search.choice_names.len().to_string()),
+        ]
+    }
+
+    /// Rough size of the similarity matrix export, to warn before one that won't fit in memory
+    fn matrix_export_size(&self) -> usize {
+        let choices = self.search.choice_names.len();
+        let per_source = if self.matrix_top_k == 0 {
+            choices
+        } else {
+            self.matrix_top_k.min(choices)
+        };
+        self.search
+            .source_names
+            .len()
+            .saturating_mul(per_source)
+            .saturating_mul(MATRIX_ROW_BYTES)
+    }
+
+    fn export_matrix_dialog(&mut self, ctx: &Context) {
+        let request = save_file(
+            &self.renames_path,
+            "Save similarity matrix",
+            "similarity_matrix.csv",
+        );
+        self.ask_path(ctx, request, |app, file, _| {
+            app.status = match fs::write(&file, app.matrix_csv()) {
+                Ok(()) => AppStatus::Info("Exported similarity matrix".to_owned()),
+                Err(error) => {
+                    AppStatus::Notice(format!("Could not write similarity matrix: {error}"))
+                }
+            };
+        });
+    }
+
+    /// The similarity matrix as CSV, see [`FuzzySearch::similarity_matrix`]
+    fn matrix_csv(&self) -> String {
+        let cells: Vec<(&str, &str, f32)> = self
+            .search
+            .similarity_matrix(self.matrix_top_k)
+            .into_iter()
+            .map(|(source, choice, score)| {
+                (
+                    self.search.source_names[source].file.name.as_str(),
+                    self.search.choice_names[choice].file.name.as_str(),
+                    score,
+                )
+            })
+            .collect();
+        report::render_matrix_csv(&cells)
+    }
+
+    fn report_rows(&self) -> Vec<report::Row<'_>> {
+        let numbers = self.numbering();
+        self.search
+            .source_names
+            .iter()
+            .zip(numbers)
+            .map(|(source, number)| {
+                let score = source.current_score();
+                let accepted = self.accepted_choice(source);
+                let choice = source
+                    .current_choice()
+                    .and_then(|c| self.search.choice_names.get(c));
+                let confidence = self.confidence(source);
+                report::Row {
+                    source: &source.file.name,
+                    score,
+                    confidence,
+                    choice: choice.map_or("", |c| c.file.name.as_str()),
+                    rename: accepted.map_or("".to_owned(), |c| {
+                        self.rename(source, &self.search.choice_names[c], number)
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    fn clear_sources_confirmation(&self) -> Confirmation {
+        let sources = &self.search.source_names;
+        let picked = sources
+            .iter()
+            .filter(|s| matches!(s.manual_choice, Some(Some(_))))
+            .count();
+        Confirmation {
+            title: "Clear all sources",
+            summary: format!(
+                "{} source(s) will be removed from the list, {picked} with a picked match. \
+                 The files themselves are kept.",
+                sources.len()
+            ),
+            files: sources.iter().map(|s| s.file.name.to_string()).collect(),
+            accept: "Clear",
+            action: Box::new(|app, _| app.run(Command::ClearSources)),
+        }
+    }
+
+    fn clear_choices_confirmation(&self) -> Confirmation {
+        let choices = &self.search.choice_names;
+        let matched = self
+            .search
+            .source_names
+            .iter()
+            .filter(|s| self.accepted_choice(s).is_some())
+            .count();
+        Confirmation {
+            title: "Clear all references",
+            summary: format!(
+                "{} reference(s) will be removed from the list and {matched} source(s) lose their \
+                 match. The files themselves are kept.",
+                choices.len()
+            ),
+            files: choices.iter().map(|c| c.file.name.to_string()).collect(),
+            accept: "Clear",
+            action: Box::new(|app, _| app.run(Command::ClearChoices)),
+        }
+    }
+
+    /// Renaming the matched files where they are, listed with their new names
+    fn rename_confirmation(&self) -> Confirmation {
+        let (summary, files) = match self.in_place_renames() {
+            Ok(renames) => {
+                let mut summary = format!(
+                    "{} file(s) will be renamed in their own folders, their old names are not kept.",
+                    renames.len()
+                );
+                let (waiting, cycles) = rename::dependencies(&renames);
+                if waiting > 0 {
+                    summary.push_str(&format!(
+                        "\n{waiting} file(s) take the name of another renamed file, they are \
+                         renamed in order"
+                    ));
+                    if cycles > 0 {
+                        summary.push_str(&format!(
+                            " and {cycles} cycle(s) go through temporary names"
+                        ));
+                    }
+                }
+                let name = |path: &Path| {
+                    path.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned()
+                };
+                let files = renames
+                    .iter()
+                    .map(|(from, to)| format!("{} \u{2192} {}", name(from), name(to)))
+                    .collect();
+                (summary, files)
+            }
+            Err(path) => (
+                format!(
+                    "{} has no folder, nothing can be renamed in place",
+                    path.display()
+                ),
+                vec![],
+            ),
+        };
+        Confirmation {
+            title: "Directly rename files",
+            summary,
+            files,
+            accept: "Rename",
+            action: Box::new(|app, ctx| {
+                app.confirm_overlaps(ctx, &[], |app, ctx| {
+                    if let Err(reason) = app.validate_renames(false) {
+                        app.status = AppStatus::Notice(reason);
+                        return;
+                    }
+                    app.rename_in_place(ctx);
+                    app.edits.save();
+                });
+            }),
+        }
+    }
+
+    /// Files imported as both a source and a choice, which renaming one side overwrites
+    fn shared_files_warning(&self) -> Option<String> {
+        let sources: HashSet<&Path> = self
+            .search
+            .source_names
+            .iter()
+            .filter(|s| !s.file.path.as_os_str().is_empty())
+            .map(|s| s.file.canonical.as_path())
+            .collect();
+        let shared: Vec<&Path> = self
+            .search
+            .choice_names
+            .iter()
+            .map(|c| c.file.canonical.as_path())
+            .filter(|path| sources.contains(path))
+            .collect();
+        let first = shared.first()?;
+        Some(format!(
+            "{} file(s) are both a source and a choice, like {}",
+            shared.len(),
+            first.display()
+        ))
+    }
+
+    /// Whether `folder` holds imported sources or choices, which copies into it may overwrite
+    fn output_folder_warning(&self, folder: &Path) -> Option<String> {
+        let output = fs::canonicalize(folder).unwrap_or_else(|_| folder.to_owned());
+        let folders: HashSet<&Path> = self
+            .search
+            .source_names
+            .iter()
+            .map(|s| &s.file.canonical)
+            .chain(self.search.choice_names.iter().map(|c| &c.file.canonical))
+            .filter_map(|path| paths::parent_folder(path))
+            .collect();
+        folders.contains(output.as_path()).then(|| {
+            format!(
+                "{} holds imported files, renamed copies may overwrite them",
+                output.display()
+            )
+        })
+    }
+
+    /// Runs `action` now, or once the user confirms it when copying into `outputs` or renaming
+    /// may overwrite imported files
+    fn confirm_overlaps(
+        &mut self,
+        ctx: &Context,
+        outputs: &[&Path],
+        action: impl FnOnce(&mut MainApp, &Context) + 'static,
+    ) {
+        let warnings: Vec<String> = self
+            .shared_files_warning()
+            .into_iter()
+            .chain(
+                outputs
+                    .iter()
+                    .filter_map(|folder| self.output_folder_warning(folder)),
+            )
+            .collect();
+        if warnings.is_empty() {
+            action(self, ctx);
+        } else {
+            self.confirm_overlap = Some((warnings, Box::new(action)));
+        }
+    }
+
+    /// Runs every enabled output target in order, returning how each went, or `Err` with the
+    /// reason nothing was run. Imported files that may be overwritten cancel the outputs unless
+    /// the user already went ahead in [`Self::confirm_overlaps`].
+    fn execute_outputs(&mut self, overlaps_confirmed: bool) -> Result<Vec<StepResult>, String> {
+        self.validate_renames(self.copy_failed_sources)?;
+        if !overlaps_confirmed {
+            let warnings: Vec<String> = self
+                .shared_files_warning()
+                .into_iter()
+                .chain(
+                    self.copy_target_folders()
+                        .iter()
+                        .filter_map(|folder| self.output_folder_warning(folder)),
+                )
+                .collect();
+            if !warnings.is_empty() {
+                return Err(format!("Cancelled, {}", warnings.join(", ").to_lowercase()));
+            }
+        }
+        let mut results: Vec<StepResult> = vec![];
+        for index in 0..self.output_targets.len() {
+            let target = &self.output_targets[index];
+            if !target.enabled {
+                continue;
+            }
+            let path = PathBuf::from(&target.path);
+            let label = target.action.label();
+            let result = match target.action {
+                OutputAction::CopyToFolder if path.is_dir() => Ok(self.copy_results(&path)),
+                OutputAction::CopyToFolder => Err(format!("Not a folder: {path:?}")),
+                OutputAction::CopyBesideMatch => Ok(self.copy_results_beside_matches()),
+                OutputAction::ExportReport => fs::write(&path, self.report_html())
+                    .map(|_| StepResult::done("Exported"))
+                    .map_err(|e| e.to_string()),
+                OutputAction::ExportMapping => {
+                    fs::write(&path, report::render_csv(&self.report_rows()))
+                        .map(|_| StepResult::done("Exported"))
+                        .map_err(|e| e.to_string())
+                }
+                OutputAction::WriteLog => {
+                    let summaries: Vec<&str> = results.iter().map(|r| r.summary.as_str()).collect();
+                    let mut log = summaries.join("\n");
+                    log.push('\n');
+                    for (origin, new_name, _) in self.iter_renames(self.copy_failed_sources) {
+                        log.push_str(&format!(
+                            "{} -> {}\n",
+                            origin.display(),
+                            new_name.to_string_lossy()
+                        ));
+                    }
+                    fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&path)
+                        .and_then(|mut file| io::Write::write_all(&mut file, log.as_bytes()))
+                        .map(|_| StepResult::done("Written"))
+                        .map_err(|e| e.to_string())
+                }
+            };
+            results.push(match result {
+                Ok(result) => StepResult {
+                    summary: format!("{label}: {}", result.summary),
+                    ok: result.ok,
+                },
+                Err(error) => StepResult {
+                    summary: format!("{label} failed: {error}"),
+                    ok: false,
+                },
+            });
+        }
+        if !results.is_empty() {
+            self.edits.save();
+        }
+        Ok(results)
+    }
+
+    /// The folder given to the post-run command after the output profile ran
+    fn post_command_output(&self) -> PathBuf {
+        self.copy_target_folders()
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| PathBuf::from(&self.renames_path))
+    }
+
+    /// Folders the enabled output steps copy into
+    fn copy_target_folders(&self) -> Vec<PathBuf> {
+        self.output_targets
+            .iter()
+            .filter(|t| t.enabled && t.action == OutputAction::CopyToFolder)
+            .map(|t| PathBuf::from(&t.path))
+            .collect()
+    }
+
+    /// Runs the output profile once the user confirmed any overwrites, showing how it went in
+    /// the status
+    fn run_outputs(&mut self, ctx: &Context) {
+        let folders = self.copy_target_folders();
+        let folders: Vec<&Path> = folders.iter().map(PathBuf::as_path).collect();
+        self.confirm_overlaps(ctx, &folders, |app, ctx| {
+            app.show_outputs(ctx);
+        });
+    }
+
+    /// Runs the output profile of the job in `file`, keeping its mapping for the next run once
+    /// every step succeeded
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run_job_outputs(&mut self, ctx: &Context, file: PathBuf) {
+        let folders = self.copy_target_folders();
+        let folders: Vec<&Path> = folders.iter().map(PathBuf::as_path).collect();
+        self.confirm_overlaps(ctx, &folders, move |app, ctx| {
+            if app.show_outputs(ctx) {
+                app.save_last_run(&file);
+            }
+        });
+    }
+
+    /// Runs the output profile and shows how it went, returning whether every step succeeded
+    fn show_outputs(&mut self, ctx: &Context) -> bool {
+        match self.execute_outputs(true) {
+            Ok(results) => {
+                let ok = !results.is_empty() && results.iter().all(|r| r.ok);
+                let summaries: Vec<String> = results.into_iter().map(|r| r.summary).collect();
+                let summary = summaries.join("; ");
+                if ok {
+                    let output = self.post_command_output();
+                    self.start_post_command(&output, summary, ctx);
+                } else {
+                    self.status = AppStatus::Notice(summary);
+                }
+                ok
+            }
+            Err(reason) => {
+                self.status = AppStatus::Notice(reason);
+                false
+            }
+        }
+    }
+
+    /// The paths the post-run command gets for a batch written to `output`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn post_command_placeholders(&self, output: &Path) -> [(&'static str, PathBuf); 2] {
+        let report = self
+            .output_targets
+            .iter()
+            .find(|t| t.enabled && t.action == OutputAction::ExportReport)
+            .map(|t| PathBuf::from(&t.path))
+            .unwrap_or_default();
+        [("output", output.to_owned()), ("report", report)]
+    }
+
+    /// Runs the post-run command for a batch written to `output` and waits for it, for jobs run
+    /// without a window
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run_post_command(&self, output: &Path) -> Option<StepResult> {
+        if self.post_run_command.trim().is_empty() {
+            return None;
+        }
+        let placeholders = self.post_command_placeholders(output);
+        let placeholders: Vec<(&str, &Path)> = placeholders
+            .iter()
+            .map(|(name, path)| (*name, path.as_path()))
+            .collect();
+        Some(hook_result(hooks::run(
+            &self.post_run_command,
+            &placeholders,
+        )))
+    }
+
+    /// Shows `summary` of a batch written to `output`, and starts the post-run command in the
+    /// background if there is one, adding how it went once it finishes
+    fn start_post_command(&mut self, output: &Path, summary: String, ctx: &Context) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if !self.post_run_command.trim().is_empty() {
+            let command = self.post_run_command.clone();
+            let placeholders = self.post_command_placeholders(output);
+            let task = Task::spawn(ctx.clone(), move |reporter| {
+                let placeholders: Vec<(&str, &Path)> = placeholders
+                    .iter()
+                    .map(|(name, path)| (*name, path.as_path()))
+                    .collect();
+                reporter.send(hooks::run(&command, &placeholders));
+            });
+            self.status = AppStatus::Info(format!("{summary}; Post-run command: Running"));
+            self.pending_hook = Some(PendingHook { task, summary });
+            return;
+        }
+        let _ = (output, ctx);
+        self.status = AppStatus::Notice(summary);
+    }
+
+    /// Adds how the post-run command went to the status once it finished
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_post_command(&mut self) {
+        let Some(pending) = self.pending_hook.take() else {
+            return;
+        };
+        let running = pending.task.is_running();
+        let result = match pending.task.poll().into_iter().next() {
+            Some(result) => hook_result(result),
+            None if !running => StepResult {
+                summary: "Post-run command failed: it stopped without an exit code".to_owned(),
+                ok: false,
+            },
+            None => {
+                self.pending_hook = Some(pending);
+                return;
+            }
+        };
+        self.status = AppStatus::Notice(format!("{}; {}", pending.summary, result.summary));
+    }
+
+    /// Passes the planned renames to the validation command, `Err` with its reason if it
+    /// vetoes them
+    fn validate_renames(&self, include_failed: bool) -> Result<(), String> {
+        if self.naming.block_output && self.naming.is_enabled() {
+            let broken: Vec<(String, String)> = self
+                .iter_renames(include_failed)
+                .into_iter()
+                .filter_map(|(_, rename, _)| {
+                    let rename = rename.to_string_lossy().into_owned();
+                    let problem = self.naming.problems(&rename).into_iter().next()?;
+                    Some((rename, problem))
+                })
+                .collect();
+            if let Some((rename, problem)) = broken.first() {
+                return Err(format!(
+                    "{} name(s) break the {} naming convention, like {rename}: {problem}",
+                    broken.len(),
+                    self.naming.convention.label()
+                ));
+            }
+        }
+        if self.validation_command.trim().is_empty() {
+            return Ok(());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            #[derive(serde::Serialize)]
+            struct PlannedRename<'a> {
+                source: &'a Path,
+                rename: String,
+            }
+            let planned: Vec<PlannedRename<'_>> = self
+                .iter_renames(include_failed)
+                .into_iter()
+                .map(|(source, rename, _)| PlannedRename {
+                    source,
+                    rename: rename.to_string_lossy().into_owned(),
+                })
+                .collect();
+            let json = serde_json::to_string(&planned).map_err(|e| e.to_string())?;
+            return hooks::check(&self.validation_command, &json)
+                .map_err(|reason| format!("Cancelled by the validation command: {reason}"));
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = include_failed;
+            return Ok(());
+        }
+    }
+
+    fn choice_table(&mut self, ui: &mut Ui) {
+        let usage = self.choice_usage();
+        let mut order: Vec<usize> = (0..usage.len()).collect();
+        // Over-subscribed choices first, they are the likely mistakes
+        order.sort_by(|a, b| {
+            usage[*b].len().cmp(&usage[*a].len()).then_with(|| {
+                self.search.choice_names[*a]
+                    .file
+                    .name
+                    .cmp(&self.search.choice_names[*b].file.name)
+            })
+        });
+
+        let mut regroup = false;
+        TableBuilder::new(ui)
+            .striped(true)
+            .auto_shrink([false; 2])
+            .column(
+                Column::remainder()
+                    .clip(true)
+                    .at_least(100.0)
+                    .resizable(true),
+            )
+            .column(
+                Column::initial(100.0)
+                    .at_least(60.0)
+                    .resizable(true)
+                    .clip(true),
+            )
+            .column(Column::initial(60.0).range(35.0..=60.0).clip(true))
+            .column(Column::remainder().clip(true).at_least(100.0))
+            .header(20.0, |mut header| {
+                header.col(|ui| {
+                    ui.label("Choice Name");
+                });
+                header.col(|ui| {
+                    ui.label("Group");
+                });
+                header.col(|ui| {
+                    ui.label("Used");
+                });
+                header.col(|ui| {
+                    ui.label("Matched Sources");
+                });
+            })
+            .body(|body| {
+                body.rows(20.0, order.len(), |row_index, mut row| {
+                    let choice_index = order[row_index];
+                    let sources = &usage[choice_index];
+
+                    row.col(|ui| {
+                        let file = &self.search.choice_names[choice_index].file;
+                        hover_file_details(ui.label(file.name.as_str()), file);
+                    });
+                    row.col(|ui| {
+                        let group = &mut self.search.choice_names[choice_index].group;
+                        if ui.text_edit_singleline(group).lost_focus() {
+                            regroup = true;
+                        }
+                    });
+                    row.col(|ui| {
+                        let count = format!("{}x", sources.len());
+                        if sources.len() > 1 {
+                            ui.colored_label(self.palette.warning(), count);
+                        } else {
+                            ui.weak(count);
+                        }
+                    });
+                    row.col(|ui| {
+                        let names: Vec<&str> = sources
+                            .iter()
+                            .map(|s| self.search.source_names[*s].file.name.as_str())
+                            .collect();
+                        ui.label(names.join(", "));
+                    });
+                });
+            });
+
+        if regroup {
+            self.search.update_all();
+            self.edits.mark();
+        }
+    }
+
+    fn status_counts(&self) -> StatusCounts {
+        let mut counts = StatusCounts {
+            sources: self.search.source_names.len(),
+            choices: self.search.choice_names.len(),
+            matched: 0,
+            pending: 0,
+        };
+        for source in &self.search.source_names {
+            match self.confidence(source) {
+                report::Confidence::NeedsReview => counts.pending += 1,
+                report::Confidence::Trusted => counts.matched += 1,
+                report::Confidence::Manual if self.accepted_choice(source).is_some() => {
+                    counts.matched += 1
+                }
+                report::Confidence::Manual | report::Confidence::Unmatched => {}
+            }
+        }
+        counts
+    }
+
+    /// Choices left over: not excluded, and no source is matched to them
+    fn unmatched_choices(&self) -> Vec<usize> {
+        let usage = self.choice_usage();
+        (0..usage.len())
+            .filter(|c| usage[*c].is_empty() && !self.search.choice_names[*c].excluded)
+            .collect()
+    }
+
+    /// Key of the lists, scores and settings output names are worked out from, to notice when
+    /// what was worked out from them is out of date
+    fn output_key(&self) -> u64 {
+        let settings = (
+            self.keep_extension,
+            self.relocate_articles,
+            &self.name_template,
+            self.number_start,
+            &self.number_order,
+            &self.source_grouping,
+            &self.group_rules,
+            &self.name_prefix,
+            &self.name_suffix,
+            &self.replace_rules,
+            &self.side_to_copy,
+            self.copy_failed_sources,
+            self.threshold,
+            self.trust_threshold,
+            self.exclude_unreviewed,
+            &self.search.options,
+        );
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(&settings)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        self.edits.count().hash(&mut hasher);
+        self.search.revision.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Works out what the table and panels show again if the lists, scores or settings changed,
+    /// or other parts of it are shown
+    fn update_output_cache(&mut self) {
+        let shown = (
+            self.panels.preview,
+            self.panels.unmatched,
+            self.columns.collision,
+            self.columns.usage,
+        );
+        let mut hasher = DefaultHasher::new();
+        (self.output_key(), shown).hash(&mut hasher);
+        let key = hasher.finish();
+        if self.output_cache.key == Some(key) {
+            return;
+        }
+        let renames = if self.panels.preview {
+            self.iter_renames(self.copy_failed_sources)
+                .into_iter()
+                .map(|(path, name, destination)| {
+                    (path.clone(), name, destination.map(Path::to_owned))
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        self.output_cache = OutputCache {
+            key: Some(key),
+            numbers: self.numbering(),
+            collisions: if self.columns.collision {
+                self.output_collisions()
+            } else {
+                vec![]
+            },
+            usage: if self.columns.usage {
+                self.match_usage()
+            } else {
+                vec![]
+            },
+            renames,
+            unmatched: if self.panels.unmatched {
+                self.unmatched_choices()
+            } else {
+                vec![]
+            },
+        };
+    }
+
+    fn unmatched_panel(&self, ui: &mut Ui) {
+        let unmatched = &self.output_cache.unmatched;
+        ui.heading("Unmatched");
+        ui.weak(format!(
+            "{} of {} choice(s)",
+            unmatched.len(),
+            self.search.choice_names.len()
+        ));
+        ui.separator();
+        ScrollArea::vertical().auto_shrink([false; 2]).show_rows(
+            ui,
+            18.0,
+            unmatched.len(),
+            |ui, rows| {
+                for choice in &unmatched[rows] {
+                    let file = &self.search.choice_names[*choice].file;
+                    hover_file_details(ui.label(file.name.as_str()), file);
+                }
+            },
+        );
+    }
+
+    fn preview_panel(&self, ui: &mut Ui) {
+        let renames = &self.output_cache.renames;
+        ui.horizontal(|ui| {
+            ui.strong("Preview");
+            ui.weak(format!("{} file(s)", renames.len()));
+        });
+        ScrollArea::both()
+            .id_source("preview_scroll")
+            .auto_shrink([false; 2])
+            .show_rows(ui, 18.0, renames.len(), |ui, rows| {
+                for (path, name, destination) in &renames[rows] {
+                    ui.horizontal(|ui| {
+                        let old_name = path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string();
+                        ui.label(old_name).on_hover_text(path.display().to_string());
+                        ui.weak("\u{2192}");
+                        let new_name = ui.label(name.to_string_lossy().to_string());
+                        if let Some(destination) = destination {
+                            new_name.on_hover_text(destination.display().to_string());
+                        }
+                    });
+                }
+            });
+    }
+
+    /// Adds the status to the log once it changed
+    fn log_status(&mut self) {
+        if self.status == self.logged_status {
+            return;
+        }
+        self.logged_status = self.status.clone();
+        if self.status != AppStatus::None {
+            self.log.push(self.status.clone());
+            let excess = self.log.len().saturating_sub(LOG_LENGTH);
+            self.log.drain(..excess);
+        }
+    }
+
+    fn log_panel(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.strong("Log");
+            if ui.button("Clear").clicked() {
+                self.log.clear();
+            }
+        });
+        ScrollArea::vertical()
+            .id_source("log_scroll")
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                // Newest first
+                for status in self.log.iter().rev() {
+                    match status {
+                        AppStatus::None => {}
+                        AppStatus::Info(message) => {
+                            ui.weak(message);
+                        }
+                        AppStatus::Notice(message) => {
+                            ui.strong(message);
+                        }
+                    }
+                }
+            });
+    }
+}
+
+impl eframe::App for MainApp {
+    /// Called by the frame work to save state before shutdown.
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
+        let session = if self.keep_session {
+            self.search.to_session().to_json().unwrap_or_default()
+        } else {
+            String::new()
+        };
+        storage.set_string(SESSION_KEY, session);
+    }
+
+    /// Holds the window open while there is unsaved work, see `confirm_close`
+    fn on_close_event(&mut self) -> bool {
+        if self.edits.unsaved() && !self.allow_close {
+            self.confirm_close = true;
+            return false;
+        }
+        true
+    }
+
+    /// Called each time the UI needs repainting, which may be many times per second.
+    /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
+    fn update(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.handle_automation();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_folder_import(ctx);
+        self.poll_rescore(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_folder_check();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.read_checksums(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_rename(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_copy(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_post_command();
+        self.poll_dialog(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.read_exif(ctx);
+        #[cfg(target_arch = "wasm32")]
+        self.read_exif_now();
+
+        if ctx
+            .input_mut()
+            .consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::B)
+        {
+            self.show_benchmark = !self.show_benchmark;
+        }
+
+        // Text fields keep their own undo
+        let editing_text = ctx.memory().focus().is_some();
+        if !editing_text
+            && (ctx
+                .input_mut()
+                .consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z)
+                || ctx.input_mut().consume_key(Modifiers::COMMAND, Key::Y))
+        {
+            self.redo();
+        }
+        if !editing_text && ctx.input_mut().consume_key(Modifiers::COMMAND, Key::Z) {
+            self.undo();
+        }
+
+        if ctx.input_mut().consume_key(Modifiers::COMMAND, Key::F) {
+            let find = self.find.get_or_insert_with(Default::default);
+            find.focus = true;
+        }
+
+        // Menu bar
+
+        // Where the Sources, Choices and Output menus are, for the walkthrough
+        let mut menu_rects = [Rect::NOTHING; 3];
+        TopBottomPanel::top("top_panel").show(ctx, |ui| {
+
+            // Files bar
+
+            menu::bar(ui, |ui| {
+
+                // Session
+
+                ui.menu_button("Session", |ui| {
+                    ui.weak("Sources, choices and picked matches");
+                    ui.separator();
+
+                    ui.checkbox(&mut self.keep_session, "Restore on startup")
+                        .on_hover_text("Keep the current session with the settings, instead of starting empty");
+                    ui.separator();
+
+                    ui.add_enabled_ui(self.history.can_undo(), |ui| {
+                        if ui.button("Undo").on_hover_text("Ctrl+Z").clicked() {
+                            self.undo();
+                        }
+                    });
+                    ui.add_enabled_ui(self.history.can_redo(), |ui| {
+                        if ui.button("Redo").on_hover_text("Ctrl+Y").clicked() {
+                            self.redo();
+                        }
+                    });
+                    ui.separator();
+
+                    ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")), |ui| {
+                        if ui.button("Open session").on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
+                            self.open_session_dialog(ctx);
+                        }
+                        if ui.button("Save session").on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
+                            self.save_session_dialog(ctx, false);
+                        }
+                        ui.separator();
+                        if ui
+                            .button("Save as job template")
+                            .on_hover_text("Store the settings and source and choice folders, to repeat this rename later")
+                            .on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP)
+                            .clicked()
+                        {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            self.save_job_dialog(ctx);
+                            ui.close_menu();
+                        }
+                        if ui
+                            .button("Run job")
+                            .on_hover_text("Scan a job's folders again and run its output steps")
+                            .on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP)
+                            .clicked()
+                        {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            self.run_job_dialog(ctx);
+                            ui.close_menu();
+                        }
+                    });
+                });
+
+                // Sources
+
+                menu_rects[0] = ui.menu_button("Sources", |ui| {
+                    ui.weak("Base names to match to");
+                    ui.weak("All files will be used");
+                    ui.separator();
+
+                    let count = self.search.source_names.len();
+                    if count > 0 {
+                        ui.radio_value(&mut self.import_mode, ImportMode::Append, format!("Add to the {count} source(s)"));
+                        ui.radio_value(&mut self.import_mode, ImportMode::Replace, "Replace them")
+                            .on_hover_text("Start the list over with the imported files, keeping picked matches of files imported again");
+                    }
+
+                    ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")), |ui| {
+                        if ui.button("Import folder").on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
+                            let request = pick_folder(&self.sources_path, "Choose a folder with source files");
+                            self.ask_path(ctx, request, |app, folder, ctx| {
+                                app.sources_path = folder.to_string_lossy().into_owned();
+                                app.start_folder_import(folder, SideToUse::Sources, ctx);
+                            });
+                        }
+                    });
+
+                    if ui.button("Import files").clicked() {
+                        let request = pick_files(&self.sources_path, "Choose source files");
+                        self.ask_paths(ctx, request, |app, files, _| {
+                            if let Some(file) = files.first() {
+                                app.sources_path = paths::dialog_folder(file);
+                            }
+
+                            let replaced = app.replace_for_import(SideToUse::Sources);
+                            let mut known = app.search.source_paths();
+                            let index = ChoiceIndex::new(&app.search.choice_names);
+                            let mut summary = ImportSummary::new(Path::new(&app.sources_path));
+                            for item in files {
+                                if app.accepts_source(&item) {
+                                    let outcome = app.search.add_source(item.clone(), &index, &mut known);
+                                    summary.record(item, outcome);
+                                }
+                            }
+                            if let Some(replaced) = replaced {
+                                app.search.restore_replaced(replaced, false);
+                            }
+                            app.edits.mark();
+                            app.status = AppStatus::Info(summary.message("source(s)"));
+                            app.show_import_summary(summary);
+                        });
+                    }
+
+                    ui.menu_button("Group sources", |ui| {
+                        ui.radio_value(&mut self.source_grouping, SourceGrouping::None, "Don't group");
+                        ui.radio_value(&mut self.source_grouping, SourceGrouping::Folder, "By folder");
+                        ui.radio_value(&mut self.source_grouping, SourceGrouping::Extension, "By extension");
+
+                        if self.source_grouping != SourceGrouping::None {
+                            ui.separator();
+                            ui.weak("Settings for each group");
+                            for group in self.source_groups() {
+                                let label = if group.is_empty() { "[None]".to_owned() } else { group.clone() };
+                                ui.menu_button(label, |ui| {
+                                    let index = match self.group_rules.iter().position(|r| r.group == group) {
+                                        Some(index) => index,
+                                        None => {
+                                            self.group_rules.push(GroupRule { group, ..Default::default() });
+                                            self.group_rules.len() - 1
+                                        }
+                                    };
+                                    let rule = &mut self.group_rules[index];
+
+                                    let mut own_threshold = rule.threshold.is_some();
+                                    if ui.checkbox(&mut own_threshold, "Own similarity threshold").changed() {
+                                        rule.threshold = own_threshold.then_some(self.threshold);
+                                    }
+                                    if let Some(threshold) = &mut rule.threshold {
+                                        ui.add(Slider::new(threshold, 0.0..=1.0).text("Similarity"));
+                                    }
+
+                                    let mut own_template = rule.name_template.is_some();
+                                    if ui.checkbox(&mut own_template, "Own name template").changed() {
+                                        rule.name_template = own_template.then(|| self.name_template.clone());
+                                    }
+                                    if let Some(name_template) = &mut rule.name_template {
+                                        ui.text_edit_singleline(name_template);
+                                    }
+                                });
+                            }
+                        }
+                    });
+
+                    let groups = self.search.choice_groups();
+                    if !groups.is_empty() {
+                        ui.menu_button("Match within group", |ui| {
+                            let mut group = self.search.source_names.first().and_then(|s| s.choice_group.clone());
+                            let mut changed = ui.radio_value(&mut group, None, "[All choices]").changed();
+                            for name in groups {
+                                changed |= ui.radio_value(&mut group, Some(name.clone()), name).changed();
+                            }
+                            if changed {
+                                for source in &mut self.search.source_names {
+                                    source.choice_group = group.clone();
+                                }
+                                self.rescore();
+                                self.edits.mark();
+                            }
+                        });
+                    }
+
+                    let kinds = self.search.choice_kinds();
+                    if kinds.len() > 1 || !self.search.options.candidate_kind.is_empty() {
+                        ui.menu_button("Match only kind", |ui| {
+                            let kind = &mut self.search.options.candidate_kind;
+                            let mut changed = ui.radio_value(kind, String::new(), "[Any kind]").changed();
+                            for name in kinds {
+                                changed |= ui.radio_value(kind, name.clone(), name).changed();
+                            }
+                            if changed {
+                                self.rescore();
+                                self.edits.mark();
+                            }
+                        })
+                        .response
+                        .on_hover_text("Only choices of this kind of file are candidates, e.g. videos and not their subtitles");
+                    }
+
+                    ui.separator();
+
+                    let removed_count = self.search.removed_sources.len();
+                    ui.add_enabled_ui(removed_count > 0, |ui| {
+                        ui.menu_button(format!("Removed sources ({removed_count})"), |ui| {
+                            let mut restore = None;
+                            ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                                for (index, source) in self.search.removed_sources.iter().enumerate().rev() {
+                                    ui.horizontal(|ui| {
+                                        if ui.small_button("Restore").clicked() {
+                                            restore = Some(index);
+                                        }
+                                        ui.label(source.file.name.as_str());
+                                    });
+                                }
+                            });
+                            if let Some(index) = restore {
+                                self.search.restore_source(index);
+                                self.edits.mark();
+                                self.status = AppStatus::Info("Restored 1 source".to_owned());
+                            }
+                            ui.separator();
+                            if ui.button("Restore all").clicked() {
+                                while !self.search.removed_sources.is_empty() {
+                                    self.search.restore_source(0);
+                                }
+                                self.edits.mark();
+                                self.status = AppStatus::Info(format!("Restored {removed_count} source(s)"));
+                                ui.close_menu();
+                            }
+                            if ui.button("Forget removed sources").clicked() {
+                                self.search.removed_sources.clear();
+                                ui.close_menu();
+                            }
+                        });
+                    });
+
+                    if ui.button("Clear all sources...").clicked() {
+                        self.confirmation = Some(self.clear_sources_confirmation());
+                        ui.close_menu();
+                    }
+                }).response.rect;
+
+                // Choices
+
+                menu_rects[1] = ui.menu_button("Choices", |ui| {
+                    ui.weak("References to match with");
+                    ui.weak("Matched files will be used");
+                    ui.separator();
+
+                    let count = self.search.choice_names.len();
+                    if count > 0 {
+                        ui.radio_value(&mut self.import_mode, ImportMode::Append, format!("Add to the {count} reference(s)"));
+                        ui.radio_value(&mut self.import_mode, ImportMode::Replace, "Replace them")
+                            .on_hover_text("Start the list over with the imported files, keeping matches picked to files imported again");
+                    }
+
+                    ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")), |ui| {
+                        if ui.button("Import folder").on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
+                            let request = pick_folder(&self.choices_path, "Choose a folder with reference files");
+                            self.ask_path(ctx, request, |app, folder, ctx| {
+                                app.choices_path = folder.to_string_lossy().into_owned();
+                                app.start_folder_import(folder, SideToUse::Choices, ctx);
+                            });
+                        }
+                    });
+
+                    if ui.button("Import files").clicked() {
+                        let request = pick_files(&self.choices_path, "Choose reference files");
+                        self.ask_paths(ctx, request, |app, files, _| {
+                            if !files.is_empty() {
+                                if let Some(file) = files.first() {
+                                    app.choices_path = paths::dialog_folder(file);
+                                }
+
+                                let replaced = app.replace_for_import(SideToUse::Choices);
+                                let first = app.search.choice_names.len();
+                                let group = files[0].parent().map_or("".to_owned(), batch_name);
+                                let mut known = app.search.choice_paths();
+                                let mut summary = ImportSummary::new(Path::new(&app.choices_path));
+                                for item in files {
+                                    let outcome = app.search.add_choice(item.clone(), &mut known);
+                                    summary.record(item, outcome);
+                                }
+                                app.search.group_choices_from(first, &group);
+                                match replaced {
+                                    Some(replaced) => app.search.restore_replaced(replaced, false),
+                                    None => app.search.update_all(),
+                                }
+                                app.edits.mark();
+                                app.status = AppStatus::Info(summary.message("reference(s)"));
+                                app.show_import_summary(summary);
+                            }
+                        });
+                    }
+
+                    if ui
+                        .button("Import name list")
+                        .on_hover_text("Use the names in a playlist (M3U, XSPF), Logiqx DAT, XML or JSON list, or text file with one name per line as choices")
+                        .clicked()
+                    {
+                        let request = pick_file(
+                            &self.choices_path,
+                            "Choose a list of names",
+                            "Name list",
+                            &["m3u", "m3u8", "xspf", "dat", "xml", "json", "txt"],
+                        );
+                        self.ask_path(ctx, request, |app, file, _| app.import_list(&file, import::ListKind::Names));
+                    }
+
+                    if self.mode == MatchMode::RomSet && ui.button("Import DAT file").clicked() {
+                        let request = pick_file(
+                            &self.choices_path,
+                            "Choose a Logiqx DAT file",
+                            "DAT",
+                            &["dat", "xml"],
+                        );
+                        self.ask_path(ctx, request, |app, file, _| app.import_list(&file, import::ListKind::Dat));
+                    }
+
+                    if ui
+                        .button("Import previous mapping")
+                        .on_hover_text("Use the new names from an exported mapping CSV as choices")
+                        .clicked()
+                    {
+                        let request = pick_file(&self.choices_path, "Choose an exported mapping CSV", "CSV", &["csv"]);
+                        self.ask_path(ctx, request, |app, file, _| app.import_list(&file, import::ListKind::Mapping));
+                    }
+
+                    if self.mode == MatchMode::CsvMapping {
+                        if ui.button("Import CSV mapping").clicked() {
+                            let request = pick_file(
+                                &self.choices_path,
+                                "Choose a CSV file (current name, new name)",
+                                "CSV",
+                                &["csv", "tsv", "txt"],
+                            );
+                            self.ask_path(ctx, request, |app, file, _| {
+                                let kind = import::ListKind::Csv { has_header: app.csv_has_header };
+                                app.import_list(&file, kind);
+                            });
+                        }
+                        ui.checkbox(&mut self.csv_has_header, "First row is a header");
+                    }
+
+                    ui.separator();
+
+                    // TODO: Open Window dialog with checked list
+                    // if ui.button("Manage references...").clicked() { }
+
+                    let excluded_count = self.search.choice_names.iter().filter(|c| c.excluded).count();
+                    ui.add_enabled_ui(excluded_count > 0, |ui| {
+                        ui.menu_button(format!("Excluded choices ({excluded_count})"), |ui| {
+                            let mut allowed = None;
+                            ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                                for (index, choice) in self.search.choice_names.iter_mut().enumerate().filter(|(_, c)| c.excluded) {
+                                    ui.horizontal(|ui| {
+                                        if ui.small_button("Allow").clicked() {
+                                            choice.excluded = false;
+                                            allowed = Some(index);
+                                        }
+                                        ui.label(choice.file.name.as_str());
+                                    });
+                                }
+                            });
+                            if let Some(choice) = allowed {
+                                self.search.offer_choice(choice);
+                                self.edits.mark();
+                            }
+                        });
+                    });
+
+                    if ui.button("Clear all references...").clicked() {
+                        self.confirmation = Some(self.clear_choices_confirmation());
+                        ui.close_menu();
+                    }
+                }).response.rect;
+
+                // Renames
+
+                menu_rects[2] = ui.menu_button("Output", |ui| {
+                    ui.weak("Results of fuzzy rename");
+                    ui.weak("Files are copied to output");
+
+                    ui.separator();
+
+                    ui.menu_button("Media server presets", |ui| {
+                        ui.weak("Name template and naming convention");
+                        for preset in &OUTPUT_PRESETS {
+                            if ui.button(preset.label).on_hover_text(preset.example).clicked() {
+                                self.apply_preset(preset);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    ui.toggle_value(&mut self.keep_extension, "Keep extensions");
+                    ui.toggle_value(&mut self.relocate_articles, "Articles at the end")
+                        .on_hover_text("Name \"The Matrix\" as \"Matrix, The\", as libraries often sort them");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.tag_titles, "Tag titles:")
+                            .on_hover_text("Write the title tag of MP3 and FLAC files after copying or renaming them, so players show the new name");
+                        ui.add_enabled(self.tag_titles, TextEdit::singleline(&mut self.tag_template).desired_width(120.0))
+                            .on_hover_text("Tokens: {name} matched name, {source}, {choice}");
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut self.name_template).on_hover_text(
+                            "Tokens: {name} matched name, {source}, {choice}, {n} or {n:03} sequence number, {lang} language tags of the source like .en.forced, {title} and {year} of a name like \"Title (1999)\", {show}, {season} and {episode} of a name like \"Show S01E02\", {exif_date} and {camera} of source photos",
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Prefix:");
+                        ui.add(TextEdit::singleline(&mut self.name_prefix).desired_width(80.0));
+                        ui.label("Suffix:");
+                        ui.add(TextEdit::singleline(&mut self.name_suffix).desired_width(80.0))
+                            .on_hover_text("Added before the extension");
+                    });
+                    ui.menu_button(format!("Find and replace ({})", self.replace_rules.len()), |ui| {
+                        ui.weak("Applied to output names only");
+                        let mut remove = None;
+                        for (index, rule) in self.replace_rules.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                let mut changed = ui
+                                    .add(TextEdit::singleline(&mut rule.find).hint_text("Find").desired_width(100.0))
+                                    .changed();
+                                ui.label("->");
+                                ui.add(TextEdit::singleline(&mut rule.replace).hint_text("Replace").desired_width(100.0));
+                                changed |= ui
+                                    .checkbox(&mut rule.regex, "Regex")
+                                    .on_hover_text("Use $1, $2... to insert captured groups")
+                                    .changed();
+                                if changed {
+                                    rule.compile();
+                                }
+                                if rule.is_invalid() {
+                                    ui.colored_label(self.palette.error(), "Invalid pattern");
+                                }
+                                if ui.small_button("x").clicked() {
+                                    remove = Some(index);
+                                }
+                            });
+                        }
+                        if let Some(index) = remove {
+                            self.replace_rules.remove(index);
+                        }
+                        if ui.button("Add rule").clicked() {
+                            self.replace_rules.push(ReplaceRule::default());
+                        }
+                    });
+                    ui.menu_button(format!("Naming convention ({})", self.naming.convention.label()), |ui| {
+                        ui.weak("Names breaking it are flagged in the table");
+                        for convention in Convention::ALL {
+                            if ui.radio(self.naming.convention == convention, convention.label()).clicked() {
+                                self.naming.set_convention(convention);
+                            }
+                        }
+                        ui.add_enabled_ui(self.naming.is_enabled(), |ui| {
+                            ui.separator();
+                            let naming = &mut self.naming;
+                            let mut changed = false;
+                            ui.horizontal(|ui| {
+                                ui.label("Pattern:");
+                                changed |= ui
+                                    .add(TextEdit::singleline(&mut naming.pattern).desired_width(200.0))
+                                    .on_hover_text("Regex every name must contain a match of, empty for any name")
+                                    .changed();
+                            });
+                            if naming.is_invalid() {
+                                ui.colored_label(self.palette.error(), "Invalid pattern");
+                            }
+                            changed |= ui
+                                .add(DragValue::new(&mut naming.max_length).prefix("Max length ").clamp_range(0..=1000))
+                                .on_hover_text("In characters, extension included; 0 for no limit")
+                                .changed();
+                            ui.horizontal(|ui| {
+                                ui.label("Forbidden:");
+                                changed |= ui.add(TextEdit::singleline(&mut naming.forbidden).desired_width(80.0)).changed();
+                            });
+                            changed |= ui.checkbox(&mut naming.ascii_only, "ASCII only").changed();
+                            if changed {
+                                naming.convention = Convention::Custom;
+                                naming.compile();
+                            }
+                            ui.checkbox(&mut naming.block_output, "Cancel output while names break it");
+                        });
+                    });
+                    if self.name_template.contains("{n") {
+                        ui.horizontal(|ui| {
+                            ui.add(DragValue::new(&mut self.number_start).prefix("Start at "));
+                            ComboBox::from_id_source("number_order")
+                                .selected_text(match self.number_order {
+                                    NumberOrder::Match => "By match",
+                                    NumberOrder::Name => "By name",
+                                    NumberOrder::Date => "By date",
+                                })
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut self.number_order, NumberOrder::Match, "By match");
+                                    ui.selectable_value(&mut self.number_order, NumberOrder::Name, "By name");
+                                    ui.selectable_value(&mut self.number_order, NumberOrder::Date, "By date");
+                                });
+                        });
+                    }
+
+                    ui.weak("Files to copy:");
+                    ui.add_enabled_ui(self.mode == MatchMode::Files, |ui| {
+                        ui.radio_value(&mut self.side_to_copy, SideToUse::Choices, "Rename Choices");
+                        ui.radio_value(&mut self.side_to_copy, SideToUse::Sources, "Rename Sources");
+                    });
+
+                    ui.separator();
+
+                    ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")), |ui| {
+                        if ui.button("Copy results to folder").on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
+                            let request = pick_folder(&self.renames_path, "Choose a folder to copy renamed files to");
+                            self.ask_path(ctx, request, |app, folder, ctx| {
+                                app.renames_path = folder.to_string_lossy().into_owned();
+
+                                let output = folder.clone();
+                                app.confirm_overlaps(ctx, &[output.as_path()], move |app, ctx| {
+                                    match app.validate_renames(app.copy_failed_sources) {
+                                        Ok(()) => app.start_copy(owned_copies(app.copies_to(&folder)), Some(folder), ctx),
+                                        Err(reason) => app.status = AppStatus::Notice(reason),
+                                    }
+                                });
+                            });
+                        }
+
+                        if ui.button("Copy next to matches").on_hover_text("Copy each renamed file into the folder of the file it matched").on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
+                            self.confirm_overlaps(ctx, &[], |app, ctx| match app.validate_renames(false) {
+                                Ok(()) => app.start_copy(owned_copies(app.renames_beside_matches()), None, ctx),
+                                Err(reason) => app.status = AppStatus::Notice(reason),
+                            });
+                        }
+
+                        if ui.button("Export report").on_hover_text("Save the proposed renames as an HTML page").on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
+                            let request = save_file(&self.renames_path, "Save rename report", "rename_report.html");
+                            self.ask_path(ctx, request, |app, file, _| {
+                                match fs::write(&file, app.report_html()) {
+                                    Ok(()) => {
+                                        app.status = AppStatus::Info("Exported report".to_owned());
+                                    }
+                                    Err(error) => {
+                                        app.status = AppStatus::Notice(format!("Could not write report: {error}"));
+                                    }
+                                }
+                            });
+                        }
+
+                        ui.horizontal(|ui| {
+                            let hint = "Save the score of every source against every choice as CSV, for tuning thresholds elsewhere";
+                            let export_size = self.matrix_export_size();
+                            if export_size < LARGE_EXPORT_BYTES {
+                                if ui.button("Export similarity matrix").on_hover_text(hint).on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
+                                    self.export_matrix_dialog(ctx);
+                                }
+                            } else {
+                                ui.menu_button("Export similarity matrix", |ui| {
+                                    ui.colored_label(
+                                        self.palette.warning(),
+                                        format!("About {} of scores, set Top to keep fewer per source", format_size(export_size as u64)),
+                                    );
+                                    if ui.button("Export anyway").clicked() {
+                                        self.export_matrix_dialog(ctx);
+                                        ui.close_menu();
+                                    }
+                                })
+                                .response
+                                .on_hover_text(hint);
+                            }
+                            ui.add(DragValue::new(&mut self.matrix_top_k).prefix("Top ").clamp_range(0..=1000))
+                                .on_hover_text("Choices kept per source, 0 for all");
+                        });
+                    });
+
+                    ui.menu_button(format!("Output profile ({})", self.output_targets.len()), |ui| {
+                        ui.weak("Steps run together by \"Execute all\"");
+                        let (mut remove, mut browse) = (None, None);
+                        for (index, target) in self.output_targets.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut target.enabled, "");
+                                ComboBox::from_id_source(("output_action", index))
+                                    .selected_text(target.action.label())
+                                    .show_ui(ui, |ui| {
+                                        for action in OutputAction::ALL {
+                                            ui.selectable_value(&mut target.action, action, action.label());
+                                        }
+                                    });
+                                let needs_path = target.action != OutputAction::CopyBesideMatch;
+                                ui.add_enabled(needs_path, TextEdit::singleline(&mut target.path).hint_text("Path").desired_width(200.0));
+                                ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")) && needs_path, |ui| {
+                                    if ui.small_button("...").clicked() {
+                                        browse = Some((index, match target.action {
+                                            OutputAction::CopyToFolder | OutputAction::CopyBesideMatch => pick_folder(&target.path, "Choose a folder to copy renamed files to"),
+                                            action => save_file(&self.renames_path, action.label(), action.default_file_name()),
+                                        }));
+                                    }
+                                });
+                                if ui.small_button("x").clicked() {
+                                    remove = Some(index);
+                                }
+                            });
+                        }
+                        if let Some((index, request)) = browse {
+                            self.ask_path(ctx, request, move |app, picked, _| {
+                                if let Some(target) = app.output_targets.get_mut(index) {
+                                    target.path = picked.to_string_lossy().into_owned();
+                                }
+                            });
+                        }
+                        if let Some(index) = remove {
+                            self.output_targets.remove(index);
+                        }
+                        if ui.button("Add step").clicked() {
+                            self.output_targets.push(OutputTarget {
+                                action: OutputAction::CopyToFolder,
+                                path: self.renames_path.clone(),
+                                enabled: true,
+                            });
+                        }
+                    });
+                    ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Before output:");
+                            ui.add(TextEdit::singleline(&mut self.validation_command).hint_text("Validation command").desired_width(200.0))
+                                .on_hover_text("Receives the planned renames as JSON on stdin before files are copied or renamed.\nA failing exit code cancels them")
+                                .on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP);
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("After output:");
+                            ui.add(TextEdit::singleline(&mut self.post_run_command).hint_text("Command").desired_width(200.0))
+                                .on_hover_text("Run when copies or renames finish without failures.\n{output} is the output folder and {report} the HTML report of the output profile")
+                                .on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP);
+                        });
+                    });
+                    ui.add_enabled_ui(
+                        cfg!(not(target_arch = "wasm32")) && self.output_targets.iter().any(|t| t.enabled),
+                        |ui| {
+                            if ui.button("Execute all").on_disabled_hover_text("Add steps to the output profile first").clicked() {
+                                self.run_outputs(ctx);
+                            }
+                        },
+                    );
+
+                    if self.side_to_copy == SideToUse::Sources {
+                        ui.toggle_value(
+                            &mut self.copy_failed_sources,
+                            "Include missing results",
+                        );
+
+                        ui.separator();
+
+                        // TODO: Add output compatible with WASM
+
+                        ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")), |ui| {
+                            if ui.button("Directly rename files...").on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
+                                self.confirmation = Some(self.rename_confirmation());
+                                ui.close_menu();
+                            }
+                        });
+                    }
+                }).response.rect;
+
+                ui.separator();
+
+                // Options
+
+                ui.menu_button("Options", |ui| {
+                    ui.weak("Mode:");
+                    if ui.radio(self.mode == MatchMode::Files, "Files").clicked() {
+                        self.set_mode(MatchMode::Files);
+                    }
+                    if ui
+                        .radio(self.mode == MatchMode::RomSet, "ROM set")
+                        .on_hover_text("Rename .zip archives to match a DAT file")
+                        .clicked()
+                    {
+                        self.set_mode(MatchMode::RomSet);
+                    }
+                    if ui
+                        .radio(self.mode == MatchMode::CsvMapping, "CSV mapping")
+                        .on_hover_text("Rename files using a two-column CSV of current and new names")
+                        .clicked()
+                    {
+                        self.set_mode(MatchMode::CsvMapping);
+                    }
+                    if ui
+                        .radio(self.mode == MatchMode::Subtitles, "Subtitles")
+                        .on_hover_text("Name subtitle files after the videos they belong to, keeping language tags like .en.forced, and copy them next to the videos")
+                        .clicked()
+                    {
+                        self.set_mode(MatchMode::Subtitles);
+                    }
+
+                    ui.menu_button("Import from folders", |ui| {
+                        ui.radio_value(&mut self.import_entries, ImportEntries::Files, "Files");
+                        ui.radio_value(&mut self.import_entries, ImportEntries::Folders, "Subfolders")
+                            .on_hover_text("Match and rename folders, e.g. albums or game directories. Copies include their contents");
+                        ui.radio_value(&mut self.import_entries, ImportEntries::Both, "Files and subfolders")
+                            .on_hover_text("Show the Type column under View > Columns to tell them apart");
+                    });
+
+                    ui.menu_button("Symbolic links", |ui| {
+                        ui.weak("In imported folders");
+                        ui.radio_value(&mut self.symlinks, SymlinkHandling::Skip, "Skip");
+                        ui.radio_value(&mut self.symlinks, SymlinkHandling::Follow, "Use link name")
+                            .on_hover_text("Renaming directly renames the link, not the file it points to");
+                        ui.radio_value(&mut self.symlinks, SymlinkHandling::TargetName, "Use target name")
+                            .on_hover_text("Match by the name of the file the link points to");
+                    });
+
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        ui.add(Slider::new(&mut self.threshold, 0.0..=1.0).text("Similarity"));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            Slider::new(&mut self.trust_threshold, self.threshold..=1.0)
+                                .text("Trusted"),
+                        )
+                        .on_hover_text("Matches between the two thresholds are marked for review");
+                    });
+                    ui.checkbox(&mut self.exclude_unreviewed, "Skip unreviewed matches in output");
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            Slider::new(&mut self.ambiguity_margin, 0.0..=0.2)
+                                .text("Ambiguity margin"),
+                        )
+                        .on_hover_text("Flag matches that barely beat the next candidate");
+                    });
+
+                    ui.weak("Search Algorithm:");
+                    let mut changed = false;
+                    for algorithm in SearchAlgorithm::ALL {
+                        let mut response =
+                            ui.radio_value(&mut self.search.options.algorithm, algorithm, algorithm.label());
+                        if let Some(hint) = algorithm.hint() {
+                            response = response.on_hover_text(hint);
+                        }
+                        changed |= response.changed();
+                    }
+                    if changed {
+                        self.rescore();
+                        self.status = AppStatus::Info("Updated search algorithm".to_owned());
+                    }
+
+                    ui.menu_button("Jaro Winkler prefix", |ui| {
+                        ui.weak("Bonus for names starting the same way");
+                        let mut changed = ui
+                            .add(Slider::new(&mut self.search.options.prefix_scale, 0.0..=0.25).text("Scale"))
+                            .on_hover_text("Bonus per shared leading character, 0.1 by default")
+                            .changed();
+                        changed |= ui
+                            .add(DragValue::new(&mut self.search.options.max_prefix).prefix("Max length ").clamp_range(0..=100))
+                            .on_hover_text("Leading characters counted, 0 for no limit. Lower it when every name starts with the same series name")
+                            .changed();
+                        if changed {
+                            self.rescore();
+                        }
+                    });
+
+                    ui.menu_button("Strip shared text", |ui| {
+                        ui.weak("Left out of names when matching");
+                        let strip = &mut self.search.options.strip;
+                        let mut changed = false;
+                        Grid::new("shared_text").show(ui, |ui| {
+                            ui.label("");
+                            ui.label("Prefix");
+                            ui.label("Suffix");
+                            ui.end_row();
+                            ui.label("Sources");
+                            changed |= ui.text_edit_singleline(&mut strip.source_prefix).changed();
+                            changed |= ui.text_edit_singleline(&mut strip.source_suffix).changed();
+                            ui.end_row();
+                            ui.label("Choices");
+                            changed |= ui.text_edit_singleline(&mut strip.choice_prefix).changed();
+                            changed |= ui.text_edit_singleline(&mut strip.choice_suffix).changed();
+                            ui.end_row();
+                        });
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button("Detect")
+                                .on_hover_text("Fill in text shared by nearly all sources or all choices")
+                                .clicked()
+                            {
+                                let detected = self.search.detect_shared_text();
+                                self.status = AppStatus::Info(if detected.is_empty() {
+                                    "No shared text found".to_owned()
+                                } else {
+                                    "Stripping shared text when matching".to_owned()
+                                });
+                                self.search.options.strip = detected;
+                                changed = true;
+                            }
+                            if ui.button("Clear").clicked() {
+                                self.search.options.strip = SharedText::default();
+                                changed = true;
+                            }
+                        });
+                        if changed {
+                            self.rescore();
+                        }
+                    });
+
+                    ui.menu_button("Normalize names", |ui| {
+                        ui.weak("Differences left out when matching");
+                        let options = &mut self.search.options;
+                        let mut changed = ui.checkbox(&mut options.ignore_case, "Upper and lower case").changed();
+                        changed |= ui
+                            .checkbox(&mut options.unify_separators, "Separators")
+                            .on_hover_text("Treat _ . - and spaces alike, so my_file_name matches my.file.name")
+                            .changed();
+                        changed |= ui
+                            .checkbox(&mut options.ignore_articles, "Leading articles")
+                            .on_hover_text("Leave out articles like The, so \"The Matrix\" matches \"Matrix, The\"")
+                            .changed();
+                        ui.menu_button("Article languages", |ui| {
+                            ui.weak("Also used for articles at the end of output names");
+                            for language in ArticleLanguage::ALL {
+                                let mut enabled = options.article_languages.contains(&language);
+                                if ui.checkbox(&mut enabled, language.label()).changed() {
+                                    if enabled {
+                                        options.article_languages.push(language);
+                                    } else {
+                                        options.article_languages.retain(|l| *l != language);
+                                    }
+                                    changed = true;
+                                }
+                            }
+                        });
+                        changed |= ui
+                            .checkbox(&mut options.roman_numerals, "Roman numerals")
+                            .on_hover_text("Compare numerals from I to XXXIX as digits, so \"Final Fantasy VII\" matches \"Final Fantasy 7\"")
+                            .changed();
+                        changed |= ui
+                            .checkbox(&mut options.language_tags, "Language tags")
+                            .on_hover_text("Leave out language codes and flags ending names, so \"Movie.en.forced\" matches \"Movie\"")
+                            .changed();
+                        ui.separator();
+                        ui.weak("Names with different years:");
+                        changed |= ui.radio_value(&mut options.years, YearCheck::Ignore, "Compare as usual").changed();
+                        changed |= ui
+                            .radio_value(&mut options.years, YearCheck::Penalize, "Halve the score")
+                            .on_hover_text("Applies when both names have a year from 1900 to 2099 and none of them agree")
+                            .changed();
+                        changed |= ui
+                            .radio_value(&mut options.years, YearCheck::Require, "Never match")
+                            .on_hover_text("Applies when both names have a year from 1900 to 2099 and none of them agree")
+                            .changed();
+                        if changed {
+                            self.rescore();
+                        }
+                    });
+
+                    if ui
+                        .checkbox(&mut self.search.options.checksums, "Match by checksum")
+                        .on_hover_text("Pair files with the same CRC-32 as certain matches, whatever their names. Checksums come from DAT files, tags like [A1B2C3D4] in names, or reading the files.")
+                        .changed()
+                    {
+                        self.rescore();
+                    }
+
+                    let mut changed = false;
+                    ComboBox::from_label("Tiebreaker")
+                        .selected_text(self.search.options.tiebreaker.label())
+                        .show_ui(ui, |ui| {
+                            for tiebreaker in Tiebreaker::ALL {
+                                changed |= ui
+                                    .selectable_value(&mut self.search.options.tiebreaker, tiebreaker, tiebreaker.label())
+                                    .changed();
+                            }
+                        });
+                    changed |= ui
+                        .add(
+                            Slider::new(&mut self.search.options.tie_margin, 0.0..=0.05)
+                                .text("Tie margin"),
+                        )
+                        .on_hover_text("Scores this close to the best are treated as a tie")
+                        .changed();
+                    if changed {
+                        self.rescore();
+                    }
+
+                    ui.checkbox(&mut self.search.remember_matches, "Remember confirmed matches")
+                        .on_hover_text("Re-apply matches picked by hand when the same names are loaded again");
+                    let remembered = self.search.remembered.len();
+                    ui.add_enabled_ui(remembered > 0, |ui| {
+                        ui.menu_button(format!("Forget {remembered} remembered match(es)"), |ui| {
+                            ui.label("Are you sure?");
+                            if ui.button("Yes").clicked() {
+                                self.search.remembered.clear();
+                                self.status = AppStatus::Info("Forgot remembered matches".to_owned());
+                            }
+                        });
+                    });
+
+                    if ui
+                        .checkbox(&mut self.search.options.initialisms, "Match initialisms")
+                        .on_hover_text("Also compare against abbreviations of choices, e.g. \"LoZ OoT\"")
+                        .changed()
+                    {
+                        self.rescore();
+                    }
+
+                    if ui
+                        .button("Learn from corrections")
+                        .on_hover_text("Check which settings would have picked your manual matches")
+                        .clicked()
+                    {
+                        self.correction_report = Some(self.search.learn_from_corrections());
+                    }
+                    let mut apply = false;
+                    if let Some(report) = &self.correction_report {
+                        if report.total == 0 {
+                            ui.weak("No matches have been picked by hand yet");
+                        } else if report.best_count > report.current {
+                            ui.label(format!(
+                                "{} would have gotten {} of your {} corrections right (currently {})",
+                                report.changes.join(", "),
+                                report.best_count,
+                                report.total,
+                                report.current,
+                            ));
+                            apply = ui.button("Use these settings").clicked();
+                        } else {
+                            ui.weak(format!(
+                                "Current settings already get {} of your {} corrections right",
+                                report.current, report.total
+                            ));
+                        }
+                    }
+                    if apply {
+                        if let Some(report) = self.correction_report.take() {
+                            self.search.options = report.best;
+                            self.rescore();
+                            self.status = AppStatus::Info("Applied suggested settings".to_owned());
+                        }
+                    }
+
+                    ui.menu_button("Performance", |ui| {
+                        let mut changed = ui
+                            .add(Slider::new(&mut self.search.workers, 0..=64).text("Scoring threads"))
+                            .on_hover_text("0 uses one thread per CPU core")
+                            .changed();
+                        changed |= ui
+                            .add(
+                                DragValue::new(&mut self.search.options.kept_candidates)
+                                    .prefix("Keep ")
+                                    .suffix(" candidates per source")
+                                    .clamp_range(1..=100),
+                            )
+                            .on_hover_text("Scores kept per source for picking and previewing candidates")
+                            .changed();
+                        changed |= ui
+                            .checkbox(&mut self.search.options.low_memory, "Low memory")
+                            .on_hover_text(format!(
+                                "Score and keep at most {LOW_MEMORY_PREVIEW_COUNT} candidates per source"
+                            ))
+                            .changed();
+                        if changed {
+                            self.rescore();
+                        }
+                        ui.separator();
+                        ui.weak(format!(
+                            "About {} used by names and scores",
+                            format_size(self.search.memory_used() as u64)
+                        ));
+                        if let Some(warning) = self.search.size_warning() {
+                            ui.colored_label(self.palette.warning(), warning);
+                        }
+                    });
+
+                    ui.separator();
+
+                    ui.weak("Window Theme:");
+                    let mut changed;
+                    changed = ui
+                        .radio_value(&mut self.window_theme, WindowTheme::Light, "Light")
+                        .changed();
+                    changed = ui
+                        .radio_value(&mut self.window_theme, WindowTheme::Dark, "Dark")
+                        .changed()
+                        | changed;
+                    ui.horizontal(|ui| {
+                        let [r, g, b, _] = ui.visuals().selection.bg_fill.to_array();
+                        let mut color = self.accent.unwrap_or([r, g, b]);
+                        if ui.color_edit_button_srgb(&mut color).changed() {
+                            self.accent = Some(color);
+                            changed = true;
+                        }
+                        ui.label("Accent");
+                        if self.accent.is_some() && ui.small_button("Reset").clicked() {
+                            self.accent = None;
+                            changed = true;
+                        }
+                    });
+                    if changed {
+                        Self::set_window_theme(&ctx, &self.window_theme, self.accent)
+                    }
+
+                    ui.weak("Highlights:");
+                    for palette in Palette::ALL {
+                        ui.radio_value(&mut self.palette, palette, palette.label());
+                    }
+                });
+
+                ui.menu_button("View", |ui| {
+                    ui.radio_value(&mut self.table_view, TableView::Sources, "By source");
+                    ui.radio_value(&mut self.table_view, TableView::Choices, "By choice")
+                        .on_hover_text("List each choice with the sources matched to it");
+
+                    ui.separator();
+
+                    ui.checkbox(&mut self.review_order, "Review order")
+                        .on_hover_text("Least confident matches first, then the ones closest to a runner-up");
+
+                    ui.menu_button("Similarity format", |ui| {
+                        for format in ScoreFormat::ALL {
+                            ui.radio_value(&mut self.score_format, format, format.label());
+                        }
+                    });
+
+                    ui.menu_button(format!("Show: {}", self.row_filter.label()), |ui| {
+                        for filter in RowFilter::ALL {
+                            ui.radio_value(&mut self.row_filter, filter, filter.label());
+                        }
+                    });
+
+                    if ui
+                        .button("Mark all visible rows as confirmed")
+                        .on_hover_text("Keep the current match of every row shown, as if picked by hand")
+                        .clicked()
+                    {
+                        let confirmed = self.confirm_visible_rows();
+                        if confirmed > 0 {
+                            self.edits.mark();
+                        }
+                        self.status = AppStatus::Info(format!("Confirmed {confirmed} match(es)"));
+                        ui.close_menu();
+                    }
+
+                    ui.menu_button("Columns", |ui| {
+                        ui.checkbox(&mut self.columns.path, "Source folder");
+                        ui.checkbox(&mut self.columns.kind, "Type");
+                        ui.checkbox(&mut self.columns.extension, "Extension");
+                        ui.checkbox(&mut self.columns.size, "Size");
+                        ui.checkbox(&mut self.columns.modified, "Date modified");
+                        ui.checkbox(&mut self.columns.origin, "Manual / auto");
+                        ui.checkbox(&mut self.columns.collision, "Output collision")
+                            .on_hover_text("Flag sources renamed to the same file as another");
+                        ui.checkbox(&mut self.columns.usage, "Match usage")
+                            .on_hover_text("How many sources are matched to the same choice");
+                    });
+                });
+
+                // Window
+
+                ui.menu_button("Window", |ui| {
+                    ui.checkbox(&mut self.panels.unmatched, "Unmatched choices")
+                        .on_hover_text("Choices no source is matched to, beside the table");
+                    ui.checkbox(&mut self.panels.preview, "Preview")
+                        .on_hover_text("What every matched file will be named");
+                    ui.checkbox(&mut self.panels.log, "Log")
+                        .on_hover_text("Earlier messages of the status bar");
+                    ui.separator();
+
+                    if ui.button("Getting started").on_hover_text("Show the walkthrough of the first launch again").clicked() {
+                        self.onboarded = false;
+                        self.onboarding_step = 0;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let (enabled, disabled_hint) = (!Self::is_extra_window(), "Open new windows from the main window");
+                    #[cfg(target_arch = "wasm32")]
+                    let (enabled, disabled_hint) = (false, "Open the page in another tab instead");
+                    ui.add_enabled_ui(enabled, |ui| {
+                        if ui
+                            .button("New window")
+                            .on_hover_text("Work on another set of files side by side, with its own settings")
+                            .on_disabled_hover_text(disabled_hint)
+                            .clicked()
+                        {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if let Err(error) = self.open_window() {
+                                self.status = AppStatus::Notice(format!("Could not open a new window: {error}"));
+                            }
+                            ui.close_menu();
+                        }
+                    });
+                });
+
+                ui.separator();
+
+                if ui
+                    .toggle_value(&mut self.search.options.ignore_case, "Ignore case")
+                    .on_hover_text("Match names regardless of upper and lower case, e.g. \"the matrix\" and \"The Matrix\"")
+                    .changed()
+                {
+                    self.rescore();
+                }
+            });
+        });
+
+        // Find bar
+
+        if self.find.is_some() && self.table_view == TableView::Sources {
+            let mut next = None;
+            let mut close = false;
+            let match_count = self
+                .find
+                .as_ref()
+                .map_or(0, |f| self.find_matches(&f.query).len());
+            TopBottomPanel::top("find_panel").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let Some(find) = &mut self.find else {
+                        return;
+                    };
+                    ui.label("Find:");
+                    let response = ui.text_edit_singleline(&mut find.query);
+                    if find.focus {
+                        response.request_focus();
+                        find.focus = false;
+                    }
+                    if response.changed() {
+                        find.row = None;
+                        next = Some(false);
+                    }
+                    if response.lost_focus() && ui.input().key_pressed(Key::Enter) {
+                        next = Some(ui.input().modifiers.shift);
+                        response.request_focus();
+                    }
+
+                    if ui.button("Previous").clicked() {
+                        next = Some(true);
+                    }
+                    if ui.button("Next").clicked() {
+                        next = Some(false);
+                    }
+                    if !find.query.is_empty() {
+                        ui.weak(format!("{match_count} match(es)"));
+                    }
+                    if ui.button("Close").clicked() || ui.input().key_pressed(Key::Escape) {
+                        close = true;
+                    }
+                });
+            });
+            if let Some(backwards) = next {
+                self.find_next(backwards);
+            }
+            if close {
+                self.find = None;
+            }
+        }
+
+        // Status bar
+
+        self.log_status();
+        TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let counts = self.status_counts();
+                ui.label(format!("{} source(s)", counts.sources));
+                ui.separator();
+                ui.label(format!("{} choice(s)", counts.choices));
+                ui.separator();
+                ui.label(format!("{} matched", counts.matched));
+                ui.separator();
+                let pending = ui
+                    .add(Label::new(format!("{} to review", counts.pending)).sense(Sense::click()))
+                    .on_hover_text(
+                        "Matches between the review and trust thresholds, click to show only them",
+                    );
+                if pending.clicked() {
+                    self.table_view = TableView::Sources;
+                    self.row_filter = RowFilter::NeedsReview;
+                }
+
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    // Little helper in case it's a debug build
+                    warn_if_debug_build(ui);
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(pending) = &self.pending_copy {
+                        if ui.button("Cancel").clicked() {
+                            pending.copy.cancel();
+                        }
+                        ui.add(
+                            ProgressBar::new(pending.fraction())
+                                .show_percentage()
+                                .desired_width(150.0),
+                        );
+                        let copying = pending.index.min(pending.copy.copies.len() - 1);
+                        let name = pending.copy.copies[copying]
+                            .0
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy();
+                        if pending.size > 0 {
+                            ui.weak(format!(
+                                "Copying {} of {}: {name} ({} of {})",
+                                copying + 1,
+                                pending.copy.copies.len(),
+                                format_size(pending.copied),
+                                format_size(pending.size)
+                            ));
+                        } else {
+                            ui.weak(format!(
+                                "Copying {} of {}: {name}",
+                                copying + 1,
+                                pending.copy.copies.len()
+                            ));
+                        }
+                        return;
+                    }
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(pending) = &self.pending_score {
+                        if ui.button("Cancel").clicked() {
+                            pending.task.cancel();
+                        }
+                        ui.weak(format!(
+                            "Scoring {} source(s)...",
+                            self.search.source_names.len()
+                        ));
+                        ui.spinner();
+                        return;
+                    }
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(pending) = &self.pending_rename {
+                        ui.weak(format!("Renaming {} file(s)...", pending.renames.len()));
+                        ui.spinner();
+                        return;
+                    }
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(pending) = &self.pending_import {
+                        if ui.button("Cancel").clicked() {
+                            pending.scan.cancel();
+                        }
+                        ui.weak(format!("Importing... {} file(s)", pending.summary.added));
+                        ui.spinner();
+                        return;
+                    }
+
+                    if let Some(warning) = self.search.size_warning() {
+                        ui.colored_label(self.palette.warning(), "\u{26a0} Large lists")
+                            .on_hover_text(warning);
+                    }
+
+                    let message = match &self.status {
+                        AppStatus::None => None,
+                        AppStatus::Info(message) => Some(RichText::new(message).weak()),
+                        AppStatus::Notice(message) => Some(RichText::new(message).strong()),
+                        // AppStatus::Progress(message, value) => {
+                        //     ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
+                        //         let progress_bar = ProgressBar::new(*value).show_percentage();
+                        //         ui.weak(message);
+                        //         ui.add(progress_bar);
+                        //     });
+                        // }
+                    };
+                    let log = match message {
+                        Some(message) => ui.add(Label::new(message).sense(Sense::click())),
+                        None if !self.log.is_empty() => {
+                            ui.add(Label::new(RichText::new("Log").weak()).sense(Sense::click()))
+                        }
+                        None => return,
+                    };
+                    if log
+                        .on_hover_text("Show or hide the log of earlier messages")
+                        .clicked()
+                    {
+                        self.panels.log = !self.panels.log;
+                    }
+                });
+            });
+        });
+
+        // Panels
+
+        self.update_output_cache();
+        if self.panels.log {
+            TopBottomPanel::bottom("log_panel")
+                .resizable(true)
+                .default_height(120.0)
+                .show(ctx, |ui| self.log_panel(ui));
+        }
+        if self.panels.preview {
+            TopBottomPanel::bottom("preview_panel")
+                .resizable(true)
+                .default_height(160.0)
+                .show(ctx, |ui| self.preview_panel(ui));
+        }
+        if self.panels.unmatched && !self.search.choice_names.is_empty() {
+            SidePanel::right("unmatched_panel")
+                .resizable(true)
+                .default_width(200.0)
+                .show(ctx, |ui| {
+                    ui.style_mut().wrap = Some(false);
+                    self.unmatched_panel(ui);
+                });
+        }
+
+        // Table
+
+        CentralPanel::default().show(ctx, |ui| {
+            ui.style_mut().wrap = Some(false);
+
+            if self.table_view == TableView::Choices {
+                self.choice_table(ui);
+                return;
+            }
+
+            enum ListTask {
+                None,
+                Run(Command),
+                /// Ask for the destination folder of the source with this path
+                ChooseDestination(PathBuf),
+            }
+
+            let mut task = ListTask::None;
+
+            // Lent to the table, which borrows the rest of the app, and put back after it
+            let numbers = std::mem::take(&mut self.output_cache.numbers);
+            let collisions = std::mem::take(&mut self.output_cache.collisions);
+            let match_usage = std::mem::take(&mut self.output_cache.usage);
+            let groups = self.search.choice_groups();
+            let columns = &self.columns;
+            let extra_titles: Vec<&'static str> = [
+                (columns.path, "Folder"),
+                (columns.kind, "Type"),
+                (columns.extension, "Extension"),
+                (columns.size, "Size"),
+                (columns.modified, "Modified"),
+                (columns.origin, "Picked"),
+                (columns.collision, "Collision"),
+                (columns.usage, "Used"),
+            ]
+            .into_iter()
+            .filter_map(|(shown, title)| shown.then_some(title))
+            .collect();
+
+            let order = self.row_order();
+            let pinned_count = self.search.source_names.iter().filter(|s| s.pinned).count();
+            let found_row = self.find.as_ref().and_then(|f| f.row);
+            let mut table = TableBuilder::new(ui);
+            if let Some(find) = self.find.as_mut().filter(|f| f.scroll) {
+                find.scroll = false;
+                let display_row = order.iter().position(|i| Some(*i) == find.row);
+                table = table.scroll_to_row(display_row.unwrap_or(0), Some(Align::Center));
+            }
+
+            table = table.striped(true).auto_shrink([false; 2]).column(
+                self.columns
+                    .sized("Source Name", Column::remainder())
+                    .clip(true)
+                    .at_least(100.0)
+                    .resizable(true),
+            );
+            for title in &extra_titles {
+                table = table.column(
+                    self.columns
+                        .sized(title, Column::initial(80.0))
+                        .at_least(40.0)
+                        .clip(true)
+                        .resizable(true),
+                );
+            }
+            table
+                .column(
+                    self.columns
+                        .sized("Similarity", Column::initial(60.0))
+                        .range(35.0..=60.0)
+                        .clip(true)
+                        .resizable(true),
+                )
+                .column(
+                    self.columns
+                        .sized("Closest Match", Column::remainder())
+                        .clip(true)
+                        .at_least(100.0)
+                        .resizable(true),
+                )
+                .column(Column::remainder().clip(true).at_least(100.0))
+                .header(20.0, |mut header| {
+                    header.col(|ui| {
+                        self.columns.remember_width("Source Name", ui.max_rect().width());
+                        if pinned_count > 0 {
+                            ui.label(format!("Source Name ({pinned_count} pinned)"));
+                        } else {
+                            ui.label("Source Name");
+                        }
+                    });
+                    for title in &extra_titles {
+                        header.col(|ui| {
+                            self.columns.remember_width(title, ui.max_rect().width());
+                            ui.label(*title);
+                        });
+                    }
+                    header.col(|ui| {
+                        self.columns.remember_width("Similarity", ui.max_rect().width());
+                        ui.label("Similarity");
+                    });
+                    header.col(|ui| {
+                        self.columns.remember_width("Closest Match", ui.max_rect().width());
+                        ui.label("Closest Match");
+                    });
+                    header.col(|ui| {
+                        ui.label(format!(
+                            "Renamed File ({})",
+                            match self.side_to_copy {
+                                SideToUse::Choices => "Choices",
+                                SideToUse::Sources => "Sources",
+                            }
+                        ));
+                    });
+                })
+                .body(|body| {
+                    body.rows(20.0, order.len(), |display_index, mut row| {
+                        let row_index = order[display_index];
+                        let threshold = self.threshold_for(&self.search.source_names[row_index]);
+                        let row_text =
+                            self.row_text(&self.search.source_names[row_index], numbers[row_index]);
+                        let item = &mut self.search.source_names[row_index];
+
+                        // Source Name
+
+                        let item_name = item.file.name.clone();
+                        let item_pinned = item.pinned;
+                        let choice_names = &self.search.choice_names;
+                        let options = &self.search.options;
+                        row.col(|ui| {
+                            let text = if item_pinned {
+                                RichText::new(format!("\u{2605} {item_name}"))
+                            } else {
+                                RichText::new(item_name.as_str())
+                            };
+                            let text = if item.query.is_some() {
+                                text.italics()
+                            } else {
+                                text
+                            };
+                            let text = if found_row == Some(row_index) {
+                                text.strong()
+                                    .background_color(ui.visuals().selection.bg_fill)
+                            } else {
+                                text
+                            };
+                            let label = ui.add(Label::new(text).wrap(false).sense(Sense::click()));
+                            let mut label = hover_file_details(label, &item.file);
+                            if let Some(query) = &item.query {
+                                label = label.on_hover_text(format!("Matched as \"{query}\""));
+                            }
+                            label.context_menu(|ui| {
+                                if ui.button("Copy").clicked() {
+                                    ui.output().copied_text = item_name.to_string();
+                                    ui.close_menu();
+                                }
+                                if ui.button("Copy row").clicked() {
+                                    ui.output().copied_text = row_text.clone();
+                                    ui.close_menu();
+                                }
+                                ui.separator();
+                                ui.label("Search text")
+                                    .on_hover_text("Match this source as if it were named this, without renaming the file");
+                                let mut query = item.search_text().to_owned();
+                                if ui.text_edit_singleline(&mut query).changed() {
+                                    item.query = Some(query);
+                                    item.update_choices(choice_names, &ChoiceIndex::new(choice_names), options);
+                                    self.edits.mark();
+                                }
+                                ui.add_enabled_ui(item.query.is_some(), |ui| {
+                                    if ui.button("Use file name").clicked() {
+                                        item.query = None;
+                                        item.update_choices(choice_names, &ChoiceIndex::new(choice_names), options);
+                                        self.edits.mark();
+                                        ui.close_menu();
+                                    }
+                                });
+                            });
+                        });
+
+                        // Optional columns
+
+                        if self.columns.path {
+                            row.col(|ui| {
+                                let folder = item.file.path.parent().unwrap_or(Path::new(""));
+                                let folder = folder.to_string_lossy();
+                                let label = ui.add(Label::new(folder.as_ref()).wrap(false));
+                                hover_if_clipped(ui, label, &folder);
+                            });
+                        }
+                        if self.columns.kind {
+                            row.col(|ui| {
+                                ui.label(if item.file.is_dir { "Folder" } else { "File" });
+                            });
+                        }
+                        if self.columns.extension {
+                            row.col(|ui| {
+                                ui.label(item.file.extension());
+                            });
+                        }
+                        if self.columns.size {
+                            row.col(|ui| {
+                                ui.label(item.file.size.map_or("".to_owned(), format_size));
+                            });
+                        }
+                        if self.columns.modified {
+                            row.col(|ui| {
+                                ui.label(item.file.modified.map_or("".to_owned(), format_date));
+                            });
+                        }
+                        if self.columns.origin {
+                            row.col(|ui| {
+                                match item.manual_choice {
+                                    Some(_) => ui.label("Manual"),
+                                    None => ui.weak("Auto"),
+                                };
+                            });
+                        }
+                        if self.columns.collision {
+                            row.col(|ui| {
+                                if collisions.get(row_index).copied().unwrap_or(false) {
+                                    ui.colored_label(self.palette.warning(), "Collides")
+                                        .on_hover_text(
+                                            "Another source is renamed to the same file",
+                                        );
+                                }
+                            });
+                        }
+                        if self.columns.usage {
+                            row.col(|ui| {
+                                let used = match_usage.get(row_index).copied().unwrap_or(0);
+                                let count = format!("{used}x");
+                                if used > 1 {
+                                    ui.colored_label(self.palette.warning(), count)
+                                        .on_hover_text(format!(
+                                            "{used} sources are matched to this choice"
+                                        ));
+                                } else if used == 1 {
+                                    ui.weak(count);
+                                }
+                            });
+                        }
+
+                        // Similarity
+
+                        let current_score = item.current_score();
+                        let below_threshold = current_score.map_or(false, |s| s < threshold);
+
+                        let rank = item.current_choice().and_then(|choice| {
+                            item.choice_map.iter().position(|(c, _)| *c == choice)
+                        });
+                        let choice_similarity = self.score_format.format(current_score, rank);
+                        let score_text = choice_similarity.clone();
+                        let tied = current_score.is_some() && item.tied_count > 1;
+                        let ambiguous = current_score.is_some()
+                            && !below_threshold
+                            && item.score_gap() < self.ambiguity_margin;
+                        let choice_similarity = if item.locked {
+                            format!("\u{1f512} {choice_similarity}")
+                        } else {
+                            choice_similarity
+                        };
+                        let choice_similarity = if tied {
+                            RichText::new(format!("{choice_similarity} ~"))
+                                .color(self.palette.warning())
+                        } else if ambiguous {
+                            RichText::new(format!("{choice_similarity} ?"))
+                                .color(self.palette.warning())
+                        } else {
+                            RichText::new(choice_similarity)
+                        };
+
+                        row.col(|ui| {
+                            let response = ui.menu_button(choice_similarity, |ui| {
+                                ui.set_min_size(Vec2::new(250.0, 0.0));
+
+                                // TODO: Add match picker window
+                                // if ui.button("Search for a match").clicked() { }
+
+                                let lock_label = if item.locked { "Unlock" } else { "Lock" };
+                                if ui
+                                    .button(lock_label)
+                                    .on_hover_text(
+                                        "Locked rows keep their match when options change or files are added",
+                                    )
+                                    .clicked()
+                                {
+                                    item.locked = !item.locked;
+                                    self.edits.mark();
+                                    ui.close_menu();
+                                }
+
+                                ui.add_enabled_ui(!item.locked, |ui| {
+                                    ui.weak("Pick a match:");
+
+                                    // Candidates are listed in rank order already
+                                    let candidate_format = match self.score_format {
+                                        ScoreFormat::Rank => ScoreFormat::PercentDecimals,
+                                        format => format,
+                                    };
+                                    for (c_index, c_score) in item.choice_map.clone() {
+                                        let btn = RadioButton::new(
+                                            item.manual_choice
+                                                .flatten()
+                                                .map_or(false, |c| c == c_index),
+                                            format!(
+                                                "[{}] {}",
+                                                candidate_format.format(Some(c_score), None),
+                                                self.search.choice_names[c_index].file.stem()
+                                            ),
+                                        );
+                                        if ui.add(btn).clicked() {
+                                            item.set_choice(Some(c_index));
+                                            self.edits.mark();
+                                            if self.search.remember_matches {
+                                                self.search.remembered.insert(
+                                                    item.file.stem().to_owned(),
+                                                    self.search.choice_names[c_index].file.stem()
+                                                    .to_owned(),
+                                                );
+                                            }
+                                        }
+                                    }
+
+                                    let btn = RadioButton::new(
+                                        item.manual_choice.map_or(false, |c| c.is_none()),
+                                        "[Don't use match]",
+                                    );
+                                    if ui.add(btn).clicked() {
+                                        item.set_choice(None);
+                                        self.edits.mark();
+                                    }
+
+                                    let current_choice = item.current_choice();
+                                    ui.add_enabled_ui(
+                                        item.manual_choice.is_none() && current_choice.is_some(),
+                                        |ui| {
+                                            if ui.button("Confirm match").clicked() {
+                                                item.set_choice(current_choice);
+                                                self.edits.mark();
+                                                if let Some(choice) = current_choice
+                                                    .filter(|_| self.search.remember_matches)
+                                                {
+                                                    self.search.remembered.insert(
+                                                        item.file.stem().to_owned(),
+                                                        self.search.choice_names[choice].file.stem()
+                                                        .to_owned(),
+                                                    );
+                                                }
+                                            }
+                                        },
+                                    );
+                                });
+
+                                let pin_label = if item.pinned {
+                                    "Unpin"
+                                } else {
+                                    "Pin for later"
+                                };
+                                if ui
+                                    .button(pin_label)
+                                    .on_hover_text("Pinned sources stay at the top of the table")
+                                    .clicked()
+                                {
+                                    item.pinned = !item.pinned;
+                                    self.edits.mark();
+                                    ui.close_menu();
+                                }
+
+                                ui.menu_button("Algorithm for this source", |ui| {
+                                    let mut changed = ui
+                                        .radio_value(&mut item.algorithm, None, "[Same as options]")
+                                        .changed();
+                                    for algorithm in SearchAlgorithm::ALL {
+                                        changed |= ui
+                                            .radio_value(
+                                                &mut item.algorithm,
+                                                Some(algorithm),
+                                                algorithm.label(),
+                                            )
+                                            .changed();
+                                    }
+                                    if changed {
+                                        item.update_choices(
+                                            &self.search.choice_names,
+                                            &ChoiceIndex::new(&self.search.choice_names),
+                                            &self.search.options,
+                                        );
+                                    }
+                                });
+
+                                if !groups.is_empty() {
+                                    ui.menu_button("Match within group", |ui| {
+                                        let mut changed = ui
+                                            .radio_value(
+                                                &mut item.choice_group,
+                                                None,
+                                                "[All choices]",
+                                            )
+                                            .changed();
+                                        for group in &groups {
+                                            changed |= ui
+                                                .radio_value(
+                                                    &mut item.choice_group,
+                                                    Some(group.clone()),
+                                                    group,
+                                                )
+                                                .changed();
+                                        }
+                                        if changed {
+                                            item.update_choices(
+                                                &self.search.choice_names,
+                                                &ChoiceIndex::new(&self.search.choice_names),
+                                                &self.search.options,
+                                            );
+                                            self.edits.mark();
+                                        }
+                                    });
+                                }
+
+                                ui.menu_button("Destination for this source", |ui| {
+                                    if let Some(destination) = &item.destination {
+                                        ui.label(destination.to_string_lossy());
+                                    }
+                                    ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")), |ui| {
+                                        if ui.button("Choose folder").clicked() {
+                                            task = ListTask::ChooseDestination(item.file.path.clone());
+                                            ui.close_menu();
+                                        }
+                                    })
+                                    .response
+                                    .on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP);
+                                    ui.add_enabled_ui(item.destination.is_some(), |ui| {
+                                        if ui.button("Use output folder").clicked() {
+                                            item.destination = None;
+                                            self.edits.mark();
+                                            ui.close_menu();
+                                        }
+                                    });
+                                });
+
+                                ui.add_enabled_ui(item.manual_choice.is_some() && !item.locked, |ui| {
+                                    if ui.button("Reset to default").clicked() {
+                                        item.reset_choice();
+                                        self.edits.mark();
+                                        self.search
+                                            .remembered
+                                            .remove(item.file.stem());
+                                    }
+                                });
+
+                                if let Some(choice) = item.current_choice() {
+                                    let owned = self.search.choice_names[choice].owner.is_some();
+                                    if owned {
+                                        if ui
+                                            .button("Let other sources match this choice")
+                                            .clicked()
+                                        {
+                                            task = ListTask::Run(Command::ReleaseChoice(choice));
+                                            ui.close_menu();
+                                        }
+                                    } else if ui
+                                        .button("Match this choice to this source only")
+                                        .on_hover_text("Other sources fall back to their next best match")
+                                        .clicked()
+                                    {
+                                        task = ListTask::Run(Command::ReserveChoice {
+                                            source: row_index,
+                                            choice,
+                                        });
+                                        ui.close_menu();
+                                    }
+                                    if ui
+                                        .button("Never match this choice for any source")
+                                        .on_hover_text("Allow it again from Choices > Excluded choices")
+                                        .clicked()
+                                    {
+                                        task = ListTask::Run(Command::ExcludeChoice(choice));
+                                        ui.close_menu();
+                                    }
+                                }
+
+                                ui.separator();
+
+                                if ui
+                                    .button("Remove source")
+                                    .on_hover_text("Can be restored from Sources > Removed sources")
+                                    .clicked()
+                                {
+                                    task = ListTask::Run(Command::RemoveSource(row_index));
+                                    ui.close_menu();
+                                }
+                            });
+                            let response = copy_menu(response.response, &score_text, &row_text);
+                            if tied {
+                                response.on_hover_text(format!(
+                                    "Tied with {} other candidate(s)",
+                                    item.tied_count - 1
+                                ));
+                            } else if ambiguous {
+                                response.on_hover_text(format!(
+                                    "Only {:.1}% ahead of the next candidate",
+                                    100.0 * item.score_gap()
+                                ));
+                            }
+                        });
+
+                        // Closest Match
+
+                        let choice = item
+                            .current_choice()
+                            .filter(|_| !below_threshold)
+                            .and_then(|i| self.search.choice_names.get(i));
+                        let needs_review = current_score
+                            .map_or(false, |s| s >= threshold && s < self.trust_threshold);
+
+                        row.col(|ui| {
+                            let name = choice.map_or("", |c| c.file.name.as_str());
+                            let text = if needs_review {
+                                RichText::new(name).italics()
+                            } else {
+                                RichText::new(name)
+                            };
+                            let mut label =
+                                ui.add(Label::new(text).wrap(false).sense(Sense::click()));
+                            if let Some(choice) = choice {
+                                label = hover_file_details(label, &choice.file);
+                            }
+                            if needs_review {
+                                label = label
+                                    .on_hover_text("Needs review: confirm it from the similarity menu");
+                            }
+                            copy_menu(label, name, &row_text);
+                        });
+
+                        // Renamed File
+
+                        let choice = choice.filter(|_| !(needs_review && self.exclude_unreviewed));
+                        row.col(|ui| {
+                            let source = &self.search.source_names[row_index];
+                            let rename = choice.map_or("".to_owned(), |reference| {
+                                self.rename(source, reference, numbers[row_index])
+                            });
+                            let problems = self.naming.problems(&rename);
+                            let text = if problems.is_empty() {
+                                RichText::new(&rename)
+                            } else {
+                                RichText::new(format!("\u{26a0} {rename}"))
+                                    .color(self.palette.warning())
+                            };
+                            let label =
+                                ui.add(Label::new(text).wrap(false).sense(Sense::click()));
+                            let mut label = hover_if_clipped(ui, label, &rename);
+                            if !problems.is_empty() {
+                                label = label.on_hover_text(problems.join("\n"));
                             }
+                            let label = copy_menu(label, &rename, &row_text);
+                            if let Some(destination) = &source.destination {
+                                label.on_hover_text(format!("Copied to {}", destination.display()));
+                            }
+                        });
+                    });
+                });
+            self.output_cache.numbers = numbers;
+            self.output_cache.collisions = collisions;
+            self.output_cache.usage = match_usage;
+
+            match task {
+                ListTask::None => {}
+                ListTask::Run(command) => self.run(command),
+                ListTask::ChooseDestination(path) => {
+                    let request = pick_folder(&self.renames_path, "Destination for this source");
+                    self.ask_path(ctx, request, move |app, folder, _| {
+                        // Found again by path, the table may have changed while the dialog was open
+                        let mut sources = app.search.source_names.iter_mut();
+                        if let Some(source) = sources.find(|s| s.file.path == path) {
+                            source.destination = Some(folder);
+                            app.edits.mark();
                         }
                     });
+                }
+            }
+        });
 
-                    if ui.button("Import files").clicked() {
-                        let files = pick_files(&self.choices_path, "Choose reference files");
+        // Benchmark
 
-                        if let Some(files) = files {
-                            if !files.is_empty() {
-                                if let Some(file) = files.first() {
-                                    self.choices_path =
-                                        file.parent().unwrap().to_str().unwrap().to_owned();
-                                }
+        if self.show_benchmark {
+            let mut open = true;
+            Window::new("Benchmark").open(&mut open).show(ctx, |ui| {
+                ui.weak(format!(
+                    "Scores up to {BENCHMARK_SAMPLE} sources against every choice with each algorithm"
+                ));
+                if ui.button("Run").clicked() {
+                    self.benchmark = self.search.benchmark();
+                }
+                if !self.benchmark.is_empty() {
+                    Grid::new("benchmark_results").striped(true).show(ui, |ui| {
+                        ui.strong("Algorithm");
+                        ui.strong("Pairs");
+                        ui.strong("Time");
+                        ui.strong("Pairs/s");
+                        ui.end_row();
+                        for result in &self.benchmark {
+                            ui.label(result.algorithm.label());
+                            ui.label(result.pairs.to_string());
+                            ui.label(format!("{:.1} ms", result.elapsed.as_secs_f64() * 1000.0));
+                            ui.label(format!("{:.0}", result.pairs_per_second()));
+                            ui.end_row();
+                        }
+                    });
+                    if ui.button("Copy").clicked() {
+                        ui.output().copied_text = format_benchmark(&self.benchmark);
+                    }
+                }
+            });
+            self.show_benchmark = open;
+        }
 
-                                let count = files.len();
-                                for item in files {
-                                    self.search.add_choice(item);
+        // Import summary
+
+        if let Some(summary) = &self.import_summary {
+            let mut open = true;
+            Window::new("Import summary")
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    ui.label(summary.folder.to_string_lossy());
+                    ui.label(format!("Added {} file(s)", summary.added));
+                    let list = |ui: &mut Ui, title: String, entries: Vec<&PathBuf>| {
+                        CollapsingHeader::new(title).show(ui, |ui| {
+                            ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                                for path in entries {
+                                    ui.label(paths::file_name(path).unwrap_or_default());
                                 }
-                                self.search.update_all();
-                                self.status = AppStatus::Info(format!("Added {count} reference(s)"));
-                            }
+                            });
+                        });
+                    };
+                    for reason in Skipped::ALL {
+                        let entries: Vec<&PathBuf> = summary.skipped(reason).collect();
+                        if !entries.is_empty() {
+                            list(
+                                ui,
+                                format!("{} ({})", reason.label(), entries.len()),
+                                entries,
+                            );
+                        }
+                    }
+                    if summary.unlisted > 0 {
+                        ui.label(format!(
+                            "{} entries could not be listed at all",
+                            summary.unlisted
+                        ));
+                    }
+                    if !summary.lossy.is_empty() {
+                        list(
+                            ui,
+                            format!("Names that aren't valid UTF-8 ({})", summary.lossy.len()),
+                            summary.lossy.iter().collect(),
+                        );
+                        ui.weak(
+                            "These were added, with unreadable characters replaced in their names",
+                        );
+                    }
+                });
+            if !open {
+                self.import_summary = None;
+            }
+        }
+
+        self.track_edits();
+
+        // Job confirmation
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some((file, summary, changes)) = self.confirm_job.clone() {
+            Window::new("Run job")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(summary);
+                    if !changes.is_empty() {
+                        CollapsingHeader::new(format!("Changes ({})", changes.len())).show(
+                            ui,
+                            |ui| {
+                                ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                                    for line in &changes {
+                                        ui.label(line);
+                                    }
+                                });
+                            },
+                        );
+                    }
+                    ui.horizontal(|ui| {
+                        let has_steps = self.output_targets.iter().any(|t| t.enabled);
+                        if ui
+                            .add_enabled(has_steps, Button::new("Run outputs"))
+                            .clicked()
+                        {
+                            self.run_job_outputs(ctx, file.clone());
+                            self.confirm_job = None;
+                        }
+                        if ui.button("Review first").clicked() {
+                            self.confirm_job = None;
+                        }
+                    });
+                });
+        }
+
+        // Confirmation
+
+        if let Some(confirmation) = &self.confirmation {
+            let mut proceed = None;
+            Window::new(confirmation.title)
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.set_max_width(400.0);
+                    ui.label(&confirmation.summary);
+                    let files = &confirmation.files;
+                    if !files.is_empty() {
+                        ui.separator();
+                        for name in files.iter().take(CONFIRM_LISTED_COUNT) {
+                            ui.weak(name);
+                        }
+                        if files.len() > CONFIRM_LISTED_COUNT {
+                            CollapsingHeader::new(format!("All {} file(s)", files.len())).show(
+                                ui,
+                                |ui| {
+                                    ScrollArea::vertical().max_height(200.0).show_rows(
+                                        ui,
+                                        18.0,
+                                        files.len(),
+                                        |ui, rows| {
+                                            for name in &files[rows] {
+                                                ui.label(name);
+                                            }
+                                        },
+                                    );
+                                },
+                            );
+                        }
+                        ui.separator();
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button(confirmation.accept).clicked() {
+                            proceed = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() || ui.input().key_pressed(Key::Escape) {
+                            proceed = Some(false);
+                        }
+                    });
+                });
+            match proceed {
+                Some(true) => {
+                    if let Some(confirmation) = self.confirmation.take() {
+                        (confirmation.action)(self, ctx);
+                    }
+                }
+                Some(false) => self.confirmation = None,
+                None => {}
+            }
+        }
+
+        // Overlap confirmation
+
+        if let Some((warnings, _)) = &self.confirm_overlap {
+            let mut proceed = None;
+            Window::new("Files may be overwritten")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    for warning in warnings {
+                        ui.label(warning);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Continue anyway").clicked() {
+                            proceed = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            proceed = Some(false);
                         }
+                    });
+                });
+            match proceed {
+                Some(true) => {
+                    if let Some((_, action)) = self.confirm_overlap.take() {
+                        action(self, ctx);
                     }
+                }
+                Some(false) => self.confirm_overlap = None,
+                None => {}
+            }
+        }
+
+        // File dialog
 
+        if let Some((pending, _)) = &self.dialog {
+            Window::new("Waiting for file dialog")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_BOTTOM, vec2(0.0, -40.0))
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(&pending.title);
+                    });
+                });
+        }
+
+        // Walkthrough
+
+        if !self.onboarded {
+            let step = self.onboarding_step.min(ONBOARDING_STEPS.len() - 1);
+            let (title, text) = ONBOARDING_STEPS[step];
+            let target = menu_rects[step];
+            let accent = ctx.style().visuals.selection.bg_fill;
+            ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("onboarding")))
+                .rect_stroke(target.expand(2.0), 3.0, Stroke::new(2.0, accent));
+
+            Window::new("Getting started")
+                .collapsible(false)
+                .resizable(false)
+                .fixed_pos(target.left_bottom() + vec2(0.0, 8.0))
+                .show(ctx, |ui| {
+                    ui.set_max_width(280.0);
+                    ui.strong(title);
+                    ui.label(text);
                     ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.add_enabled_ui(step > 0, |ui| {
+                            if ui.button("Back").clicked() {
+                                self.onboarding_step = step - 1;
+                            }
+                        });
+                        if step + 1 < ONBOARDING_STEPS.len() {
+                            if ui.button("Next").clicked() {
+                                self.onboarding_step = step + 1;
+                            }
+                            if ui.button("Skip").clicked() {
+                                self.onboarded = true;
+                            }
+                        } else if ui.button("Done").clicked() {
+                            self.onboarded = true;
+                        }
+                        ui.weak(format!("{} of {}", step + 1, ONBOARDING_STEPS.len()));
+                    });
+                });
+        }
 
-                    // TODO: Open Window dialog with checked list
-                    // if ui.button("Manage references...").clicked() { }
+        // Exit confirmation
 
-                    ui.menu_button("Clear all references", |ui| {
-                        ui.label("Are you sure?");
-                        if ui.button("Yes").clicked() {
-                            self.search.choice_names.clear();
-                            self.search.update_all();
-                            self.status = AppStatus::Info("Cleared all references".to_owned());
+        if self.confirm_close {
+            // Set once the session was saved from the dialog below
+            if self.allow_close {
+                frame.close();
+            }
+            Window::new("Unsaved work")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label("Matches have changed since the last export.");
+                    ui.label("Save the session before quitting?");
+                    ui.horizontal(|ui| {
+                        if ui.button("Save session").clicked() {
+                            self.save_session_dialog(ctx, true);
                         }
-                    })
+                        if ui.button("Quit without saving").clicked() {
+                            self.allow_close = true;
+                            frame.close();
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.confirm_close = false;
+                        }
+                    });
                 });
+        }
+    }
+}
 
-                // Renames
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str) -> FilePath {
+        FilePath {
+            name: name.into(),
+            path: PathBuf::from(name),
+            ..Default::default()
+        }
+    }
+
+    /// Names mixing words, separators, dots and multibyte letters, from a fixed seed so failures
+    /// repeat
+    fn generated_names(count: usize) -> Vec<String> {
+        const PIECES: [&str; 12] = [
+            "a",
+            "Z",
+            "\u{e9}",
+            "\u{65e5}\u{672c}",
+            ".",
+            ".",
+            " ",
+            "_",
+            "-",
+            "1999",
+            "tar",
+            "gz",
+        ];
+        let mut state: u32 = 0x9E37_79B9;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as usize
+        };
+        (0..count)
+            .map(|_| {
+                let length = next() % 8;
+                (0..length).map(|_| PIECES[next() % PIECES.len()]).collect()
+            })
+            .collect()
+    }
+
+    /// An app with `choices` listed by name, and `sources` scored against them
+    fn app_with(choices: &[&str], sources: &[&str]) -> MainApp {
+        let mut app = MainApp::default();
+        for name in choices {
+            app.search.add_choice_name((*name).to_owned());
+        }
+        for name in sources {
+            app.search.source_names.push(SourceName {
+                file: file(name),
+                ..Default::default()
+            });
+        }
+        app.search.update_all();
+        app
+    }
+
+    #[test]
+    fn confirming_rows_skips_matches_below_the_threshold() {
+        let mut app = app_with(&[], &["the.matrix.1999.mkv"]);
+        app.execute(Command::AddListedChoices {
+            names: vec![import::Listed::new(
+                "The Matrix (1999).mkv".to_owned(),
+                None,
+            )],
+            group: "List".to_owned(),
+        });
+        app.threshold = 1.1;
+        assert_eq!(app.confirm_visible_rows(), 0);
+        assert_eq!(app.search.source_names[0].manual_choice, None);
+
+        app.threshold = 0.0;
+        assert_eq!(app.confirm_visible_rows(), 1);
+        assert_eq!(app.search.source_names[0].manual_choice, Some(Some(0)));
+    }
+
+    #[test]
+    fn locks_without_a_match_survive_clearing_choices() {
+        let mut app = app_with(&[], &["the.matrix.1999.mkv"]);
+        app.execute(Command::AddListedChoices {
+            names: vec![
+                import::Listed::new("The Matrix (1999).mkv".to_owned(), None),
+                import::Listed::new("Heat (1995).mkv".to_owned(), None),
+            ],
+            group: "List".to_owned(),
+        });
+        assert!(!app.search.source_names[0].choice_map.is_empty());
+        app.search.source_names[0].set_choice(None);
+        app.search.source_names[0].locked = true;
+        app.execute(Command::ClearChoices);
+
+        let source = &app.search.source_names[0];
+        assert!(source.locked);
+        assert!(source.choice_map.is_empty());
+        assert_eq!(source.current_choice(), None);
+    }
+
+    #[test]
+    fn choice_commands_rescore() {
+        let mut app = app_with(&[], &["the.matrix.1999.mkv"]);
+        app.execute(Command::AddListedChoices {
+            names: vec![
+                import::Listed::new("Alien (1979).mkv".to_owned(), None),
+                import::Listed::new("The Matrix (1999).mkv".to_owned(), None),
+            ],
+            group: "List".to_owned(),
+        });
+        assert!(app.edits.unsaved());
+        assert_eq!(app.search.source_names[0].current_choice(), Some(1));
+
+        app.execute(Command::ExcludeChoice(1));
+        assert_eq!(app.search.source_names[0].current_choice(), Some(0));
+
+        app.execute(Command::ClearChoices);
+        assert_eq!(app.search.source_names[0].current_choice(), None);
+    }
+
+    #[test]
+    fn choice_edits_rescore_like_a_full_update() {
+        let mut app = app_with(
+            &[
+                "Alien.mkv",
+                "Aliens.mkv",
+                "Alien 3.mkv",
+                "Heat.mkv",
+                "Heathers.mkv",
+                "Up.mkv",
+            ],
+            &["alien.mkv", "aliens.1986.mkv", "heat.mkv", "up.mkv"],
+        );
+        let scores = |app: &MainApp| -> Vec<Vec<(usize, f32)>> {
+            let mut full = app.search.source_names.clone();
+            let index = ChoiceIndex::new(&app.search.choice_names);
+            for source in &mut full {
+                source.update_choices(&app.search.choice_names, &index, &app.search.options);
+            }
+            let kept: Vec<_> = app
+                .search
+                .source_names
+                .iter()
+                .map(|s| s.choice_map.clone())
+                .collect();
+            assert_eq!(
+                kept,
+                full.iter()
+                    .map(|s| s.choice_map.clone())
+                    .collect::<Vec<_>>()
+            );
+            kept
+        };
+
+        app.execute(Command::ExcludeChoice(0));
+        assert!(scores(&app)
+            .iter()
+            .all(|map| map.iter().all(|(c, _)| *c != 0)));
+        app.search.choice_names[0].excluded = false;
+        app.search.offer_choice(0);
+        scores(&app);
+
+        app.execute(Command::ReserveChoice {
+            source: 0,
+            choice: 1,
+        });
+        scores(&app);
+        app.execute(Command::ReleaseChoice(1));
+        scores(&app);
+    }
+
+    #[test]
+    fn edits_are_undone_step_by_step() {
+        let mut app = app_with(&["Alien.mkv", "Heat.mkv"], &["alien.mkv", "heat.mkv"]);
+        app.track_edits();
+
+        app.search.source_names[0].set_choice(None);
+        app.track_edits();
+        assert!(!app.history.can_undo());
+        app.edits.mark();
+        app.track_edits();
+        app.execute(Command::ExcludeChoice(1));
+        app.track_edits();
+
+        app.undo();
+        assert!(!app.search.choice_names[1].excluded);
+        assert_eq!(app.search.source_names[0].manual_choice, Some(None));
+        app.undo();
+        assert_eq!(app.search.source_names[0].manual_choice, None);
+        assert!(!app.history.can_undo());
+
+        app.redo();
+        app.redo();
+        assert_eq!(app.search.source_names[0].manual_choice, Some(None));
+        assert!(app.search.choice_names[1].excluded);
+        assert!(!app.history.can_redo());
+    }
+
+    #[test]
+    fn reserving_a_choice_drops_it_from_other_picks() {
+        let mut app = app_with(
+            &["Alien.mkv", "Aliens.mkv"],
+            &["alien.mkv", "alien.copy.mkv"],
+        );
+        app.search.source_names[1].set_choice(Some(0));
+
+        app.execute(Command::ReserveChoice {
+            source: 0,
+            choice: 0,
+        });
+        assert_eq!(app.search.source_names[0].current_choice(), Some(0));
+        assert_eq!(app.search.source_names[1].manual_choice, None);
+        assert_ne!(app.search.source_names[1].current_choice(), Some(0));
+    }
+
+    #[test]
+    fn kind_and_group_limit_candidates() {
+        let mut app = MainApp::default();
+        for (name, group) in [
+            ("Alien.mkv", "Films"),
+            ("Alien.srt", "Films"),
+            ("Alien.mp4", "Extras"),
+            ("Alien", ""),
+        ] {
+            app.search.add_choice_name(name.to_owned());
+            app.search.choice_names.last_mut().unwrap().group = group.to_owned();
+        }
+        let index = ChoiceIndex::new(&app.search.choice_names);
+        let candidates = |kind, group| index.candidates(kind, group).map(|c| c.into_owned());
+        assert_eq!(candidates("", None), None);
+        assert_eq!(candidates("video", None), Some(vec![0, 2]));
+        assert_eq!(candidates("", Some("Films")), Some(vec![0, 1]));
+        assert_eq!(candidates("video", Some("Films")), Some(vec![0]));
+        assert_eq!(candidates("audio", Some("Films")), Some(vec![]));
+
+        app.search.options.candidate_kind = "video".to_owned();
+        app.search.source_names.push(SourceName {
+            file: file("alien.en.srt"),
+            ..Default::default()
+        });
+        app.search.update_all();
+        let source = &app.search.source_names[0];
+        assert!(source.choice_map.iter().all(|(c, _)| [0, 2].contains(c)));
+        assert_eq!(source.current_choice(), Some(0));
+    }
+
+    #[test]
+    fn large_lists_warn() {
+        let mut search = FuzzySearch::default();
+        let empty = search.memory_used();
+        for index in 0..10_000 {
+            search.add_choice_name(format!("Choice {index}"));
+            search.source_names.push(SourceName {
+                file: file(&format!("source {index}.mkv")),
+                ..Default::default()
+            });
+        }
+        let entries = size_of::<SourceName>() + size_of::<ChoiceName>();
+        assert!(search.memory_used() > empty + 10_000 * entries);
+        assert!(search.size_warning().is_some());
+
+        search.source_names.truncate(100);
+        assert!(search.size_warning().is_none());
+    }
+
+    #[test]
+    fn algorithms_saved_before_the_match_options_are_kept() {
+        let mut search: FuzzySearch =
+            serde_json::from_str(r#"{"algorithm": "Levenshtein", "workers": 2}"#).unwrap();
+        search.upgrade_settings();
+        assert!(search.options.algorithm == SearchAlgorithm::Levenshtein);
+
+        let saved = serde_json::to_value(&search).unwrap();
+        assert!(saved.get("algorithm").is_none());
+        let mut reloaded: FuzzySearch = serde_json::from_value(saved).unwrap();
+        reloaded.upgrade_settings();
+        assert!(reloaded.options.algorithm == SearchAlgorithm::Levenshtein);
+    }
+
+    #[test]
+    fn corrections_suggest_normalizations() {
+        let mut app = app_with(
+            &["Final Fantasy 7", "Final Fantasy VI"],
+            &["Final Fantasy VII"],
+        );
+        app.search.source_names[0].set_choice(Some(0));
+        let report = app.search.learn_from_corrections();
+        assert_eq!((report.total, report.current, report.best_count), (1, 0, 1));
+        assert!(report.best.roman_numerals);
+        assert_eq!(report.changes, ["Roman numerals on"]);
+    }
+
+    #[test]
+    fn matches_are_streamed_a_line_per_source() {
+        let folder =
+            std::env::temp_dir().join(format!("fuzzy_rename_stream_{}", std::process::id()));
+        let (sources, choices) = (folder.join("sources"), folder.join("choices"));
+        for (side, names) in [
+            (&sources, ["alien.mkv", "heat.1995.mkv"]),
+            (&choices, ["Alien.mkv", "Heat.mkv"]),
+        ] {
+            fs::create_dir_all(side).unwrap();
+            for name in names {
+                fs::write(side.join(name), b"").unwrap();
+            }
+        }
+        let mut out = vec![];
+        let count = MainApp::stream_matches(&sources, &choices, &mut out);
+        let missing = MainApp::stream_matches(&folder.join("missing"), &choices, &mut vec![]);
+        fs::remove_dir_all(&folder).unwrap();
+
+        assert_eq!(count.unwrap(), 2);
+        let text = String::from_utf8(out).unwrap();
+        let mut lines: Vec<&str> = text.lines().collect();
+        lines.sort();
+        assert!(lines[0].starts_with("alien.mkv\tAlien.mkv\t"));
+        assert!(lines[1].starts_with("heat.1995.mkv\tHeat.mkv\t"));
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn low_memory_bounds_the_candidates_kept() {
+        let mut app = app_with(&[], &["heat.mkv"]);
+        for index in 0..20 {
+            app.search.add_choice_name(format!("Heat {index}.mkv"));
+        }
+        app.search.update_all();
+        assert_eq!(
+            app.search.source_names[0].choice_map.len(),
+            CHOICE_PREVIEW_COUNT
+        );
 
-                ui.menu_button("Output", |ui| {
-                    ui.weak("Results of fuzzy rename");
-                    ui.weak("Files are copied to output");
+        app.search.options.kept_candidates = 15;
+        app.search.update_all();
+        assert_eq!(app.search.source_names[0].choice_map.len(), 15);
 
-                    ui.separator();
+        app.search.options.low_memory = true;
+        app.search.update_all();
+        let kept = &app.search.source_names[0].choice_map;
+        assert_eq!(kept.len(), LOW_MEMORY_PREVIEW_COUNT);
+    }
 
-                    ui.toggle_value(&mut self.keep_extension, "Keep extensions");
+    #[test]
+    fn import_summary_tells_what_was_left_out() {
+        let mut summary = ImportSummary::new(Path::new("Films"));
+        summary.record(PathBuf::from("Films/Alien.mkv"), Ok(()));
+        summary.record(PathBuf::from("Films/Alien.mkv"), Err(Skipped::Duplicate));
+        assert_eq!((summary.added, summary.duplicates()), (1, 1));
+        assert!(!summary.has_details());
 
-                    ui.weak("Files to copy:");
-                    ui.radio_value(&mut self.side_to_copy, SideToUse::Choices, "Rename Choices");
-                    ui.radio_value(&mut self.side_to_copy, SideToUse::Sources, "Rename Sources");
+        summary.record(PathBuf::from("Films/Extras"), Err(Skipped::Filtered));
+        assert!(summary.has_details());
+        assert_eq!(
+            summary.message("file(s)"),
+            "Added 1 file(s), skipped 1 duplicate(s)"
+        );
+        summary.record(PathBuf::from("Films/Broken.mkv"), Err(Skipped::Unreadable));
+        assert_eq!(summary.added, 1);
+        assert_eq!(
+            summary.message("file(s)"),
+            "Added 1 file(s), skipped 1 duplicate(s), 1 could not be read"
+        );
+        assert_eq!(
+            summary.skipped(Skipped::Filtered).collect::<Vec<_>>(),
+            [&PathBuf::from("Films/Extras")]
+        );
+        assert_eq!(summary.skipped(Skipped::Unreadable).count(), 1);
+    }
 
-                    ui.separator();
+    #[test]
+    fn folder_entries_are_scored_when_read() {
+        let folder = std::env::temp_dir().join(format!("fuzzy_rename_read_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("the.matrix.1999.mkv");
+        fs::write(&path, b"").unwrap();
 
-                    ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")), |ui| {
-                        if ui.button("Copy results to folder").on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP).clicked() {
-                            let folder = pick_folder(&self.renames_path, "Choose a folder to copy renamed files to");
+        let mut app = MainApp::default();
+        app.execute(Command::AddListedChoices {
+            names: vec![import::Listed::new(
+                "The Matrix (1999).mkv".to_owned(),
+                None,
+            )],
+            group: "List".to_owned(),
+        });
+        let reader = app.entry_reader(SideToUse::Sources);
+        let read = reader.read(path.clone());
+        app.import_entries = ImportEntries::Folders;
+        let filtered = app.entry_reader(SideToUse::Sources).read(path);
+        fs::remove_dir_all(&folder).unwrap();
 
-                            if let Some(folder) = folder {
-                                self.renames_path = folder.to_str().unwrap().to_owned();
+        match read {
+            Ok(ReadEntry::Source(source)) => assert_eq!(source.current_choice(), Some(0)),
+            _ => panic!("the file should be read as a source"),
+        }
+        assert!(matches!(filtered, Err(Skipped::Filtered)));
+    }
 
-                                let mut copy_count = 0usize;
-                                let mut replace_count = 0usize;
-                                let mut failed_count = 0usize;
+    #[test]
+    fn dat_sets_keep_their_archive_name_from_either_import() {
+        let folder = std::env::temp_dir().join(format!("fuzzy_rename_list_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let dat = folder.join("Console.dat");
+        let text = r#"<datafile><game name="Pong"><rom crc="0000002a"/></game></datafile>"#;
+        fs::write(&dat, text).unwrap();
 
-                                for (file_origin, new_name) in self.iter_renames(self.copy_failed_sources) {
-                                    let destination = folder.join(new_name);
+        let mut app = MainApp::default();
+        app.import_list(&dat, import::ListKind::Names);
+        app.import_list(&dat, import::ListKind::Dat);
+        fs::remove_dir_all(&folder).unwrap();
 
-                                    match destination.try_exists().and_then(|overwrite| {
-                                        fs::copy(file_origin, &destination).map(|_| overwrite)
-                                    }) {
-                                        Ok(true) => {
-                                            replace_count += 1;
-                                            copy_count += 1;
-                                        }
-                                        Ok(false) => {
-                                            copy_count += 1;
-                                        }
-                                        Err(error) => {
-                                            eprintln!("Could not copy file: {} ({:?} -> {:?})", error, file_origin, destination.to_str());
-                                            failed_count += 1;
-                                        }
-                                    }
-                                }
+        let names: Vec<&str> = app
+            .search
+            .choice_names
+            .iter()
+            .map(|c| c.file.name.as_str())
+            .collect();
+        assert_eq!(names, ["Pong.zip", "Pong.zip"]);
+        let choice = &app.search.choice_names[0];
+        assert_eq!(
+            (choice.file.crc, choice.group.as_str()),
+            (Some(42), "Console")
+        );
+        assert!(app.edits.unsaved());
+    }
 
-                                let mut results: Vec<String> = Vec::with_capacity(3);
-                                if copy_count > 0 {
-                                    results.push(format!("{copy_count} Copied"));
-                                }
-                                if replace_count > 0 {
-                                    results.push(format!("{replace_count} Replaced"));
-                                }
-                                if failed_count > 0 {
-                                    results.push(format!("{failed_count} Failed"));
-                                }
-                                self.status = AppStatus::Notice(results.join(" | "));
-                            }
-                        }
-                    });
+    #[test]
+    fn checksums_read_later_rescore() {
+        let mut app = MainApp::default();
+        app.search.options.checksums = true;
+        for name in ["Alien.mkv", "Heat.mkv"] {
+            app.search.choice_names.push(ChoiceName {
+                file: file(name),
+                ..Default::default()
+            });
+        }
+        app.search.source_names.push(SourceName {
+            file: file("x.mkv"),
+            ..Default::default()
+        });
+        app.search.update_all();
+        assert_eq!(app.search.unread_checksums(&HashSet::new()).len(), 3);
 
-                    if self.side_to_copy == SideToUse::Sources {
-                        ui.toggle_value(
-                            &mut self.copy_failed_sources,
-                            "Include missing results",
-                        );
+        let crcs = HashMap::from([(PathBuf::from("x.mkv"), 7), (PathBuf::from("Heat.mkv"), 7)]);
+        app.search.set_checksums(&crcs);
+        app.search.update_all();
+        assert_eq!(app.search.source_names[0].current_choice(), Some(1));
+        let unreadable = HashSet::from([PathBuf::from("Alien.mkv")]);
+        assert!(app.search.unread_checksums(&unreadable).is_empty());
+    }
 
-                        ui.separator();
+    #[test]
+    fn exif_is_read_once_a_template_uses_it() {
+        let mut app = app_with(&[], &["IMG_0001.jpg"]);
+        assert!(app.unread_exif().is_empty());
 
-                        // TODO: Add output compatible with WASM
+        app.name_template = "{exif_date} {name}".to_owned();
+        assert_eq!(app.unread_exif(), [PathBuf::from("IMG_0001.jpg")]);
+        let exif = exif::Exif {
+            date: Some("2023-07-14".to_owned()),
+            camera: None,
+        };
+        app.set_exif(vec![(PathBuf::from("IMG_0001.jpg"), exif)]);
+        assert!(app.unread_exif().is_empty());
+    }
 
-                        ui.add_enabled_ui(cfg!(not(target_arch = "wasm32")), |ui| {
-                            ui.menu_button("Directly rename files", |ui| {
-                                ui.label("Are you sure?");
-                                if ui.button("Yes").clicked() {
-                                    let mut rename_count = 0usize;
-                                    let mut replace_count = 0usize;
-                                    let mut failed_count = 0usize;
-
-                                    for (file_origin, new_name) in self.iter_renames(false) {
-                                        if let Some(destination) = file_origin.parent().map(|p| p.join(new_name)) {
-                                            match destination.try_exists().and_then(|overwrite| {
-                                                fs::rename(file_origin, &destination).map(|_| overwrite)
-                                            }) {
-                                                Ok(true) => {
-                                                    replace_count += 1;
-                                                    rename_count += 1;
-                                                }
-                                                Ok(false) => {
-                                                    rename_count += 1;
-                                                }
-                                                Err(error) => {
-                                                    eprintln!("Could not rename file: {} ({:?} -> {:?})", error, file_origin, destination.to_str());
-                                                    failed_count += 1;
-                                                }
-                                            }
-                                        } else {
-                                            eprintln!("Could not rename file: Malformed parent in filepath ({:?})", file_origin);
-                                            failed_count += 1;
-                                        }
-                                    }
+    #[test]
+    fn settings_changes_rescore_in_the_background() {
+        let ctx = Context::default();
+        let mut app = app_with(&[], &["the.matrix.1999.mkv"]);
+        app.execute(Command::AddListedChoices {
+            names: vec![
+                import::Listed::new("The Matrix (1999).mkv".to_owned(), None),
+                import::Listed::new("Heat (1995).mkv".to_owned(), None),
+            ],
+            group: "List".to_owned(),
+        });
+        let before = app.search.source_names[0].choice_map.clone();
 
-                                    let mut results: Vec<String> = Vec::with_capacity(3);
-                                    if rename_count > 0 {
-                                        results.push(format!("{rename_count} Renamed"));
-                                    }
-                                    if replace_count > 0 {
-                                        results.push(format!("{replace_count} Replaced"));
-                                    }
-                                    if failed_count > 0 {
-                                        results.push(format!("{failed_count} Failed"));
-                                    }
-                                    self.status = AppStatus::Notice(results.join(" | "));
-                                }
-                            }).response.on_disabled_hover_text(WASM_NO_FOLDERS_TOOLTIP);
-                        });
-                    }
-                });
+        app.search.options.algorithm = SearchAlgorithm::Levenshtein;
+        app.rescore();
+        app.poll_rescore(&ctx);
+        while app.pending_score.is_some() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            app.poll_rescore(&ctx);
+        }
+        let scored = app.search.source_names[0].choice_map.clone();
+        assert_ne!(scored, before);
+        app.search.update_all();
+        assert_eq!(app.search.source_names[0].choice_map, scored);
+    }
 
-                ui.separator();
+    #[test]
+    fn unreadable_files_are_not_added() {
+        let mut app = MainApp::default();
+        let missing = std::env::temp_dir().join("fuzzy_rename_missing_file.mkv");
+        let mut known = HashSet::new();
+        assert!(matches!(
+            app.search
+                .add_source(missing.clone(), &ChoiceIndex::default(), &mut known),
+            Err(Skipped::Unreadable)
+        ));
+        assert!(matches!(
+            app.search.add_choice(missing, &mut known),
+            Err(Skipped::Unreadable)
+        ));
+        assert!(app.search.source_names.is_empty());
+        assert!(app.search.choice_names.is_empty());
+        assert!(known.is_empty());
+    }
 
-                // Options
+    #[test]
+    fn shared_matches_are_counted() {
+        let mut app = app_with(
+            &["Alien.mkv", "Heat.mkv"],
+            &["alien.mkv", "aliens.mkv", "heat.mkv"],
+        );
+        assert_eq!(app.match_usage(), [2, 2, 1]);
 
-                ui.menu_button("Options", |ui| {
-                    ui.horizontal(|ui| {
-                        ui.add(Slider::new(&mut self.threshold, 0.0..=1.0).text("Similarity"));
-                    });
+        app.search.source_names[2].set_choice(None);
+        assert_eq!(app.match_usage(), [2, 2, 0]);
+    }
 
-                    let mut changed;
-                    ui.weak("Search Algorithm:");
-                    changed = ui
-                        .radio_value(&mut self.search.algorithm, SearchAlgorithm::Jaro, "Jaro")
-                        .changed();
-                    changed = ui
-                        .radio_value(
-                            &mut self.search.algorithm,
-                            SearchAlgorithm::JaroWinkler,
-                            "Jaro Winkler",
-                        )
-                        .changed()
-                        | changed;
-                    changed = ui
-                        .radio_value(
-                            &mut self.search.algorithm,
-                            SearchAlgorithm::Levenshtein,
-                            "Levenshtein",
-                        )
-                        .changed()
-                        | changed;
-                    changed = ui
-                        .radio_value(
-                            &mut self.search.algorithm,
-                            SearchAlgorithm::DamerauLevenshtein,
-                            "Damerau Levenshtein",
-                        )
-                        .changed()
-                        | changed;
-                    if changed {
-                        self.search.update_all();
-                        self.status = AppStatus::Info("Updated search algorithm".to_owned());
-                    }
+    #[test]
+    fn vetoed_outputs_run_nothing() {
+        let mut app = MainApp::default();
+        let shared = FilePath {
+            canonical: PathBuf::from("Films/alien.mkv"),
+            ..file("Films/alien.mkv")
+        };
+        app.search.source_names.push(SourceName {
+            file: shared.clone(),
+            ..Default::default()
+        });
+        app.search.choice_names.push(ChoiceName {
+            file: shared,
+            ..Default::default()
+        });
+        app.output_targets.push(OutputTarget {
+            action: OutputAction::WriteLog,
+            path: "never-written.log".to_owned(),
+            enabled: true,
+        });
+        assert!(app.execute_outputs(false).is_err());
+        assert!(!Path::new("never-written.log").exists());
+    }
 
-                    ui.separator();
+    #[test]
+    fn overlapping_files_are_found() {
+        let mut app = MainApp::default();
+        let at = |path: &str| FilePath {
+            canonical: PathBuf::from(path),
+            ..file(path)
+        };
+        app.search.source_names.push(SourceName {
+            file: at("Films/alien.mkv"),
+            ..Default::default()
+        });
+        app.search.choice_names.push(ChoiceName {
+            file: at("Names/Alien.mkv"),
+            ..Default::default()
+        });
+        assert_eq!(app.shared_files_warning(), None);
+        assert!(app.output_folder_warning(Path::new("Films")).is_some());
+        assert!(app.output_folder_warning(Path::new("Names")).is_some());
+        assert_eq!(app.output_folder_warning(Path::new("Output")), None);
 
-                    ui.weak("Window Theme:");
-                    let mut changed;
-                    changed = ui
-                        .radio_value(&mut self.window_theme, WindowTheme::Light, "Light")
-                        .changed();
-                    changed = ui
-                        .radio_value(&mut self.window_theme, WindowTheme::Dark, "Dark")
-                        .changed()
-                        | changed;
-                    if changed {
-                        Self::set_window_theme(&ctx, &self.window_theme)
-                    }
-                });
+        app.search.choice_names.push(ChoiceName {
+            file: at("Films/alien.mkv"),
+            ..Default::default()
+        });
+        assert!(app.shared_files_warning().is_some());
+    }
 
-                ui.add_space(50.0);
+    #[test]
+    fn status_bar_counts_matched_and_pending_sources() {
+        let mut app = app_with(
+            &["Alien.mkv", "Heat.mkv", "Up.mkv"],
+            &["alien.mkv", "heat.mkv", "up.mkv", "zodiac.mkv"],
+        );
+        app.threshold = 0.0;
+        app.trust_threshold = 1.0;
+        app.search.source_names[0].set_choice(Some(0));
+        app.search.source_names[3].set_choice(None);
 
-                ui.horizontal(|ui| {
-                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
-                        // Little helper in case it's a debug build
-                        warn_if_debug_build(ui);
+        let counts = app.status_counts();
+        assert_eq!((counts.sources, counts.choices), (4, 3));
+        assert_eq!(counts.matched, 1);
+        assert_eq!(counts.pending, 2);
+    }
 
-                        match &self.status {
-                            AppStatus::None => {}
-                            AppStatus::Info(message) => {
-                                ui.weak(message);
-                            }
-                            AppStatus::Notice(message) => {
-                                ui.strong(message);
-                            }
-                            // AppStatus::Progress(message, value) => {
-                            //     ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
-                            //         let progress_bar = ProgressBar::new(*value).show_percentage();
-                            //         ui.weak(message);
-                            //         ui.add(progress_bar);
-                            //     });
-                            // }
-                        }
-                    });
-                });
+    #[test]
+    fn confirmations_list_affected_files() {
+        let mut app = MainApp::default();
+        for name in ["Alien", "Heat"] {
+            app.search.add_choice_name(format!("{name}.mkv"));
+        }
+        for name in ["alien.mkv", "heat.mkv", "up.mkv"] {
+            app.search.source_names.push(SourceName {
+                file: FilePath {
+                    path: Path::new("Films").join(name),
+                    ..file(name)
+                },
+                ..Default::default()
             });
-        });
+        }
+        app.search.update_all();
+        app.search.source_names[0].set_choice(Some(0));
+        app.search.source_names[2].set_choice(None);
+        app.side_to_copy = SideToUse::Sources;
 
-        // Table
+        let clear = app.clear_sources_confirmation();
+        assert_eq!(clear.files.len(), 3);
+        assert!(clear.summary.starts_with("3 source(s)"));
+        assert!(clear.summary.contains("1 with a picked match"));
+        let clear = app.clear_choices_confirmation();
+        assert!(clear.summary.contains("2 source(s) lose their match"));
 
-        CentralPanel::default().show(ctx, |ui| {
-            ui.style_mut().wrap = Some(false);
+        let rename = app.rename_confirmation();
+        assert!(rename.summary.starts_with("2 file(s)"));
+        assert_eq!(rename.files[0], "alien.mkv \u{2192} Alien.mkv");
+    }
 
-            enum ListTask {
-                None,
-                RemoveRow(usize),
-            }
+    #[test]
+    fn panels_list_unmatched_choices_and_earlier_statuses() {
+        let mut app = app_with(&["Alien.mkv", "Heat.mkv", "Up.mkv"], &["alien.mkv"]);
+        app.execute(Command::ExcludeChoice(2));
+        assert_eq!(app.unmatched_choices(), [1]);
 
-            let mut task = ListTask::None;
+        app.update_output_cache();
+        assert!(app.output_cache.unmatched.is_empty());
+        app.panels.preview = true;
+        app.panels.unmatched = true;
+        app.update_output_cache();
+        assert_eq!(app.output_cache.unmatched, [1]);
+        assert_eq!(app.output_cache.renames.len(), 1);
+        app.execute(Command::ExcludeChoice(0));
+        app.update_output_cache();
+        let renames = &app.output_cache.renames;
+        assert!(renames.iter().all(|(_, name, _)| name != "Alien.mkv"));
+        app.execute(Command::ReserveChoice {
+            source: 0,
+            choice: 1,
+        });
+        app.name_prefix = "New ".to_owned();
+        app.update_output_cache();
+        assert_eq!(app.output_cache.renames[0].1, "New Heat.mkv");
+        assert!(app.output_cache.unmatched.is_empty());
 
-            TableBuilder::new(ui)
-                .striped(true)
-                .auto_shrink([false; 2])
-                .column(
-                    Column::remainder()
-                        .clip(true)
-                        .at_least(100.0)
-                        .resizable(true),
-                )
-                .column(
-                    Column::initial(60.0)
-                        .range(35.0..=60.0)
-                        .clip(true)
-                        .resizable(true),
-                )
-                .column(
-                    Column::remainder()
-                        .clip(true)
-                        .at_least(100.0)
-                        .resizable(true),
-                )
-                .column(Column::remainder().clip(true).at_least(100.0))
-                .header(20.0, |mut header| {
-                    header.col(|ui| {
-                        ui.label("Source Name");
-                    });
-                    header.col(|ui| {
-                        ui.label("Similarity");
-                    });
-                    header.col(|ui| {
-                        ui.label("Closest Match");
-                    });
-                    header.col(|ui| {
-                        ui.label(format!(
-                            "Renamed File ({})",
-                            match self.side_to_copy {
-                                SideToUse::Choices => "Choices",
-                                SideToUse::Sources => "Sources",
-                            }
-                        ));
-                    });
-                })
-                .body(|body| {
-                    body.rows(
-                        20.0,
-                        self.search.source_names.len(),
-                        |row_index, mut row| {
-                            let item = &mut self.search.source_names[row_index];
+        app.status = AppStatus::Info("Added 3 file(s)".to_owned());
+        app.log_status();
+        app.log_status();
+        app.status = AppStatus::None;
+        app.log_status();
+        app.status = AppStatus::Notice("Nothing to copy".to_owned());
+        app.log_status();
+        assert_eq!(app.log.len(), 2);
+    }
 
-                            // Source Name
+    #[test]
+    fn table_outputs_are_worked_out_again_after_a_change() {
+        let mut app = app_with(&["Heat.mkv"], &["heat.mkv", "heat.1995.mkv"]);
+        for source in app.search.source_names.iter_mut() {
+            source.set_choice(Some(0));
+        }
+        app.name_template = "{name}".to_owned();
+        app.columns.collision = true;
+        app.update_output_cache();
+        assert_eq!(app.output_cache.numbers, [None, None]);
+        assert_eq!(app.output_cache.collisions, [true, true]);
+        assert!(app.output_cache.usage.is_empty());
 
-                            let item_name = item.file.name.clone();
-                            row.col(|ui| {
-                                ui.label(&item_name);
-                            });
+        app.name_template = "{name} {n}".to_owned();
+        app.update_output_cache();
+        assert!(app.output_cache.numbers.iter().all(Option::is_some));
+        assert_eq!(app.output_cache.collisions, [false, false]);
+    }
 
-                            // Similarity
+    #[test]
+    fn replacing_choices_keeps_picks_of_files_imported_again() {
+        let mut app = MainApp::default();
+        let at = |path: &str| FilePath {
+            canonical: PathBuf::from(path),
+            ..file(path)
+        };
+        for name in ["Names/Alien.mkv", "Names/Heat.mkv"] {
+            app.search.choice_names.push(ChoiceName {
+                file: at(name),
+                ..Default::default()
+            });
+        }
+        for name in ["Films/alien.mkv", "Films/heat.mkv", "Films/heat.srt"] {
+            app.search.source_names.push(SourceName {
+                file: at(name),
+                ..Default::default()
+            });
+        }
+        // Picked the wrong way around on purpose, so only a kept pick puts them there
+        app.search.source_names[0].set_choice(Some(1));
+        app.search.source_names[1].set_choice(Some(0));
+        app.search.source_names[2].set_choice(Some(1));
+        app.search.remove_source(2);
+        app.search.choice_names[1].rename_to = Some("Heat (1995).mkv".to_owned());
 
-                            let current_score = item.current_score();
-                            let below_threshold =
-                                current_score.map_or(false, |s| s < self.threshold);
+        app.import_mode = ImportMode::Replace;
+        let replaced = app.replace_for_import(SideToUse::Choices).unwrap();
+        assert!(app.search.choice_names.is_empty());
+        for name in ["New/Up.mkv", "Names/Heat.mkv"] {
+            app.search.choice_names.push(ChoiceName {
+                file: at(name),
+                ..Default::default()
+            });
+        }
+        app.search.restore_replaced(replaced, false);
 
-                            let choice_similarity = if let Some(value) = current_score {
-                                format!("{:2.0}%", 100.0 * value)
-                            } else {
-                                "N/A".to_owned()
-                            };
+        let names = &app.search.choice_names;
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[1].rename_to.as_deref(), Some("Heat (1995).mkv"));
+        assert_eq!(app.search.source_names[0].manual_choice, Some(Some(1)));
+        // Its pick is gone, it is matched again
+        assert_eq!(app.search.source_names[1].manual_choice, None);
+        assert_eq!(app.search.removed_sources[0].manual_choice, Some(Some(1)));
+    }
 
-                            row.col(|ui| {
-                                ui.menu_button(choice_similarity, |ui| {
-                                    ui.set_min_size(Vec2::new(250.0, 0.0));
+    #[test]
+    fn unfinished_replacing_imports_keep_the_old_list() {
+        let mut app = app_with(&["Alien.mkv", "Heat.mkv"], &["heat.mkv"]);
+        app.search.source_names[0].set_choice(Some(0));
+        app.search.source_names[0].locked = true;
 
-                                    // TODO: Add match picker window
-                                    // if ui.button("Search for a match").clicked() { }
+        app.import_mode = ImportMode::Replace;
+        let replaced = app.replace_for_import(SideToUse::Choices).unwrap();
+        app.search.add_choice_name("Partial.mkv".to_owned());
+        app.search.restore_replaced(replaced, true);
 
-                                    ui.weak("Pick a match:");
+        let names: Vec<&str> = app
+            .search
+            .choice_names
+            .iter()
+            .map(|c| c.file.name.as_str())
+            .collect();
+        assert_eq!(names, ["Alien.mkv", "Heat.mkv"]);
+        let source = &app.search.source_names[0];
+        assert_eq!((source.manual_choice, source.locked), (Some(Some(0)), true));
+    }
 
-                                    for (c_index, c_score) in item.choice_map.clone() {
-                                        let btn = RadioButton::new(
-                                            item.manual_choice
-                                                .flatten()
-                                                .map_or(false, |c| c == c_index),
-                                            format!(
-                                                "[{:2.2}%] {}",
-                                                100.0 * c_score,
-                                                remove_extension(
-                                                    &self.search.choice_names[c_index].name
-                                                )
-                                            ),
-                                        );
-                                        if ui.add(btn).clicked() {
-                                            item.set_choice(Some(c_index));
-                                        }
-                                    }
+    #[test]
+    fn extensions_split_without_losing_text() {
+        for name in generated_names(2000) {
+            let (body, extension) = split_extension(&name);
+            assert!(
+                (body == name && extension.is_empty()) || format!("{body}.{extension}") == name,
+                "{name:?} split as {body:?} and {extension:?}"
+            );
+            assert!(name.starts_with(remove_extension(&name)));
+        }
+    }
 
-                                    let btn = RadioButton::new(
-                                        item.manual_choice.map_or(false, |c| c.is_none()),
-                                        "[Don't use match]",
-                                    );
-                                    if ui.add(btn).clicked() {
-                                        item.set_choice(None);
-                                    }
+    #[test]
+    fn presets_render_names_their_servers_read() {
+        let expected = [
+            "The Matrix (1999)",
+            "Show - S02E05",
+            "The Matrix (1999)",
+            "Show S02E05",
+            "The Matrix (1999)",
+            "Show S02E05",
+            "03 - Song",
+        ];
+        for (preset, expected) in OUTPUT_PRESETS.iter().zip(expected) {
+            let body = match preset.convention {
+                Convention::Music => "Song",
+                _ if preset.label.ends_with("movies") => "The Matrix 1999",
+                _ => "Show s2e5 Title",
+            };
+            let name = apply_template(preset.name_template, body, body, body, Some(3), None);
+            assert_eq!(name, expected, "{}", preset.label);
 
-                                    ui.add_enabled_ui(item.manual_choice.is_some(), |ui| {
-                                        if ui.button("Reset to default").clicked() {
-                                            item.reset_choice();
-                                        }
-                                    });
+            let mut rules = NamingRules::default();
+            rules.set_convention(preset.convention);
+            assert!(rules.problems(&name).is_empty(), "{}", preset.label);
+        }
+        // A name without a year keeps the token, so the naming rules flag it
+        assert_eq!(
+            apply_template("{title} ({year})", "Heat", "", "", None, None),
+            "Heat ({year})"
+        );
+    }
 
-                                    ui.separator();
+    #[test]
+    fn renames_keep_the_source_extension() {
+        let mut app = MainApp {
+            side_to_copy: SideToUse::Sources,
+            ..Default::default()
+        };
+        let names = generated_names(500);
+        for (index, name) in names.iter().filter(|n| !n.is_empty()).enumerate() {
+            let extension = ["mkv", "srt", "flac"][index % 3];
+            let source = SourceName {
+                file: file(&format!("source {index}.{extension}")),
+                ..Default::default()
+            };
+            let choice = ChoiceName {
+                file: file(name),
+                ..Default::default()
+            };
+            app.keep_extension = index % 2 == 0;
+            let renamed = app.rename(&source, &choice, None);
+            assert_eq!(
+                split_extension(&renamed).1,
+                extension,
+                "{name:?} as {renamed:?}"
+            );
 
-                                    ui.menu_button("Remove source", |ui| {
-                                        ui.label("Are you sure?");
-                                        if ui.button("Yes").clicked() {
-                                            task = ListTask::RemoveRow(row_index);
-                                        }
-                                    })
-                                });
-                            });
+            // Renaming the result again changes nothing
+            let again = SourceName {
+                file: file(&renamed),
+                ..Default::default()
+            };
+            assert_eq!(app.rename(&again, &choice, None), renamed);
+        }
+    }
+
+    #[test]
+    fn normalizing_twice_changes_nothing() {
+        let options = MatchOptions {
+            ignore_case: true,
+            unify_separators: true,
+            ..Default::default()
+        };
+        for name in generated_names(2000) {
+            let once = options.normalize(&name).into_owned();
+            assert_eq!(options.normalize(&once), once, "{name:?}");
+        }
+    }
 
-                            // Closest Match
+    #[test]
+    fn golden_renames() {
+        let expected: HashMap<&str, &str> = include_str!("../tests/golden/renames.tsv")
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| {
+                line.split_once('\t')
+                    .unwrap_or_else(|| panic!("no tab in golden line {line:?}"))
+            })
+            .collect();
+        let choices: Vec<&str> = include_str!("../tests/golden/choices.txt")
+            .lines()
+            .collect();
+        let sources: Vec<&str> = expected.keys().copied().collect();
+        let mut app = app_with(&choices, &sources);
+        app.side_to_copy = SideToUse::Sources;
 
-                            let choice_name = item
-                                .current_choice()
-                                .filter(|_| !below_threshold)
-                                .and_then(|i| self.search.choice_names.get(i).map(|c| &c.name));
+        let renamed: HashMap<String, String> = app
+            .iter_renames(false)
+            .into_iter()
+            .map(|(path, name, _)| {
+                let path = path.to_string_lossy().into_owned();
+                (path, name.to_string_lossy().into_owned())
+            })
+            .collect();
+        for (source, rename) in expected {
+            let actual = renamed.get(source).map_or("", String::as_str);
+            assert_eq!(actual, rename, "{source}");
+        }
+    }
 
-                            row.col(|ui| {
-                                ui.label(choice_name.unwrap_or(&"".into()));
-                            });
+    #[test]
+    fn equal_scores_pick_the_same_choice_in_any_order() {
+        for names in [["abe.mkv", "abd.mkv"], ["abd.mkv", "abe.mkv"]] {
+            let app = app_with(&names, &["abc.mkv"]);
+            let source = &app.search.source_names[0];
+            let choice = source.current_choice().map(|c| &app.search.choice_names[c]);
+            assert_eq!(choice.map(|c| c.file.name.as_str()), Some("abd.mkv"));
+        }
+    }
 
-                            // Renamed File
+    #[test]
+    fn top_k_keeps_the_first_in_order() {
+        assert_eq!(top_k([5, 1, 4, 1, 3, 9, 2], 3), [1, 1, 2]);
+        assert_eq!(top_k([3, 2, 1], 5), [1, 2, 3]);
+        assert!(top_k([1, 2], 0).is_empty());
 
-                            row.col(|ui| {
-                                ui.label(choice_name.map_or("".to_owned(), |reference| {
-                                    self.rename(&item_name, &reference)
-                                }));
-                            });
-                        },
-                    );
-                });
+        // Against sorting everything, for lists of every length around k
+        let values: Vec<usize> = generated_names(300).iter().map(|n| n.len()).collect();
+        for length in 0..40 {
+            let mut sorted = values[..length].to_vec();
+            sorted.sort_unstable();
+            sorted.truncate(10);
+            assert_eq!(top_k(values[..length].iter().copied(), 10), sorted);
+        }
+    }
 
-            match task {
-                ListTask::None => {}
-                ListTask::RemoveRow(row_index) => {
-                    self.search.remove_source(row_index);
-                    self.status = AppStatus::Info("Removed 1 source".to_owned());
-                }
-            }
+    #[test]
+    fn empty_lists_change_nothing() {
+        let mut app = app_with(&[], &["a.mkv"]);
+        let status = app.execute(Command::AddListedChoices {
+            names: vec![],
+            group: "List".to_owned(),
         });
+        assert!(matches!(status, AppStatus::Notice(_)));
+        assert!(!app.edits.unsaved());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn jobs_with_missing_folders_keep_the_lists() {
+        let mut app = app_with(&[], &["a.mkv"]);
+        app.edits.mark();
+        let missing = std::env::temp_dir().join("fuzzy_rename_missing_job_folder");
+        let job = Job {
+            sources: missing.clone(),
+            choices: missing,
+            settings: MainApp::default(),
+        };
+        assert!(app.load_job(job).is_err());
+        assert_eq!(app.search.source_names.len(), 1);
+        assert!(app.edits.unsaved());
     }
 }
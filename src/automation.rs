@@ -0,0 +1,101 @@
+//! Lets other programs drive the app with JSON requests, one per line on stdin.
+//!
+//! Each request is answered with one line of JSON on stdout, e.g.
+//! `{"command": "import_sources", "path": "/videos"}` -> `{"ok":true,"message":"Added 3 source(s)"}`.
+
+use std::{io::BufRead, path::PathBuf, sync::mpsc};
+
+use crate::app::{SearchAlgorithm, SideToUse};
+
+#[derive(serde::Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// Adds a folder's files, or a single file, as sources
+    ImportSources {
+        path: PathBuf,
+    },
+    /// Adds a folder's files, or a single file, as choices
+    ImportChoices {
+        path: PathBuf,
+    },
+    ClearSources,
+    ClearChoices,
+    SetOptions {
+        algorithm: Option<SearchAlgorithm>,
+        threshold: Option<f32>,
+        keep_extension: Option<bool>,
+        side_to_copy: Option<SideToUse>,
+        name_template: Option<String>,
+    },
+    GetMapping,
+    CopyResults {
+        folder: PathBuf,
+    },
+    ExportReport {
+        path: PathBuf,
+    },
+}
+
+#[derive(serde::Serialize)]
+pub struct MappingEntry {
+    pub source: String,
+    pub choice: Option<String>,
+    pub score: Option<f32>,
+    pub rename: Option<String>,
+}
+
+#[derive(serde::Serialize, Default)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mapping: Option<Vec<MappingEntry>>,
+}
+
+impl Response {
+    pub fn success(message: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            message: Some(message.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn failure(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            message: Some(message.into()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Reads requests from stdin on a background thread, waking the UI for each one.
+pub fn listen(ctx: egui::Context) -> mpsc::Receiver<Result<Request, String>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let request = serde_json::from_str(&line).map_err(|e| e.to_string());
+            if sender.send(request).is_err() {
+                break;
+            }
+            ctx.request_repaint();
+        }
+    });
+    receiver
+}
+
+pub fn respond(response: &Response) {
+    match serde_json::to_string(response) {
+        Ok(json) => println!("{json}"),
+        Err(error) => eprintln!("Could not serialize response: {error}"),
+    }
+}
@@ -0,0 +1,89 @@
+//! Copies files on a background thread, reporting progress within each file so large ones
+//! don't leave the window frozen.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{paths, tags, task::Task};
+
+/// Progress within a file is reported at most this often
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+pub enum Progress {
+    /// `copied` of `size` bytes of copy `index` are done
+    File {
+        index: usize,
+        copied: u64,
+        size: u64,
+    },
+    /// Copy `index` finished, `Ok(true)` when it replaced a file
+    Copied(usize, io::Result<bool>),
+    /// The title tag of copy `index` was written, `Ok(false)` when its format has none
+    Tagged(usize, io::Result<bool>),
+    /// Every copy finished, or the rest were cancelled
+    Done,
+}
+
+pub struct BackgroundCopy {
+    /// Each file and where it is copied to
+    pub copies: Vec<(PathBuf, PathBuf)>,
+    task: Task<Progress>,
+}
+
+impl BackgroundCopy {
+    /// Starts copying, waking the UI whenever there is progress to show. Copies of the files
+    /// `titles` has a title for are tagged with it, see [`tags`].
+    pub fn start(
+        copies: Vec<(PathBuf, PathBuf)>,
+        titles: HashMap<PathBuf, String>,
+        ctx: egui::Context,
+    ) -> Self {
+        let thread_copies = copies.clone();
+        let task = Task::spawn(ctx, move |reporter| {
+            let mut exists = paths::ExistsCheck::default();
+            for (index, (from, to)) in thread_copies.iter().enumerate() {
+                if reporter.is_cancelled() {
+                    break;
+                }
+                let mut last_report = Instant::now();
+                let mut progress = |copied, size| {
+                    if last_report.elapsed() >= PROGRESS_INTERVAL {
+                        last_report = Instant::now();
+                        reporter.send(Progress::File {
+                            index,
+                            copied,
+                            size,
+                        });
+                    }
+                };
+                let result = exists.try_exists(to).and_then(|overwrite| {
+                    paths::copy_with_progress(from, to, &mut progress).map(|_| overwrite)
+                });
+                let title = titles.get(from).filter(|_| result.is_ok());
+                if !reporter.send(Progress::Copied(index, result)) {
+                    return;
+                }
+                if let Some(title) = title {
+                    reporter.send(Progress::Tagged(index, tags::write_title(to, title)));
+                }
+            }
+            reporter.send(Progress::Done);
+        });
+
+        Self { copies, task }
+    }
+
+    /// Stops after the file being copied
+    pub fn cancel(&self) {
+        self.task.cancel();
+    }
+
+    /// Everything the copy reported since the last call
+    pub fn poll(&self) -> Vec<Progress> {
+        self.task.poll()
+    }
+}
@@ -0,0 +1,191 @@
+//! CRC-32 checksums, as listed in ROM DATs and tagged in release names like `[A1B2C3D4]`, for
+//! pairing files that are certainly the same whatever their names.
+
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+const TABLE: [u32; 256] = table();
+
+const fn table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Continues the checksum `crc` (0 to start) over `bytes`
+pub fn update(crc: u32, bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!crc, |crc, byte| {
+        TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Reads the hexadecimal checksum in a DAT or tagged in a name, like `a1b2c3d4`
+pub fn parse(text: &str) -> Option<u32> {
+    (text.len() == 8)
+        .then(|| u32::from_str_radix(text, 16).ok())
+        .flatten()
+}
+
+/// The checksum tagged in `name` in brackets or parentheses, like `[A1B2C3D4]`
+pub fn from_name(name: &str) -> Option<u32> {
+    name.split(['[', '(']).skip(1).find_map(|tag| {
+        let end = tag.find([']', ')'])?;
+        parse(&tag[..end])
+    })
+}
+
+/// The checksum of the file at `path`. For a `.zip` archive, it is the one recorded for the
+/// first file inside, as ROM DATs list them.
+pub fn of_file(path: &Path) -> io::Result<u32> {
+    let mut file = fs::File::open(path)?;
+    let is_zip = path
+        .extension()
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("zip"));
+    if is_zip {
+        if let Some(crc) = first_zip_entry(&mut file)? {
+            return Ok(crc);
+        }
+        file.seek(SeekFrom::Start(0))?;
+    }
+    let mut crc = 0;
+    let mut buffer = vec![0; 1 << 16];
+    loop {
+        match file.read(&mut buffer)? {
+            0 => return Ok(crc),
+            read => crc = update(crc, &buffer[..read]),
+        }
+    }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<usize> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as usize)
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// The most of a zip's central directory read, far more than a ROM archive lists
+const MAX_DIRECTORY_SIZE: u32 = 16 << 20;
+
+/// The checksum of the first file in a zip archive, from its central directory
+fn first_zip_entry(file: &mut fs::File) -> io::Result<Option<u32>> {
+    // The end of central directory record is in the last 22 bytes, plus a comment of up to 64 KiB
+    let length = file.seek(SeekFrom::End(0))?;
+    let tail_length = length.min(22 + 0xFFFF);
+    file.seek(SeekFrom::Start(length - tail_length))?;
+    let mut tail = vec![0; tail_length as usize];
+    file.read_exact(&mut tail)?;
+    let Some(end) = tail.windows(4).rposition(|w| w == [0x50, 0x4B, 0x05, 0x06]) else {
+        return Ok(None);
+    };
+    let (Some(directory_size), Some(directory_offset)) =
+        (u32_at(&tail, end + 12), u32_at(&tail, end + 16))
+    else {
+        return Ok(None);
+    };
+
+    // A damaged record could claim more than the file holds
+    let directory_end = directory_offset as u64 + directory_size as u64;
+    if directory_end > length || directory_size > MAX_DIRECTORY_SIZE {
+        return Ok(None);
+    }
+    file.seek(SeekFrom::Start(directory_offset as u64))?;
+    let mut directory = vec![0; directory_size as usize];
+    file.read_exact(&mut directory)?;
+    let mut at = 0;
+    while u32_at(&directory, at) == Some(0x0201_4B50) {
+        let (Some(crc), Some(name_length), Some(extra_length), Some(comment_length)) = (
+            u32_at(&directory, at + 16),
+            u16_at(&directory, at + 28),
+            u16_at(&directory, at + 30),
+            u16_at(&directory, at + 32),
+        ) else {
+            break;
+        };
+        let name = directory
+            .get(at + 46..at + 46 + name_length)
+            .unwrap_or_default();
+        // Folders are listed too, with a trailing slash
+        if !name.ends_with(b"/") {
+            return Ok(Some(crc));
+        }
+        at += 46 + name_length + extra_length + comment_length;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum() {
+        assert_eq!(update(0, b"123456789"), 0xCBF4_3926);
+        assert_eq!(update(update(0, b"1234"), b"56789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(from_name("Game (USA) [A1B2C3D4]"), Some(0xA1B2_C3D4));
+        assert_eq!(from_name("[Group] Show - 01 (1f2e3d4c)"), Some(0x1F2E_3D4C));
+        assert_eq!(from_name("Game (USA) [!]"), None);
+        assert_eq!(parse("a1b2c3"), None);
+    }
+
+    /// A zip with just a central directory listing `name` with `crc`, and the end record
+    /// claiming `directory_size`
+    fn zip(name: &str, crc: u32, directory_size: u32) -> Vec<u8> {
+        let mut bytes = vec![0x50, 0x4B, 0x01, 0x02];
+        bytes.extend([0; 12]);
+        bytes.extend(crc.to_le_bytes());
+        bytes.extend([0; 8]);
+        bytes.extend((name.len() as u16).to_le_bytes());
+        bytes.extend([0; 16]);
+        bytes.extend(name.as_bytes());
+        bytes.extend([0x50, 0x4B, 0x05, 0x06]);
+        bytes.extend([0; 8]);
+        bytes.extend(directory_size.to_le_bytes());
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend([0; 2]);
+        bytes
+    }
+
+    fn first_entry_of(bytes: &[u8]) -> Option<u32> {
+        let path = std::env::temp_dir().join(format!("fuzzy_rename_zip_{}", std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        let crc = first_zip_entry(&mut fs::File::open(&path).unwrap()).unwrap();
+        let _ = fs::remove_file(&path);
+        crc
+    }
+
+    #[test]
+    fn zip_directories() {
+        let size = 46 + "game.bin".len() as u32;
+        assert_eq!(
+            first_entry_of(&zip("game.bin", 0xA1B2_C3D4, size)),
+            Some(0xA1B2_C3D4)
+        );
+        assert_eq!(
+            first_entry_of(&zip("game.bin", 0xA1B2_C3D4, 0xFFFF_FFF0)),
+            None
+        );
+    }
+}
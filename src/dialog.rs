@@ -0,0 +1,160 @@
+//! File dialogs run on a background thread, so the window keeps drawing while one is open and
+//! the choice is picked up on a later frame. On the web they wait on the browser instead, and
+//! read the chosen file there since it has no path to read later.
+
+#[cfg(target_arch = "wasm32")]
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::task::Task;
+
+pub enum Kind {
+    Folder,
+    File {
+        filter: String,
+        extensions: Vec<String>,
+    },
+    Files,
+    Save {
+        file_name: String,
+    },
+}
+
+/// What to ask for, and where the dialog starts
+pub struct Request {
+    pub kind: Kind,
+    pub directory: String,
+    pub title: String,
+}
+
+/// What was chosen in a dialog, nothing if it was cancelled
+#[derive(Default)]
+pub struct Chosen {
+    pub paths: Vec<PathBuf>,
+    /// Contents of a file chosen with [`Kind::File`] by its name, the browser gives no paths
+    #[cfg(target_arch = "wasm32")]
+    pub contents: HashMap<PathBuf, Vec<u8>>,
+}
+
+pub struct PendingDialog {
+    pub title: String,
+    task: Task<Chosen>,
+}
+
+impl PendingDialog {
+    /// Shows the dialog of `request`, waking the UI once it closes
+    pub fn open(request: Request, ctx: egui::Context) -> Self {
+        let title = request.title.clone();
+        #[cfg(not(target_arch = "wasm32"))]
+        let task = Task::spawn(ctx, move |reporter| {
+            reporter.send(block_on(show(request)));
+        });
+        #[cfg(target_arch = "wasm32")]
+        let task = Task::spawn_local(ctx, |reporter| async move {
+            reporter.send(show(request).await);
+        });
+        Self { title, task }
+    }
+
+    /// What was chosen once the dialog closed
+    pub fn poll(&self) -> Option<Chosen> {
+        // Checked first, so whatever was sent before the dialog ended is still read below
+        let running = self.task.is_running();
+        match self.task.poll().into_iter().next() {
+            Some(chosen) => Some(chosen),
+            None if !running => Some(Chosen::default()),
+            None => None,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn show(request: Request) -> Chosen {
+    let dialog = rfd::AsyncFileDialog::new()
+        .set_directory(&request.directory)
+        .set_title(&request.title);
+    let path = |handle: rfd::FileHandle| handle.path().to_path_buf();
+    let paths = match request.kind {
+        Kind::Folder => dialog.pick_folder().await.map(path).into_iter().collect(),
+        Kind::File { filter, extensions } => {
+            let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+            dialog
+                .add_filter(&filter, &extensions)
+                .pick_file()
+                .await
+                .map(path)
+                .into_iter()
+                .collect()
+        }
+        Kind::Files => dialog
+            .pick_files()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(path)
+            .collect(),
+        Kind::Save { file_name } => dialog
+            .set_file_name(&file_name)
+            .save_file()
+            .await
+            .map(path)
+            .into_iter()
+            .collect(),
+    };
+    Chosen { paths }
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn show(request: Request) -> Chosen {
+    let dialog = rfd::AsyncFileDialog::new().set_title(&request.title);
+    let mut chosen = Chosen::default();
+    match request.kind {
+        Kind::File { filter, extensions } => {
+            let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+            let picked = dialog.add_filter(&filter, &extensions).pick_file().await;
+            if let Some(handle) = picked {
+                let path = PathBuf::from(handle.file_name());
+                chosen.contents.insert(path.clone(), handle.read().await);
+                chosen.paths.push(path);
+            }
+        }
+        // Only the names are used, reading every media file would take a while
+        Kind::Files => {
+            let picked = dialog.pick_files().await.unwrap_or_default();
+            chosen.paths = picked
+                .iter()
+                .map(|handle| PathBuf::from(handle.file_name()))
+                .collect();
+        }
+        // Browsers can't choose folders, or where to save
+        Kind::Folder | Kind::Save { .. } => {}
+    }
+    chosen
+}
+
+/// Runs `future` on the current thread, sleeping until it is woken
+#[cfg(not(target_arch = "wasm32"))]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::{
+        sync::Arc,
+        task::{Context, Poll, Wake, Waker},
+        thread::{self, Thread},
+    };
+
+    struct Unpark(Thread);
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
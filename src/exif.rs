@@ -0,0 +1,239 @@
+//! Capture metadata of photos, from the EXIF block of JPEGs and the tags of TIFF-based raw
+//! files, for naming them after when and with what they were taken.
+
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// How much of a TIFF-based file is read, its tags are near the start
+const TIFF_HEAD: u64 = 1 << 20;
+
+const MAKE: u16 = 0x010F;
+const MODEL: u16 = 0x0110;
+const DATE_TIME: u16 = 0x0132;
+/// Points to the IFD with the capture details
+const EXIF_IFD: u16 = 0x8769;
+const DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+#[derive(Clone, Default)]
+pub struct Exif {
+    /// When the photo was taken, like `2023-07-14`
+    pub date: Option<String>,
+    /// Camera maker and model, like `Canon EOS 5D`
+    pub camera: Option<String>,
+}
+
+/// Reads the capture metadata of a JPEG or TIFF-based image. Other files, and images without
+/// any, give an empty [`Exif`].
+pub fn read(path: &Path) -> io::Result<Exif> {
+    let mut file = fs::File::open(path)?;
+    let mut magic = [0; 4];
+    if file.read(&mut magic)? < 4 {
+        return Ok(Exif::default());
+    }
+    file.seek(SeekFrom::Start(0))?;
+    let tiff = match magic {
+        [0xFF, 0xD8, ..] => jpeg_exif(&mut file)?,
+        [b'I', b'I', 42, 0] | [b'M', b'M', 0, 42] => {
+            let mut head = vec![];
+            file.take(TIFF_HEAD).read_to_end(&mut head)?;
+            Some(head)
+        }
+        _ => None,
+    };
+    Ok(tiff.map_or_else(Exif::default, |tiff| parse_tiff(&tiff)))
+}
+
+/// The TIFF data of a JPEG's `APP1` EXIF segment, if it has one before the image data
+fn jpeg_exif(file: &mut fs::File) -> io::Result<Option<Vec<u8>>> {
+    file.seek(SeekFrom::Start(2))?;
+    loop {
+        let mut header = [0; 4];
+        if file.read_exact(&mut header).is_err() || header[0] != 0xFF {
+            return Ok(None);
+        }
+        let length = u16::from_be_bytes([header[2], header[3]]).saturating_sub(2);
+        match header[1] {
+            // Start of scan or end of image, the metadata comes before
+            0xDA | 0xD9 => return Ok(None),
+            0xE1 => {
+                let mut segment = vec![0; length as usize];
+                file.read_exact(&mut segment)?;
+                if let Some(tiff) = segment.strip_prefix(b"Exif\0\0") {
+                    return Ok(Some(tiff.to_vec()));
+                }
+            }
+            _ => {
+                file.seek(SeekFrom::Current(length as i64))?;
+            }
+        }
+    }
+}
+
+/// TIFF data in either byte order, with offsets from its start
+struct Tiff<'a> {
+    bytes: &'a [u8],
+    little_endian: bool,
+}
+
+impl Tiff<'_> {
+    fn u16_at(&self, at: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.bytes.get(at..at + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, at: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.bytes.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// The entry of `tag` in the IFD at `ifd`, as the offset of its 12 bytes
+    fn entry(&self, ifd: usize, tag: u16) -> Option<usize> {
+        let count = self.u16_at(ifd)? as usize;
+        (0..count)
+            .map(|index| ifd + 2 + index * 12)
+            .find(|&entry| self.u16_at(entry) == Some(tag))
+    }
+
+    /// The text of an ASCII entry, without its trailing NUL and spaces
+    fn text(&self, ifd: usize, tag: u16) -> Option<String> {
+        let entry = self.entry(ifd, tag)?;
+        const ASCII: u16 = 2;
+        if self.u16_at(entry + 2)? != ASCII {
+            return None;
+        }
+        let count = self.u32_at(entry + 4)? as usize;
+        // Values of up to 4 bytes are stored in the entry itself
+        let at = if count <= 4 {
+            entry + 8
+        } else {
+            self.u32_at(entry + 8)? as usize
+        };
+        let text = String::from_utf8_lossy(self.bytes.get(at..at + count)?);
+        let text = text.trim_end_matches('\0').trim();
+        (!text.is_empty()).then(|| text.to_owned())
+    }
+}
+
+fn parse_tiff(bytes: &[u8]) -> Exif {
+    let little_endian = bytes.starts_with(b"II");
+    let tiff = Tiff {
+        bytes,
+        little_endian,
+    };
+    let Some(ifd0) = tiff.u32_at(4).map(|offset| offset as usize) else {
+        return Exif::default();
+    };
+
+    let original = tiff
+        .entry(ifd0, EXIF_IFD)
+        .and_then(|entry| tiff.u32_at(entry + 8))
+        .and_then(|exif_ifd| tiff.text(exif_ifd as usize, DATE_TIME_ORIGINAL));
+    let date = original
+        .or_else(|| tiff.text(ifd0, DATE_TIME))
+        .and_then(|date_time| format_date(&date_time));
+
+    let make = tiff.text(ifd0, MAKE);
+    let model = tiff.text(ifd0, MODEL);
+    Exif {
+        date,
+        camera: camera_name(make.as_deref(), model.as_deref()),
+    }
+}
+
+/// `2023:07:14 18:22:05` as `2023-07-14`; unset dates are all zeros or spaces
+fn format_date(date_time: &str) -> Option<String> {
+    let date = date_time.get(..10)?;
+    let parts: Vec<&str> = date.split(':').collect();
+    let valid = matches!(parts.as_slice(), [year, month, day]
+        if year.len() == 4 && month.len() == 2 && day.len() == 2
+            && date.chars().all(|c| c == ':' || c.is_ascii_digit())
+            && *year != "0000");
+    valid.then(|| parts.join("-"))
+}
+
+/// The model, with the maker in front unless the model already names it, as in "Canon EOS 5D"
+fn camera_name(make: Option<&str>, model: Option<&str>) -> Option<String> {
+    match (make, model) {
+        (Some(make), Some(model)) => {
+            let brand = make.split_whitespace().next().unwrap_or(make);
+            let named = model
+                .get(..brand.len())
+                .map_or(false, |start| start.eq_ignore_ascii_case(brand));
+            Some(if named {
+                model.to_owned()
+            } else {
+                format!("{make} {model}")
+            })
+        }
+        (make, model) => model.or(make).map(str::to_owned),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            format_date("2023:07:14 18:22:05").as_deref(),
+            Some("2023-07-14")
+        );
+        assert_eq!(format_date("0000:00:00 00:00:00"), None);
+        assert_eq!(format_date("    :  :     :  :  "), None);
+    }
+
+    #[test]
+    fn cameras() {
+        let name = |make, model| camera_name(make, model);
+        assert_eq!(
+            name(Some("Canon"), Some("Canon EOS 5D")).as_deref(),
+            Some("Canon EOS 5D")
+        );
+        assert_eq!(
+            name(Some("NIKON CORPORATION"), Some("NIKON D750")).as_deref(),
+            Some("NIKON D750")
+        );
+        assert_eq!(
+            name(Some("Apple"), Some("iPhone 12")).as_deref(),
+            Some("Apple iPhone 12")
+        );
+        assert_eq!(name(None, Some("X100V")).as_deref(), Some("X100V"));
+    }
+
+    #[test]
+    fn tiff_tags() {
+        // Little-endian TIFF with Make, Model and DateTime in IFD0
+        let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+        let strings = 8 + 2 + 3 * 12 + 4;
+        tiff.extend_from_slice(&3u16.to_le_bytes());
+        for (tag, text_at, count) in [
+            (MAKE, strings, 6),
+            (MODEL, strings + 6, 5),
+            (DATE_TIME, strings + 11, 20),
+        ] {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&2u16.to_le_bytes());
+            tiff.extend_from_slice(&(count as u32).to_le_bytes());
+            tiff.extend_from_slice(&(text_at as u32).to_le_bytes());
+        }
+        tiff.extend_from_slice(&[0; 4]);
+        tiff.extend_from_slice(b"Canon\0EOS\0\0");
+        tiff.extend_from_slice(b"2021:12:31 23:59:59\0");
+
+        let exif = parse_tiff(&tiff);
+        assert_eq!(exif.date.as_deref(), Some("2021-12-31"));
+        assert_eq!(exif.camera.as_deref(), Some("Canon EOS"));
+    }
+}
@@ -0,0 +1,23 @@
+//! Entry points for the fuzz targets in `fuzz/`, which can only reach public items.
+
+/// Splits `name` into body and extension, as output names are
+pub fn split_extension(name: &str) -> (&str, &str) {
+    crate::app::split_extension(name)
+}
+
+/// Runs every rewrite names go through before they are compared
+pub fn normalize(name: &str) {
+    use crate::normalize::*;
+
+    let _ = unify_separators(name);
+    let _ = without_article(name, &ArticleLanguage::ALL);
+    let _ = relocate_article(name, &ArticleLanguage::ALL);
+    let _ = roman_to_digits(name);
+    let _ = years(name);
+    let _ = split_language_tags(name);
+}
+
+/// Renders an output name `template`, with `name` for every text token
+pub fn render_template(template: &str, name: &str) -> String {
+    crate::app::apply_template(template, name, name, name, Some(7), None)
+}
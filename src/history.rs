@@ -0,0 +1,231 @@
+//! Undo and redo of in-app edits, as the parts of the edited state each step changed.
+
+use std::mem;
+
+/// Steps kept before the oldest are dropped
+const LIMIT: usize = 50;
+
+pub struct History<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+}
+
+impl<T> History<T> {
+    /// Remembers how to undo an edit, forgetting anything that was undone
+    pub fn record(&mut self, step: T) {
+        if self.undo.len() >= LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(step);
+        self.redo.clear();
+    }
+
+    /// Goes back a step with `apply`, which returns the step that redoes it
+    pub fn undo(&mut self, apply: impl FnOnce(T) -> T) -> bool {
+        let Some(step) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(apply(step));
+        true
+    }
+
+    /// Goes forward a step that was undone with `apply`, which returns the step that undoes it
+    pub fn redo(&mut self, apply: impl FnOnce(T) -> T) -> bool {
+        let Some(step) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(apply(step));
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+/// Counts edits as they are made, so nothing has to be compared to notice them
+#[derive(Default)]
+pub struct Edits {
+    count: u64,
+    saved: u64,
+}
+
+impl Edits {
+    pub fn mark(&mut self) {
+        self.count += 1;
+    }
+
+    /// Takes the current state as saved or exported
+    pub fn save(&mut self) {
+        self.saved = self.count;
+    }
+
+    pub fn unsaved(&self) -> bool {
+        self.count != self.saved
+    }
+
+    /// Identifies the state after the last edit
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+/// The items of a list before an edit, or the whole list when the edit added or removed some
+pub enum ListChange<T> {
+    Unchanged,
+    Items(Vec<(usize, T)>),
+    Whole(Vec<T>),
+}
+
+impl<T: Clone> ListChange<T> {
+    /// How `current` differs from `tracked`, holding what `tracked` had. Items are compared by
+    /// `key`, which covers what can be edited of them. `tracked` is brought up to date.
+    pub fn track(tracked: &mut Vec<T>, current: &[T], key: impl Fn(&T) -> u64) -> Self {
+        if tracked.len() != current.len() {
+            return ListChange::Whole(mem::replace(tracked, current.to_vec()));
+        }
+        let items: Vec<(usize, T)> = tracked
+            .iter_mut()
+            .zip(current)
+            .enumerate()
+            .filter(|(_, (before, after))| key(before) != key(after))
+            .map(|(index, (before, after))| (index, mem::replace(before, after.clone())))
+            .collect();
+        if items.is_empty() {
+            ListChange::Unchanged
+        } else {
+            ListChange::Items(items)
+        }
+    }
+}
+
+impl<T> ListChange<T> {
+    /// Puts what was held back into `list`, returning the change that reverts this
+    pub fn apply(self, list: &mut Vec<T>) -> Self {
+        match self {
+            ListChange::Unchanged => ListChange::Unchanged,
+            ListChange::Whole(whole) => ListChange::Whole(mem::replace(list, whole)),
+            ListChange::Items(items) => ListChange::Items(
+                items
+                    .into_iter()
+                    .filter_map(|(index, item)| {
+                        let slot = list.get_mut(index)?;
+                        Some((index, mem::replace(slot, item)))
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        matches!(self, ListChange::Unchanged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(item: &&str) -> u64 {
+        item.len() as u64 * 31 + item.bytes().map(u64::from).sum::<u64>()
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_steps() {
+        let mut state = 0;
+        let mut history = History::default();
+        for step in [1, 2, 3] {
+            state += step;
+            history.record(step);
+        }
+        let mut revert = |step: i32| {
+            state -= step;
+            -step
+        };
+        assert!(history.undo(&mut revert));
+        assert!(history.undo(&mut revert));
+        assert_eq!(state, 1);
+        assert!(history.can_redo());
+
+        assert!(history.redo(|step| {
+            state -= step;
+            -step
+        }));
+        assert_eq!(state, 3);
+
+        history.record(10);
+        assert!(!history.can_redo());
+        assert!(!history.redo(|step| step));
+        assert_eq!(history.undo.len(), 3);
+    }
+
+    #[test]
+    fn only_the_latest_steps_are_kept() {
+        let mut history = History::default();
+        for step in 0..LIMIT + 5 {
+            history.record(step);
+        }
+        let mut undone = vec![];
+        while history.undo(|step| {
+            undone.push(step);
+            step
+        }) {}
+        assert_eq!(undone.len(), LIMIT);
+        assert_eq!(undone.last(), Some(&5));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn edits_are_unsaved_until_saved() {
+        let mut edits = Edits::default();
+        assert!(!edits.unsaved());
+        edits.mark();
+        assert!(edits.unsaved());
+        edits.save();
+        assert!(!edits.unsaved());
+        assert_eq!(edits.count(), 1);
+    }
+
+    #[test]
+    fn list_changes_hold_only_edited_items() {
+        let mut tracked = vec!["a", "b", "c"];
+        let mut list = vec!["a", "B", "c"];
+        let change = ListChange::track(&mut tracked, &list, key);
+        assert!(matches!(&change, ListChange::Items(items) if items == &[(1, "b")]));
+        assert_eq!(tracked, list);
+        assert!(ListChange::track(&mut tracked, &list, key).is_unchanged());
+
+        let redo = change.apply(&mut list);
+        assert_eq!(list, ["a", "b", "c"]);
+        redo.apply(&mut list);
+        assert_eq!(list, ["a", "B", "c"]);
+
+        list.push("d");
+        let change = ListChange::track(&mut tracked, &list, key);
+        assert!(matches!(&change, ListChange::Whole(whole) if whole.len() == 3));
+        change.apply(&mut list);
+        assert_eq!(list, ["a", "B", "c"]);
+    }
+
+    #[test]
+    fn items_past_the_end_are_not_put_back() {
+        let mut list = vec!["a"];
+        let change = ListChange::Items(vec![(0, "x"), (4, "y")]);
+        let redo = change.apply(&mut list);
+        assert_eq!(list, ["x"]);
+        assert!(matches!(redo, ListChange::Items(items) if items == [(0, "a")]));
+    }
+}
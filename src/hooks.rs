@@ -0,0 +1,119 @@
+//! External commands run around an output batch, like a policy check of the planned renames
+//! before anything moves, or asking a media server to rescan the folder they were copied to.
+
+use std::{
+    io::{self, Write},
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    thread,
+};
+
+/// The environment variable holding the path of `{name}`
+fn variable(name: &str) -> String {
+    format!("FUZZY_RENAME_{}", name.to_uppercase())
+}
+
+/// `command` with each `{name}` replaced by its quoted environment variable. The shell expands
+/// the path as one word and never reads it as commands, whatever characters the name has.
+fn fill_placeholders(command: &str, placeholders: &[(&str, &Path)]) -> String {
+    let mut command = command.to_owned();
+    for (name, _) in placeholders {
+        let reference = if cfg!(windows) {
+            format!("\"%{}%\"", variable(name))
+        } else {
+            format!("\"${{{}}}\"", variable(name))
+        };
+        command = command.replace(&format!("{{{name}}}"), &reference);
+    }
+    command
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+}
+
+/// Runs `command` through the system shell and waits for it to finish
+pub fn run(command: &str, placeholders: &[(&str, &Path)]) -> io::Result<ExitStatus> {
+    let mut shell = shell(&fill_placeholders(command, placeholders));
+    for (name, path) in placeholders {
+        shell.env(variable(name), path);
+    }
+    shell.status()
+}
+
+/// Runs `command` with `input` on its standard input. A failing exit code vetoes the batch,
+/// with the command's output as the reason.
+pub fn check(command: &str, input: &str) -> Result<(), String> {
+    let mut child = shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| error.to_string())?;
+    // Written from another thread, so a command that prints before reading can't block on us
+    let writer = child.stdin.take().map(|mut stdin| {
+        let input = input.to_owned();
+        thread::spawn(move || stdin.write_all(input.as_bytes()))
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|error| error.to_string())?;
+    if let Some(writer) = writer {
+        // A command may exit without reading everything, only its exit code matters
+        let _ = writer.join();
+    }
+    if output.status.success() {
+        return Ok(());
+    }
+    let mut reason = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+    if reason.is_empty() {
+        reason = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    }
+    if reason.is_empty() {
+        reason = output.status.to_string();
+    }
+    Err(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_name_variables() {
+        let command = fill_placeholders("scan {output} {other}", &[("output", Path::new("x"))]);
+        if cfg!(windows) {
+            assert_eq!(command, "scan \"%FUZZY_RENAME_OUTPUT%\" {other}");
+        } else {
+            assert_eq!(command, "scan \"${FUZZY_RENAME_OUTPUT}\" {other}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_are_not_run() {
+        let folder =
+            std::env::temp_dir().join(format!("fuzzy_rename_hooks_{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let output = folder.join("a \"$(touch injected)\"`touch injected`\\ b");
+        let written = folder.join("written");
+        let placeholders = [("output", output.as_path()), ("written", written.as_path())];
+        let status = run("printf %s {output} > {written}", &placeholders);
+        assert!(status.unwrap().success());
+        assert_eq!(
+            std::fs::read_to_string(&written).unwrap(),
+            output.to_str().unwrap()
+        );
+        assert!(!folder.join("injected").exists());
+        assert!(!Path::new("injected").exists());
+        let _ = std::fs::remove_dir_all(&folder);
+    }
+}
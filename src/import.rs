@@ -0,0 +1,422 @@
+//! Parsers for reference lists that aren't plain folders of files.
+
+/// Decodes the predefined XML entities used in attribute values.
+fn decode_xml_entities(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Returns the value of `attribute` inside a single XML start tag.
+fn xml_attribute<'a>(tag: &'a str, attribute: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(pos) = rest.find(attribute) {
+        let before = rest[..pos].chars().last();
+        let after = rest[pos + attribute.len()..].trim_start();
+        rest = &rest[pos + attribute.len()..];
+
+        if !before.map_or(false, char::is_whitespace) {
+            continue;
+        }
+        if let Some(after) = after.strip_prefix('=') {
+            let after = after.trim_start();
+            let quote = after.chars().next()?;
+            if quote != '"' && quote != '\'' {
+                return None;
+            }
+            let value = &after[1..];
+            return value.find(quote).map(|end| &value[..end]);
+        }
+    }
+    None
+}
+
+/// Reads the set names out of a Logiqx XML DAT (`<game name="...">` or `<machine name="...">`),
+/// each with the first CRC-32 listed for its `<rom>`s.
+pub fn parse_logiqx_dat(text: &str) -> Vec<(String, Option<u32>)> {
+    let mut sets: Vec<(String, Option<u32>)> = vec![];
+    let mut in_set = false;
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let end = rest.find('>').unwrap_or(rest.len());
+        let tag = &rest[..end];
+        rest = &rest[end..];
+
+        let element = tag.split(|c: char| c.is_whitespace()).next().unwrap_or("");
+        if element == "game" || element == "machine" {
+            in_set = false;
+            if let Some(name) = xml_attribute(tag, "name") {
+                sets.push((decode_xml_entities(name), None));
+                in_set = true;
+            }
+        } else if element == "rom" && in_set {
+            if let Some((_, crc @ None)) = sets.last_mut() {
+                *crc = xml_attribute(tag, "crc").and_then(crate::crc::parse);
+            }
+        } else if element == "/game" || element == "/machine" {
+            in_set = false;
+        }
+    }
+    sets
+}
+
+/// Splits one CSV line into fields, honoring double quotes and `""` escapes.
+fn split_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Reads `(current name, desired name)` pairs from the first two columns of a CSV file.
+///
+/// Semicolon and tab separated files (common spreadsheet exports) are detected from the first line.
+pub fn parse_csv_pairs(text: &str, has_header: bool) -> Vec<(String, String)> {
+    let first_line = text.lines().next().unwrap_or("");
+    let delimiter = [',', ';', '\t']
+        .into_iter()
+        .find(|d| first_line.contains(*d))
+        .unwrap_or(',');
+
+    text.lines()
+        .skip(usize::from(has_header))
+        .filter_map(|line| {
+            let mut fields = split_csv_line(line.trim_end_matches('\r'), delimiter).into_iter();
+            let current = fields.next()?.trim().to_owned();
+            let desired = fields.next()?.trim().to_owned();
+            (!current.is_empty() && !desired.is_empty()).then_some((current, desired))
+        })
+        .collect()
+}
+
+/// Reads the new names out of a mapping exported by this app (`source,choice,score,rename`),
+/// so the output of one session can be the choices of the next.
+pub fn parse_mapping_renames(text: &str) -> Vec<String> {
+    let mut lines = text.lines().map(|line| line.trim_end_matches('\r'));
+    let header = split_csv_line(lines.next().unwrap_or(""), ',');
+    let Some(column) = header
+        .iter()
+        .position(|h| h.trim().eq_ignore_ascii_case("rename"))
+    else {
+        return vec![];
+    };
+    lines
+        .filter_map(|line| {
+            let rename = split_csv_line(line, ',').into_iter().nth(column)?;
+            let rename = rename.trim();
+            (!rename.is_empty()).then(|| rename.to_owned())
+        })
+        .collect()
+}
+
+/// The file name at the end of a path or URL, with `%20`-style escapes decoded
+fn entry_file_name(entry: &str) -> Option<String> {
+    let entry = entry.trim().trim_end_matches(['/', '\\']);
+    let name = entry.rsplit(['/', '\\']).next()?;
+    let name = decode_percent(name.split(['?', '#']).next().unwrap_or(name));
+    (!name.trim().is_empty()).then_some(name)
+}
+
+/// Decodes `%XX` escapes as used in URLs, leaving malformed ones as they are
+fn decode_percent(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Reads the file names of the entries in an M3U playlist, skipping `#` directives.
+pub fn parse_m3u(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim_start_matches('\u{FEFF}').trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(entry_file_name)
+        .collect()
+}
+
+/// Returns the text inside every `<element>...</element>` pair.
+fn xml_texts<'a>(text: &'a str, element: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{element}"), format!("</{element}>"));
+    let mut texts = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        // Not a longer element name like `<titles>`
+        if !rest.starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            continue;
+        }
+        let Some(content) = rest.find('>').map(|end| &rest[end + 1..]) else {
+            break;
+        };
+        let Some(end) = content.find(&close) else {
+            break;
+        };
+        texts.push(content[..end].trim());
+        rest = &content[end..];
+    }
+    texts
+}
+
+/// Reads names out of an XML list: the file names of an XSPF playlist's `<location>`s, or else
+/// the text of every `<name>` (or `<title>`) element.
+pub fn parse_xml_list(text: &str) -> Vec<String> {
+    let locations: Vec<String> = xml_texts(text, "location")
+        .into_iter()
+        .filter_map(|location| entry_file_name(&decode_xml_entities(location)))
+        .collect();
+    if !locations.is_empty() {
+        return locations;
+    }
+    let mut names = xml_texts(text, "name");
+    if names.is_empty() {
+        names = xml_texts(text, "title");
+    }
+    names
+        .into_iter()
+        .filter(|name| !name.is_empty())
+        .map(decode_xml_entities)
+        .collect()
+}
+
+/// Reads names out of a JSON array of strings, or of objects with a `name` (or `title`) and
+/// optionally a hexadecimal `crc`.
+pub fn parse_json_list(text: &str) -> Vec<(String, Option<u32>)> {
+    let Ok(serde_json::Value::Array(items)) = serde_json::from_str(text) else {
+        return vec![];
+    };
+    items
+        .iter()
+        .filter_map(|item| match item {
+            serde_json::Value::String(name) => Some((name.clone(), None)),
+            serde_json::Value::Object(fields) => {
+                let name = fields.get("name").or_else(|| fields.get("title"))?;
+                let crc = fields
+                    .get("crc")
+                    .and_then(|crc| crc.as_str())
+                    .and_then(crate::crc::parse);
+                Some((name.as_str()?.to_owned(), crc))
+            }
+            _ => None,
+        })
+        .filter(|(name, _)| !name.trim().is_empty())
+        .collect()
+}
+
+/// The sets of a Logiqx DAT as the archives they are kept in, see [`parse_logiqx_dat`]
+fn dat_archives(text: &str) -> Vec<(String, Option<u32>)> {
+    parse_logiqx_dat(text)
+        .into_iter()
+        .map(|(name, crc)| (format!("{name}.zip"), crc))
+        .collect()
+}
+
+/// Reads a list of choice names in the format its `extension` suggests: a Logiqx DAT, an M3U
+/// or XSPF playlist, an XML or JSON list, or else one name per line.
+pub fn parse_name_list(text: &str, extension: &str) -> Vec<(String, Option<u32>)> {
+    let without_crc = |names: Vec<String>| names.into_iter().map(|name| (name, None)).collect();
+    match extension.to_ascii_lowercase().as_str() {
+        "dat" => dat_archives(text),
+        "m3u" | "m3u8" => without_crc(parse_m3u(text)),
+        "json" => parse_json_list(text),
+        "xml" | "xspf" => {
+            let sets = dat_archives(text);
+            if sets.is_empty() {
+                without_crc(parse_xml_list(text))
+            } else {
+                sets
+            }
+        }
+        _ => without_crc(
+            text.lines()
+                .map(|line| line.trim_start_matches('\u{FEFF}').trim())
+                .filter(|line| !line.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        ),
+    }
+}
+
+/// A choice read from a list
+#[derive(Debug, Default, PartialEq)]
+pub struct Listed {
+    pub name: String,
+    /// The checksum the list gives for it
+    pub crc: Option<u32>,
+    /// The name its matches are renamed to, for lists that map names to new ones
+    pub rename_to: Option<String>,
+}
+
+impl Listed {
+    pub fn new(name: String, crc: Option<u32>) -> Self {
+        Self {
+            name,
+            crc,
+            rename_to: None,
+        }
+    }
+}
+
+/// The kinds of list choices are imported from
+#[derive(Clone, Copy)]
+pub enum ListKind {
+    /// Any list [`parse_name_list`] reads
+    Names,
+    /// A Logiqx DAT, even one saved as `.xml`
+    Dat,
+    /// A mapping exported by this app, see [`parse_mapping_renames`]
+    Mapping,
+    /// Current and new names, see [`parse_csv_pairs`]
+    Csv { has_header: bool },
+}
+
+/// Reads the choices in a list of `kind`, from a file with `extension`
+pub fn parse_list(text: &str, kind: ListKind, extension: &str) -> Vec<Listed> {
+    let listed = |names: Vec<(String, Option<u32>)>| {
+        names
+            .into_iter()
+            .map(|(name, crc)| Listed::new(name, crc))
+            .collect()
+    };
+    match kind {
+        ListKind::Names => listed(parse_name_list(text, extension)),
+        ListKind::Dat => listed(dat_archives(text)),
+        ListKind::Mapping => parse_mapping_renames(text)
+            .into_iter()
+            .map(|name| Listed::new(name, None))
+            .collect(),
+        ListKind::Csv { has_header } => parse_csv_pairs(text, has_header)
+            .into_iter()
+            .map(|(name, rename_to)| Listed {
+                name,
+                rename_to: Some(rename_to),
+                ..Default::default()
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(listed: Vec<Listed>) -> Vec<String> {
+        listed.into_iter().map(|listed| listed.name).collect()
+    }
+
+    #[test]
+    fn dat_sets_are_listed_as_archives() {
+        let dat = r#"<?xml version="1.0"?>
+<datafile>
+    <header><name>Console</name></header>
+    <game name="Tom &amp; Jerry (USA)">
+        <rom name="a.bin" size="4" crc="1234abcd"/>
+        <rom name="b.bin" size="4" crc="ffffffff"/>
+    </game>
+    <machine name='Pong'>
+    </machine>
+</datafile>"#;
+        assert_eq!(
+            parse_logiqx_dat(dat),
+            [
+                ("Tom & Jerry (USA)".to_owned(), Some(0x1234_abcd)),
+                ("Pong".to_owned(), None),
+            ]
+        );
+        let through_names = parse_list(dat, ListKind::Names, "dat");
+        assert_eq!(through_names, parse_list(dat, ListKind::Dat, "xml"));
+        assert_eq!(through_names[0].crc, Some(0x1234_abcd));
+        assert_eq!(names(through_names), ["Tom & Jerry (USA).zip", "Pong.zip"]);
+    }
+
+    #[test]
+    fn playlists_list_file_names() {
+        let m3u = "#EXTM3U\r\n#EXTINF:123,Artist - Song\r\nMusic/Artist%20-%20Song.mp3\r\n\r\nhttp://host/Other.ogg?x=1\r\n";
+        assert_eq!(parse_m3u(m3u), ["Artist - Song.mp3", "Other.ogg"]);
+
+        let xspf = "<playlist><trackList>\
+            <track><location>file:///music/One%20&amp;%20Two.flac</location><title>x</title></track>\
+            <track><location>file:///music/Three.flac</location></track>\
+            </trackList></playlist>";
+        assert_eq!(
+            names(parse_list(xspf, ListKind::Names, "xspf")),
+            ["One & Two.flac", "Three.flac"]
+        );
+        let titles =
+            "<list><titles><title>Alien</title><title> </title><title>Heat</title></titles></list>";
+        assert_eq!(parse_xml_list(titles), ["Alien", "Heat"]);
+    }
+
+    #[test]
+    fn json_lists_take_strings_and_named_objects() {
+        let json = r#"["Alien", {"name": "Heat", "crc": "0000002a"}, {"title": "Up"}, 3, ""]"#;
+        assert_eq!(
+            parse_json_list(json),
+            [
+                ("Alien".to_owned(), None),
+                ("Heat".to_owned(), Some(42)),
+                ("Up".to_owned(), None),
+            ]
+        );
+        assert!(parse_json_list("{\"name\": \"Alien\"}").is_empty());
+    }
+
+    #[test]
+    fn csv_pairs_detect_the_delimiter_and_quotes() {
+        let csv =
+            "current;new\r\nalien.mkv;\"Alien; the \"\"Director's\"\" cut.mkv\"\r\nheat.mkv;\r\n";
+        assert_eq!(
+            parse_csv_pairs(csv, true),
+            [(
+                "alien.mkv".to_owned(),
+                "Alien; the \"Director's\" cut.mkv".to_owned()
+            )]
+        );
+        let listed = parse_list("a.mkv,A.mkv", ListKind::Csv { has_header: false }, "csv");
+        assert_eq!(listed[0].rename_to.as_deref(), Some("A.mkv"));
+
+        let mapping = "source,choice,score,Rename\nalien.mkv,Alien,0.9,Alien.mkv\nheat.mkv,,,\n";
+        assert_eq!(parse_mapping_renames(mapping), ["Alien.mkv"]);
+        assert!(parse_mapping_renames("source,choice\na,b\n").is_empty());
+    }
+
+    #[test]
+    fn plain_lists_have_a_name_per_line() {
+        let text = "\u{FEFF}Alien\n\n  Heat  \r\n";
+        assert_eq!(
+            names(parse_list(text, ListKind::Names, "txt")),
+            ["Alien", "Heat"]
+        );
+    }
+}
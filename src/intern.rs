@@ -0,0 +1,91 @@
+//! Shared storage for file names, so the same name is kept once however many tables, sort keys
+//! and labels refer to it, and copying one around doesn't copy its text.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+/// Every name in use; entries only the pool still holds are dropped by [`release_unused`]
+static POOL: Mutex<Option<HashSet<Arc<str>>>> = Mutex::new(None);
+
+/// A file name from the shared pool, cheap to clone and compare
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Name(Arc<str>);
+
+impl Name {
+    pub fn new(text: &str) -> Self {
+        let mut pool = POOL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let pool = pool.get_or_insert_with(HashSet::new);
+        if let Some(name) = pool.get(text) {
+            return Name(name.clone());
+        }
+        let name: Arc<str> = Arc::from(text);
+        pool.insert(name.clone());
+        Name(name)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Forgets names no longer used anywhere, after tables are cleared or replaced
+pub fn release_unused() {
+    let mut pool = POOL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(pool) = pool.as_mut() {
+        pool.retain(|name| Arc::strong_count(name) > 1);
+    }
+}
+
+impl Default for Name {
+    fn default() -> Self {
+        Name::new("")
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Name {
+    fn from(text: &str) -> Self {
+        Name::new(text)
+    }
+}
+
+impl From<String> for Name {
+    fn from(text: String) -> Self {
+        Name::new(&text)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared() {
+        let a = Name::new("Intern Test.mkv");
+        let b = Name::from("Intern Test.mkv".to_owned());
+        assert!(Arc::ptr_eq(&a.0, &b.0));
+        assert_eq!(a.as_str(), "Intern Test.mkv");
+
+        drop((a, b));
+        release_unused();
+        let pool = POOL.lock().unwrap();
+        assert!(!pool.as_ref().unwrap().contains("Intern Test.mkv"));
+    }
+}
@@ -0,0 +1,218 @@
+//! Job templates: the folders and settings of a rename that is repeated regularly, like new
+//! downloads against a canonical list, so it can be run again in one step.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct Job<S> {
+    /// Folder re-scanned for sources each run
+    pub sources: PathBuf,
+    /// Folder re-scanned for choices each run
+    pub choices: PathBuf,
+    /// Everything else: matching options, filters and output steps
+    pub settings: S,
+}
+
+impl<S: serde::Serialize> Job<S> {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        fs::write(path, json)
+    }
+}
+
+impl<S: serde::de::DeserializeOwned> Job<S> {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Outcome of running a job, printed as JSON by `--job <file> --yes`
+#[derive(serde::Serialize, Default)]
+pub struct Summary {
+    pub ok: bool,
+    pub sources: usize,
+    pub choices: usize,
+    /// Sources with an accepted match
+    pub matched: usize,
+    /// One line per output step, as shown in the status bar
+    pub outputs: Vec<String>,
+    pub error: Option<String>,
+    /// Changes since the last run, `None` on the first one
+    pub changes: Option<Changes>,
+}
+
+/// A source of a run and what it was matched to
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct Mapped {
+    pub source: String,
+    pub choice: Option<String>,
+}
+
+/// Where the mapping of the last run of the job in `path` is kept, e.g. `weekly.last-run.json`
+/// for `weekly.json`
+fn last_run_path(path: &Path) -> PathBuf {
+    path.with_extension("last-run.json")
+}
+
+/// The mapping of the last run of the job in `path`, `None` if it never ran
+pub fn load_last_run(path: &Path) -> io::Result<Option<Vec<Mapped>>> {
+    let text = match fs::read_to_string(last_run_path(path)) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn save_last_run(path: &Path, mapping: &[Mapped]) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(mapping)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    fs::write(last_run_path(path), json)
+}
+
+/// A source whose match differs from the last run
+#[derive(serde::Serialize)]
+pub struct Changed {
+    pub source: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// How a run's mapping differs from the last one, for auditing repeated runs
+#[derive(serde::Serialize, Default)]
+pub struct Changes {
+    /// Sources that weren't in the last run
+    pub added: Vec<Mapped>,
+    pub changed: Vec<Changed>,
+    /// Sources of the last run that are gone
+    pub removed: Vec<String>,
+}
+
+impl Changes {
+    pub fn between(previous: Vec<Mapped>, current: &[Mapped]) -> Self {
+        let mut previous: HashMap<String, Option<String>> = previous
+            .into_iter()
+            .map(|mapped| (mapped.source, mapped.choice))
+            .collect();
+        let mut changes = Changes::default();
+        for mapped in current {
+            match previous.remove(&mapped.source) {
+                None => changes.added.push(Mapped {
+                    source: mapped.source.clone(),
+                    choice: mapped.choice.clone(),
+                }),
+                Some(before) if before != mapped.choice => changes.changed.push(Changed {
+                    source: mapped.source.clone(),
+                    before,
+                    after: mapped.choice.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        changes.removed = previous.into_keys().collect();
+        changes.removed.sort();
+        changes
+    }
+
+    /// One line per change, like `+ Pilot.mkv -> Pilot`
+    pub fn lines(&self) -> Vec<String> {
+        let name =
+            |choice: &Option<String>| choice.clone().unwrap_or_else(|| "(no match)".to_owned());
+        let mut lines = vec![];
+        for mapped in &self.added {
+            lines.push(format!("+ {} -> {}", mapped.source, name(&mapped.choice)));
+        }
+        for changed in &self.changed {
+            lines.push(format!(
+                "~ {}: {} -> {}",
+                changed.source,
+                name(&changed.before),
+                name(&changed.after)
+            ));
+        }
+        for source in &self.removed {
+            lines.push(format!("- {source}"));
+        }
+        lines
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} new, {} changed, {} gone",
+            self.added.len(),
+            self.changed.len(),
+            self.removed.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapped(source: &str, choice: Option<&str>) -> Mapped {
+        Mapped {
+            source: source.to_owned(),
+            choice: choice.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn changes_list_new_changed_and_gone_sources() {
+        let previous = vec![
+            mapped("Pilot.mkv", Some("Pilot")),
+            mapped("Finale.mkv", None),
+            mapped("Old.mkv", Some("Old")),
+            mapped("Same.mkv", Some("Same")),
+        ];
+        let current = [
+            mapped("Pilot.mkv", Some("Pilot (Extended)")),
+            mapped("Finale.mkv", Some("Finale")),
+            mapped("Same.mkv", Some("Same")),
+            mapped("New.mkv", None),
+        ];
+        let changes = Changes::between(previous, &current);
+        assert_eq!(changes.describe(), "1 new, 2 changed, 1 gone");
+        assert_eq!(
+            changes.lines(),
+            [
+                "+ New.mkv -> (no match)",
+                "~ Pilot.mkv: Pilot -> Pilot (Extended)",
+                "~ Finale.mkv: (no match) -> Finale",
+                "- Old.mkv",
+            ]
+        );
+    }
+
+    #[test]
+    fn an_identical_run_has_no_changes() {
+        let current = [mapped("Pilot.mkv", Some("Pilot")), mapped("B.mkv", None)];
+        let previous = vec![mapped("B.mkv", None), mapped("Pilot.mkv", Some("Pilot"))];
+        let changes = Changes::between(previous, &current);
+        assert!(changes.lines().is_empty());
+        assert_eq!(changes.describe(), "0 new, 0 changed, 0 gone");
+    }
+
+    #[test]
+    fn last_run_is_kept_beside_the_job() {
+        let folder = std::env::temp_dir().join(format!("fuzzy_rename_job_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let job = folder.join("weekly.json");
+        assert!(load_last_run(&job).unwrap().is_none());
+        save_last_run(&job, &[mapped("Pilot.mkv", Some("Pilot"))]).unwrap();
+        assert!(folder.join("weekly.last-run.json").is_file());
+        let loaded = load_last_run(&job).unwrap().unwrap();
+        assert!(
+            Changes::between(loaded, &[mapped("Pilot.mkv", Some("Pilot"))])
+                .lines()
+                .is_empty()
+        );
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
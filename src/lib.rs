@@ -1,4 +1,34 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+mod affix;
 mod app;
+#[cfg(not(target_arch = "wasm32"))]
+mod automation;
+#[cfg(not(target_arch = "wasm32"))]
+mod copier;
+mod crc;
+mod dialog;
+mod exif;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+mod history;
+#[cfg(not(target_arch = "wasm32"))]
+mod hooks;
+mod import;
+mod intern;
+#[cfg(not(target_arch = "wasm32"))]
+mod job;
+mod lint;
+mod metrics;
+mod normalize;
+mod paths;
+mod rename;
+mod report;
+#[cfg(not(target_arch = "wasm32"))]
+mod scan;
+mod session;
+mod tags;
+mod task;
+mod template;
 pub use app::MainApp;
@@ -0,0 +1,156 @@
+//! Checks of output names against a naming convention, like the ones media servers expect, so
+//! names they would misread are caught before anything is copied.
+
+use regex::Regex;
+
+/// Characters Windows and most network shares refuse in file names
+const WINDOWS_FORBIDDEN: &str = "<>:\"/\\|?*";
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Convention {
+    #[default]
+    None,
+    Plex,
+    Jellyfin,
+    Kodi,
+    /// Tracks numbered like "01 - Title", as Plex and Jellyfin read them
+    Music,
+    /// Rules edited by hand
+    Custom,
+}
+
+impl Convention {
+    pub const ALL: [Convention; 6] = [
+        Convention::None,
+        Convention::Plex,
+        Convention::Jellyfin,
+        Convention::Kodi,
+        Convention::Music,
+        Convention::Custom,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Convention::None => "None",
+            Convention::Plex => "Plex",
+            Convention::Jellyfin => "Jellyfin",
+            Convention::Kodi => "Kodi",
+            Convention::Music => "Music tracks",
+            Convention::Custom => "Custom",
+        }
+    }
+
+    /// The pattern names must contain: a year like "(1999)" for movies or an episode like
+    /// "S01E02" for shows, or a leading track number for music
+    fn pattern(&self) -> Option<&'static str> {
+        match self {
+            Convention::Plex => Some(r"\((19|20)\d{2}\)|[Ss]\d{1,2}[Ee]\d{1,3}"),
+            Convention::Jellyfin => {
+                Some(r"\((19|20)\d{2}\)|[Ss]\d{1,2}[Ee]\d{1,3}|\[(tmdbid|imdbid|tvdbid)-[^\]]+\]")
+            }
+            Convention::Kodi => {
+                Some(r"\((19|20)\d{2}\)|[Ss]\d{1,2}[Ee]\d{1,3}|\b\d{1,2}x\d{2,3}\b")
+            }
+            Convention::Music => Some(r"^\d{1,3} - \S"),
+            Convention::None | Convention::Custom => None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub struct NamingRules {
+    pub convention: Convention,
+    /// Names must contain a match of this regex, empty for any name
+    pub pattern: String,
+    /// Longest name allowed in characters, extension included; 0 for no limit
+    pub max_length: usize,
+    /// Characters names may not contain
+    pub forbidden: String,
+    pub ascii_only: bool,
+    /// Cancel copies and renames while any name breaks the rules
+    pub block_output: bool,
+    #[serde(skip)]
+    compiled: Option<Regex>,
+}
+
+impl NamingRules {
+    /// Switches to `convention`, filling in its rules unless it is [`Convention::Custom`]
+    pub fn set_convention(&mut self, convention: Convention) {
+        self.convention = convention;
+        if let Some(pattern) = convention.pattern() {
+            self.pattern = pattern.to_owned();
+            self.max_length = 255;
+            self.forbidden = WINDOWS_FORBIDDEN.to_owned();
+            self.ascii_only = false;
+        }
+        self.compile();
+    }
+
+    pub fn compile(&mut self) {
+        self.compiled = if self.pattern.is_empty() {
+            None
+        } else {
+            Regex::new(&self.pattern).ok()
+        };
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.convention != Convention::None
+    }
+
+    pub fn is_invalid(&self) -> bool {
+        !self.pattern.is_empty() && self.compiled.is_none()
+    }
+
+    /// What `name` breaks, empty when it follows the rules
+    pub fn problems(&self, name: &str) -> Vec<String> {
+        let mut problems = vec![];
+        if !self.is_enabled() || name.is_empty() {
+            return problems;
+        }
+        if let Some(regex) = &self.compiled {
+            if !regex.is_match(name) {
+                problems.push(format!("Doesn't match {}", self.pattern));
+            }
+        }
+        let length = name.chars().count();
+        if self.max_length > 0 && length > self.max_length {
+            problems.push(format!(
+                "{length} characters long, more than {}",
+                self.max_length
+            ));
+        }
+        if let Some(c) = name.chars().find(|c| self.forbidden.contains(*c)) {
+            problems.push(format!("Contains \"{c}\""));
+        }
+        if self.ascii_only && !name.is_ascii() {
+            problems.push("Has characters other than ASCII".to_owned());
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conventions() {
+        let mut rules = NamingRules::default();
+        assert!(rules.problems("anything?").is_empty());
+
+        rules.set_convention(Convention::Plex);
+        assert!(rules.problems("The Matrix (1999).mkv").is_empty());
+        assert!(rules.problems("Show - s01e02 - Pilot.mkv").is_empty());
+        assert_eq!(rules.problems("The Matrix.mkv").len(), 1);
+        assert_eq!(rules.problems("What? (2001).mkv"), ["Contains \"?\""]);
+
+        rules.set_convention(Convention::Kodi);
+        assert!(rules.problems("Show 1x02.mkv").is_empty());
+
+        rules.set_convention(Convention::Music);
+        assert!(rules.problems("01 - Intro.flac").is_empty());
+        assert_eq!(rules.problems("Intro.flac").len(), 1);
+    }
+}
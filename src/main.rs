@@ -4,14 +4,93 @@
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
-    // Log to stdout (if you run with `RUST_LOG=debug`).
-    tracing_subscriber::fmt::init();
+    // Log to stderr (if you run with `RUST_LOG=debug`), stdout carries the automation replies.
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
+    // Time each algorithm on two folders and exit, without opening a window
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--bench") {
+        match (args.get(index + 1), args.get(index + 2)) {
+            (Some(sources), Some(choices)) => {
+                match fuzzy_rename_rs::MainApp::benchmark_folders(
+                    std::path::Path::new(sources),
+                    std::path::Path::new(choices),
+                ) {
+                    Ok(report) => println!("{report}"),
+                    Err(error) => eprintln!("Benchmark failed: {error}"),
+                }
+            }
+            _ => eprintln!("Usage: fuzzy_rename_rs --bench <sources folder> <choices folder>"),
+        }
+        return;
+    }
+
+    // Print the best match of each file as it is scored, without opening a window
+    if let Some(index) = args.iter().position(|arg| arg == "--match") {
+        match (args.get(index + 1), args.get(index + 2)) {
+            (Some(sources), Some(choices)) => {
+                let stdout = std::io::stdout();
+                if let Err(error) = fuzzy_rename_rs::MainApp::stream_matches(
+                    std::path::Path::new(sources),
+                    std::path::Path::new(choices),
+                    &mut stdout.lock(),
+                ) {
+                    eprintln!("Matching failed: {error}");
+                    std::process::exit(1);
+                }
+            }
+            _ => {
+                eprintln!("Usage: fuzzy_rename_rs --match <sources folder> <choices folder>");
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
+    // Run a saved job: unattended with `--yes`, otherwise in the window after a confirmation
+    let job = args
+        .iter()
+        .position(|arg| arg == "--job")
+        .and_then(|index| args.get(index + 1))
+        .map(std::path::PathBuf::from);
+    if let Some(job) = &job {
+        if args.iter().any(|arg| arg == "--yes") {
+            let (summary, ok) = fuzzy_rename_rs::MainApp::run_job_unattended(job);
+            println!("{summary}");
+            std::process::exit(if ok { 0 } else { 1 });
+        }
+    }
+
+    // Accept JSON requests on stdin, see `automation.rs`
+    let automation = args.iter().any(|arg| arg == "--automation");
+
+    // Windows opened from the Window menu keep their settings apart from the main one and from
+    // each other, by the number the main window gives them. The numbers start over with the main
+    // window, so a window left open from an earlier run shares its settings with the new one.
+    let app_name = match args.iter().position(|arg| arg == "--new-window") {
+        Some(index) => {
+            let number = args.get(index + 1).and_then(|n| n.parse::<usize>().ok());
+            format!("Fuzzy Rename-RS (window {})", number.unwrap_or(2))
+        }
+        None => "Fuzzy Rename-RS".to_owned(),
+    };
 
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(
-        "Fuzzy Rename-RS",
+        &app_name,
         native_options,
-        Box::new(|cc| Box::new(fuzzy_rename_rs::MainApp::new(cc))),
+        Box::new(move |cc| {
+            let mut app = fuzzy_rename_rs::MainApp::new(cc);
+            if automation {
+                app.start_automation(&cc.egui_ctx);
+            }
+            if let Some(job) = &job {
+                app.open_job(job);
+            }
+            Box::new(app)
+        }),
     );
 }
 
@@ -0,0 +1,194 @@
+//! String similarity metrics not provided by `strsim`.
+//!
+//! All metrics return a score between 0.0 (nothing in common) and 1.0 (identical).
+
+/// Splits a name into words, treating common filename separators as spaces.
+fn tokenize(s: &str) -> Vec<&str> {
+    s.split(|c: char| c.is_whitespace() || matches!(c, '_' | '.' | '-'))
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Edit distance between two sequences, where each element is inserted, removed, or substituted whole.
+fn sequence_levenshtein<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, a_item) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, b_item) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_item != b_item);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Levenshtein distance normalized by the longer name, as `strsim::normalized_levenshtein`.
+/// With the `simd` feature, ASCII names are compared with AVX2 or SSE4.1 when the CPU has them.
+pub fn normalized_levenshtein(a: &str, b: &str) -> f64 {
+    #[cfg(feature = "simd")]
+    if a.is_ascii() && b.is_ascii() {
+        let longest = a.len().max(b.len());
+        if longest == 0 {
+            return 1.0;
+        }
+        let distance = triple_accel::levenshtein::levenshtein(a.as_bytes(), b.as_bytes());
+        return 1.0 - distance as f64 / longest as f64;
+    }
+    strsim::normalized_levenshtein(a, b)
+}
+
+/// Levenshtein distance over words instead of characters, normalized by the longer word count.
+pub fn token_levenshtein(a: &str, b: &str) -> f64 {
+    let a = tokenize(a);
+    let b = tokenize(b);
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - sequence_levenshtein(&a, &b) as f64 / longest as f64
+}
+
+/// Jaro similarity with a bonus of `scale` per shared leading character, counting at most
+/// `max_prefix` of them (0 for no limit, as `strsim::jaro_winkler` does).
+pub fn jaro_winkler(a: &str, b: &str, scale: f64, max_prefix: usize) -> f64 {
+    let jaro = strsim::jaro(a, b);
+    let prefix = a
+        .chars()
+        .zip(b.chars())
+        .take_while(|(a, b)| a == b)
+        .take(if max_prefix == 0 {
+            usize::MAX
+        } else {
+            max_prefix
+        })
+        .count();
+    (jaro + scale * prefix as f64 * (1.0 - jaro)).min(1.0)
+}
+
+/// Scales a shared length against the combined length of both names, like Python's `difflib` ratio.
+fn shared_ratio(shared: usize, a_len: usize, b_len: usize) -> f64 {
+    if a_len + b_len == 0 {
+        return 1.0;
+    }
+    2.0 * shared as f64 / (a_len + b_len) as f64
+}
+
+/// Ratio based on the longest common subsequence of characters.
+pub fn lcs_ratio(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut previous = vec![0usize; b.len() + 1];
+    let mut current = vec![0usize; b.len() + 1];
+    for a_char in &a {
+        for (j, b_char) in b.iter().enumerate() {
+            current[j + 1] = if a_char == b_char {
+                previous[j] + 1
+            } else {
+                previous[j + 1].max(current[j])
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    shared_ratio(previous[b.len()], a.len(), b.len())
+}
+
+/// Ratio based on the longest run of characters both names share exactly.
+pub fn longest_substring_ratio(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut longest = 0;
+    let mut previous = vec![0usize; b.len() + 1];
+    let mut current = vec![0usize; b.len() + 1];
+    for a_char in &a {
+        for (j, b_char) in b.iter().enumerate() {
+            current[j + 1] = if a_char == b_char { previous[j] + 1 } else { 0 };
+            longest = longest.max(current[j + 1]);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    shared_ratio(longest, a.len(), b.len())
+}
+
+/// Words commonly left out of initialisms ("Legend of Zelda" -> "LoZ", not "TLoZ").
+const MINOR_WORDS: [&str; 8] = ["the", "a", "an", "of", "and", "in", "on", "to"];
+
+/// Derives lowercase initialisms from a multi-word name.
+///
+/// Parts separated by `:` or ` - ` are abbreviated separately and joined with a space, so
+/// "The Legend of Zelda: Ocarina of Time" yields "tlozoot", "tloz oot", "lozoot" and "loz oot".
+pub fn initialisms(s: &str) -> Vec<String> {
+    let parts: Vec<Vec<String>> = s
+        .split(':')
+        .flat_map(|p| p.split(" - "))
+        .map(|part| {
+            tokenize(part)
+                .into_iter()
+                .map(|w| w.to_lowercase())
+                .filter(|w| w.chars().any(char::is_alphanumeric))
+                .collect::<Vec<_>>()
+        })
+        .filter(|words| !words.is_empty())
+        .collect();
+
+    if parts.iter().map(Vec::len).sum::<usize>() < 2 {
+        return vec![];
+    }
+
+    let abbreviate = |skip_leading_articles: bool| -> Vec<String> {
+        parts
+            .iter()
+            .map(|words| {
+                words
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, w)| {
+                        !(skip_leading_articles && *i == 0 && MINOR_WORDS.contains(&w.as_str()))
+                    })
+                    .filter_map(|(_, w)| w.chars().find(|c| c.is_alphanumeric()))
+                    .collect::<String>()
+            })
+            .collect()
+    };
+
+    let mut variants = vec![];
+    for skip_leading_articles in [false, true] {
+        let abbreviated = abbreviate(skip_leading_articles);
+        for joined in [abbreviated.concat(), abbreviated.join(" ")] {
+            if !variants.contains(&joined) {
+                variants.push(joined);
+            }
+        }
+    }
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levenshtein_agrees_with_strsim() {
+        let names = [
+            "",
+            "a",
+            "The Matrix (1999)",
+            "the.matrix.1999.1080p",
+            "Matrix Reloaded, The",
+            "\u{65e5}\u{672c} Matrix",
+        ];
+        for a in names {
+            for b in names {
+                assert_eq!(
+                    normalized_levenshtein(a, b),
+                    strsim::normalized_levenshtein(a, b),
+                    "{a:?} and {b:?}"
+                );
+            }
+        }
+    }
+}
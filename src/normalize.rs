@@ -0,0 +1,425 @@
+//! Rewrites of names applied before they are compared, so differences that don't matter (like
+//! `my_file_name` against `My File Name`) don't lower the score. Output names are unaffected.
+
+/// Whether `c` separates words in a file name
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '_' | '.' | '-')
+}
+
+/// `text` with every run of `_`, `.`, `-` and whitespace replaced by a single space
+pub fn unify_separators(text: &str) -> String {
+    text.split(is_separator)
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Languages whose articles are recognized. Only English is by default: the short articles of
+/// the others are often ordinary words elsewhere, like "Die Hard" or "I Am Legend".
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArticleLanguage {
+    English,
+    French,
+    German,
+    Spanish,
+    Italian,
+    Portuguese,
+    Dutch,
+}
+
+impl ArticleLanguage {
+    pub const ALL: [ArticleLanguage; 7] = [
+        ArticleLanguage::English,
+        ArticleLanguage::French,
+        ArticleLanguage::German,
+        ArticleLanguage::Spanish,
+        ArticleLanguage::Italian,
+        ArticleLanguage::Portuguese,
+        ArticleLanguage::Dutch,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ArticleLanguage::English => "English",
+            ArticleLanguage::French => "French",
+            ArticleLanguage::German => "German",
+            ArticleLanguage::Spanish => "Spanish",
+            ArticleLanguage::Italian => "Italian",
+            ArticleLanguage::Portuguese => "Portuguese",
+            ArticleLanguage::Dutch => "Dutch",
+        }
+    }
+
+    fn articles(&self) -> &'static [&'static str] {
+        match self {
+            ArticleLanguage::English => &["the", "a", "an"],
+            ArticleLanguage::French => &["le", "la", "les", "un", "une"],
+            ArticleLanguage::German => &["der", "die", "das", "ein", "eine"],
+            ArticleLanguage::Spanish => &["el", "la", "los", "las", "un", "una"],
+            ArticleLanguage::Italian => &["il", "lo", "la", "gli", "le", "i", "un", "uno", "una"],
+            ArticleLanguage::Portuguese => &["o", "a", "os", "as", "um", "uma"],
+            ArticleLanguage::Dutch => &["de", "het", "een"],
+        }
+    }
+
+    /// Whether the language elides its article before a vowel, as in "L'Odyssée"
+    fn elides(&self) -> bool {
+        matches!(self, ArticleLanguage::French | ArticleLanguage::Italian)
+    }
+}
+
+/// Whether `word` is an article in one of `languages`
+fn is_article(word: &str, languages: &[ArticleLanguage]) -> bool {
+    languages
+        .iter()
+        .flat_map(|language| language.articles())
+        .any(|article| article.eq_ignore_ascii_case(word))
+}
+
+/// Splits a leading article like "The " or "L'" from `text`, if there is one in `languages` and
+/// more follows
+fn split_article<'a>(text: &'a str, languages: &[ArticleLanguage]) -> Option<(&'a str, &'a str)> {
+    let elided: &[&str] = if languages.iter().any(ArticleLanguage::elides) {
+        &["l'", "l\u{2019}"]
+    } else {
+        &[]
+    };
+    for elided in elided {
+        if text.len() > elided.len()
+            && text.is_char_boundary(elided.len())
+            && text[..elided.len()].eq_ignore_ascii_case(elided)
+        {
+            return Some(text.split_at(elided.len()));
+        }
+    }
+    let (word, rest) = text.split_once(' ')?;
+    let rest = rest.trim_start();
+    (!rest.is_empty() && is_article(word, languages)).then_some((word, rest))
+}
+
+/// `text` without its article in one of `languages`, whether leading ("The Matrix") or moved
+/// to the end ("Matrix, The"), so both forms compare the same
+pub fn without_article<'a>(text: &'a str, languages: &[ArticleLanguage]) -> &'a str {
+    if let Some((_, rest)) = split_article(text, languages) {
+        return rest;
+    }
+    match text.rsplit_once(',') {
+        Some((title, article))
+            if !title.trim().is_empty() && is_article(article.trim(), languages) =>
+        {
+            title.trim_end()
+        }
+        _ => text,
+    }
+}
+
+/// `text` with a leading article in one of `languages` moved to the end, as in library sort
+/// order: "The Matrix" becomes "Matrix, The"
+pub fn relocate_article(text: &str, languages: &[ArticleLanguage]) -> String {
+    match split_article(text, languages) {
+        Some((article, rest)) => format!("{rest}, {}", article.trim_end()),
+        None => text.to_owned(),
+    }
+}
+
+/// `value` in Roman numerals
+fn to_roman(mut value: u32) -> String {
+    let mut roman = String::new();
+    for (step, numeral) in [(10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I")] {
+        while value >= step {
+            roman.push_str(numeral);
+            value -= step;
+        }
+    }
+    roman
+}
+
+/// The value of `word` as a capitalized Roman numeral from I to XXXIX, the range of sequels
+/// and parts. Larger ones are mostly ordinary words, like "MIX" or "CD".
+fn roman_value(word: &str) -> Option<u32> {
+    let digits: Vec<u32> = word
+        .chars()
+        .map(|c| match c {
+            'I' => Some(1),
+            'V' => Some(5),
+            'X' => Some(10),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    let value = digits
+        .iter()
+        .enumerate()
+        .map(|(i, &digit)| match digits.get(i + 1) {
+            Some(&next) if next > digit => -(digit as i64),
+            _ => digit as i64,
+        })
+        .sum::<i64>();
+    // Only the usual spelling, so "IIII" or "VX" stay words
+    let value = u32::try_from(value).ok().filter(|&v| v > 0)?;
+    (to_roman(value) == word).then_some(value)
+}
+
+/// Words a single-letter numeral follows when it numbers something, like "Part I"
+const NUMBERING_WORDS: [&str; 8] = [
+    "act", "book", "chapter", "episode", "part", "season", "vol", "volume",
+];
+
+/// `text` with whole-word Roman numerals written as digits, so "Final Fantasy VII" and
+/// "Final Fantasy 7" compare the same. Single letters are only numerals after a word like
+/// "Part", elsewhere they are mostly words or initials, like "I, Robot" or "Malcolm X".
+pub fn roman_to_digits(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut word_start = None;
+    let mut previous_word = "";
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        if c.is_alphanumeric() {
+            word_start.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = word_start.take() {
+            let word = &text[start..i];
+            let numbered = word.len() > 1
+                || NUMBERING_WORDS
+                    .iter()
+                    .any(|numbering| previous_word.eq_ignore_ascii_case(numbering));
+            match roman_value(word).filter(|_| numbered) {
+                Some(value) => result.push_str(&value.to_string()),
+                None => result.push_str(word),
+            }
+            previous_word = word;
+        }
+        if i < text.len() {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Four-digit years from 1900 to 2099 in `text`, like "1999" in "The Matrix (1999)". Other
+/// numbers, like "1080" in "1080p", are left out.
+pub fn years(text: &str) -> Vec<u16> {
+    year_spans(text).into_iter().map(|(_, year)| year).collect()
+}
+
+/// The years of [`years`], with where each starts in `text`
+fn year_spans(text: &str) -> Vec<(usize, u16)> {
+    let bytes = text.as_bytes();
+    let mut years = vec![];
+    let mut start = 0;
+    while start < bytes.len() {
+        let end = start
+            + bytes[start..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+        if end - start == 4 {
+            let year: u16 = text[start..end].parse().unwrap_or(0);
+            if (1900..=2099).contains(&year) {
+                years.push((start, year));
+            }
+        }
+        start = end + 1;
+    }
+    years
+}
+
+/// Splits the last year off `text` with the separators and brackets before it, like "The Matrix
+/// (1999)" into "The Matrix" and 1999. The last, so "Blade Runner 2049 (2017)" keeps its title.
+pub fn split_year(text: &str) -> Option<(&str, u16)> {
+    let (start, year) = *year_spans(text).last()?;
+    let title = text[..start].trim_end_matches(|c| is_separator(c) || matches!(c, '(' | '['));
+    (!title.is_empty()).then_some((title, year))
+}
+
+/// A season and episode marked in a name, like "S02E05"
+#[derive(Debug, PartialEq, Eq)]
+pub struct Episode<'a> {
+    /// The name before the marker, like "Show" in "Show - S02E05 - Title"
+    pub show: &'a str,
+    pub season: usize,
+    pub episode: usize,
+}
+
+/// The first season and episode marked in `text` like "S02E05" or "s2e5", as a word of its own
+pub fn episode(text: &str) -> Option<Episode<'_>> {
+    let digits = |from: usize| {
+        text[from..]
+            .bytes()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    text.char_indices().find_map(|(start, c)| {
+        let after_word = text[..start]
+            .chars()
+            .next_back()
+            .map_or(false, char::is_alphanumeric);
+        if !matches!(c, 'S' | 's') || after_word {
+            return None;
+        }
+        let season_length = digits(start + 1);
+        let marker = start + 1 + season_length;
+        if !(1..=2).contains(&season_length)
+            || !matches!(text[marker..].chars().next(), Some('E' | 'e'))
+        {
+            return None;
+        }
+        let episode_length = digits(marker + 1);
+        let end = marker + 1 + episode_length;
+        if !(1..=3).contains(&episode_length)
+            || text[end..]
+                .chars()
+                .next()
+                .map_or(false, char::is_alphanumeric)
+        {
+            return None;
+        }
+        Some(Episode {
+            show: text[..start].trim_end_matches(is_separator),
+            season: text[start + 1..marker].parse().ok()?,
+            episode: text[marker + 1..end].parse().ok()?,
+        })
+    })
+}
+
+/// Common ISO 639-1 and 639-2 language codes, as subtitle files are tagged with
+const LANGUAGES: [&str; 62] = [
+    "en", "eng", "fr", "fre", "fra", "de", "ger", "deu", "es", "spa", "it", "ita", "pt", "por",
+    "nl", "dut", "nld", "ru", "rus", "ja", "jpn", "zh", "chi", "zho", "ko", "kor", "ar", "ara",
+    "sv", "swe", "no", "nor", "nb", "da", "dan", "fi", "fin", "pl", "pol", "cs", "cze", "ces",
+    "hu", "hun", "tr", "tur", "el", "gre", "ell", "he", "heb", "th", "tha", "vi", "vie", "uk",
+    "ukr", "ro", "rum", "ron", "id", "ind",
+];
+
+/// Flags subtitle files carry beside their language, like "forced" in "Movie.en.forced.srt"
+const SUBTITLE_FLAGS: [&str; 5] = ["forced", "sdh", "cc", "hi", "default"];
+
+/// Whether `tag` is a language code, optionally with a region like "pt-BR", or a subtitle flag
+fn is_language_tag(tag: &str) -> bool {
+    let (language, region) = tag.split_once(['-', '_']).unwrap_or((tag, ""));
+    let known = |list: &[&str]| list.iter().any(|t| t.eq_ignore_ascii_case(language));
+    (known(&LANGUAGES) || (region.is_empty() && known(&SUBTITLE_FLAGS)))
+        && region.len() <= 4
+        && region.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Splits the language codes and flags ending a name without extension, like "Movie.en.forced"
+/// into "Movie" and "en.forced". The name is never left empty.
+pub fn split_language_tags(stem: &str) -> (&str, &str) {
+    let mut end = stem.len();
+    while let Some(dot) = stem[..end].rfind('.').filter(|&dot| dot > 0) {
+        if !is_language_tag(&stem[dot + 1..end]) {
+            break;
+        }
+        end = dot;
+    }
+    (&stem[..end], stem.get(end + 1..).unwrap_or(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separators() {
+        assert_eq!(unify_separators("my_file.name"), "my file name");
+        assert_eq!(unify_separators(" My -- File  Name "), "My File Name");
+    }
+
+    #[test]
+    fn articles() {
+        let english = [ArticleLanguage::English];
+        assert_eq!(without_article("The Matrix", &english), "Matrix");
+        assert_eq!(without_article("Matrix, The", &english), "Matrix");
+        assert_eq!(without_article("The", &english), "The");
+        assert_eq!(without_article("Theatre", &english), "Theatre");
+        assert_eq!(relocate_article("The Matrix", &english), "Matrix, The");
+        assert_eq!(relocate_article("Matrix", &english), "Matrix");
+
+        let others = [ArticleLanguage::French, ArticleLanguage::German];
+        assert_eq!(without_article("L'Odyssée", &others), "Odyssée");
+        assert_eq!(without_article("Der Untergang", &others), "Untergang");
+        assert_eq!(without_article("The Matrix", &others), "The Matrix");
+    }
+
+    #[test]
+    fn english_titles_keep_words_other_languages_use_as_articles() {
+        let english = [ArticleLanguage::English];
+        for title in [
+            "Die Hard",
+            "I Am Legend",
+            "As Good as It Gets",
+            "O Brother, Where Art Thou",
+            "De Niro",
+            "L'Amour",
+        ] {
+            assert_eq!(without_article(title, &english), title);
+            assert_eq!(relocate_article(title, &english), title);
+        }
+        assert_eq!(without_article("Hard, Die", &english), "Hard, Die");
+    }
+
+    #[test]
+    fn roman_numerals() {
+        assert_eq!(roman_to_digits("Final Fantasy VII"), "Final Fantasy 7");
+        assert_eq!(roman_to_digits("Rocky IV: Part XIX"), "Rocky 4: Part 19");
+        assert_eq!(
+            roman_to_digits("MIX IIII vi XVII.mkv"),
+            "MIX IIII vi 17.mkv"
+        );
+        assert_eq!(roman_to_digits("Civilization"), "Civilization");
+    }
+
+    #[test]
+    fn single_letters_are_numerals_only_after_a_numbering_word() {
+        assert_eq!(roman_to_digits("Malcolm X"), "Malcolm X");
+        assert_eq!(roman_to_digits("I, Robot"), "I, Robot");
+        assert_eq!(roman_to_digits("I"), "I");
+        assert_eq!(roman_to_digits("Kill Bill Vol. I"), "Kill Bill Vol. 1");
+        assert_eq!(roman_to_digits("Part V - Chapter X"), "Part 5 - Chapter 10");
+    }
+
+    #[test]
+    fn year_tokens() {
+        assert_eq!(years("The Matrix (1999) 1080p"), [1999]);
+        assert_eq!(years("Blade Runner 2049 (2017)"), [2049, 2017]);
+        assert_eq!(years("12345 1899 x2010"), [2010]);
+    }
+
+    #[test]
+    fn titles_and_years() {
+        assert_eq!(split_year("The Matrix (1999)"), Some(("The Matrix", 1999)));
+        assert_eq!(
+            split_year("Blade.Runner.2049.2017"),
+            Some(("Blade.Runner.2049", 2017))
+        );
+        assert_eq!(split_year("1917"), None);
+        assert_eq!(split_year("Heat"), None);
+    }
+
+    #[test]
+    fn episodes() {
+        let episode_of = |text| episode(text).map(|e| (e.show, e.season, e.episode));
+        assert_eq!(episode_of("Show - s02e05 - Title"), Some(("Show", 2, 5)));
+        assert_eq!(
+            episode_of("The.Show.S1E123.720p"),
+            Some(("The.Show", 1, 123))
+        );
+        assert_eq!(episode_of("Mass Effect S2E"), None);
+        assert_eq!(episode_of("Chess02E05"), None);
+    }
+
+    #[test]
+    fn language_tags() {
+        assert_eq!(
+            split_language_tags("Movie.en.forced"),
+            ("Movie", "en.forced")
+        );
+        assert_eq!(
+            split_language_tags("Movie (2020).pt-BR"),
+            ("Movie (2020)", "pt-BR")
+        );
+        assert_eq!(split_language_tags("Movie.mkv"), ("Movie.mkv", ""));
+        assert_eq!(split_language_tags("en.forced"), ("en", "forced"));
+        assert_eq!(split_language_tags("Movie"), ("Movie", ""));
+    }
+}
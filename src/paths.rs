@@ -0,0 +1,467 @@
+//! File operations that hold up on network shares and long Windows paths.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+/// How long an existence check may take before the share is considered unreachable
+const EXISTS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Attempts made for a copy that fails with a possibly transient error
+const COPY_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Chunk size for copying file contents, large enough that network shares stay busy
+const COPY_BUFFER: usize = 1 << 20;
+
+/// The name of the file at `path` (lossy if it isn't valid UTF-8), or `None` for roots like
+/// `/` or `C:\`. A trailing separator is ignored, as in `folder/file.txt/`.
+pub fn file_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+/// The folder containing `path`, or `None` for roots and bare file names
+pub fn parent_folder(path: &Path) -> Option<&Path> {
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+}
+
+/// Folder to open the next file dialog in: the one containing `path`, or `path` itself for a root
+pub fn dialog_folder(path: &Path) -> String {
+    parent_folder(path)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The user's home folder, from `HOME` (or `USERPROFILE` on Windows)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn home_folder() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Adds the `\\?\` prefix on Windows, so paths longer than `MAX_PATH` (including UNC paths
+/// like `\\server\share\...`) can be used. Other platforms get the path unchanged.
+pub fn long_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        let text = path.as_os_str().to_string_lossy();
+        if !path.is_absolute() || text.starts_with(r"\\?\") {
+            return path.to_owned();
+        }
+        if let Some(unc) = text.strip_prefix(r"\\") {
+            return PathBuf::from(format!(r"\\?\UNC\{unc}"));
+        }
+        return PathBuf::from(format!(r"\\?\{text}"));
+    }
+    #[cfg(not(windows))]
+    {
+        path.to_owned()
+    }
+}
+
+/// Names Windows reserves for devices, with or without an extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Whether Windows would refuse `name` as a file name because it means a device, like `CON`
+/// or `nul.txt`
+pub fn is_reserved_name(name: &str) -> bool {
+    let device = name.split('.').next().unwrap_or(name).trim_end();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(device))
+}
+
+/// `name`, with `_` added after a reserved device name so it can be created on Windows and
+/// shares served from it, e.g. `CON.mkv` becomes `CON_.mkv`
+pub fn usable_name(name: &str) -> String {
+    if !is_reserved_name(name) {
+        return name.to_owned();
+    }
+    let device_end = name.find('.').unwrap_or(name.len());
+    format!("{}_{}", &name[..device_end], &name[device_end..])
+}
+
+/// Existence checks for a batch of files. Whether a folder responds at all is found out once, so
+/// a share that stopped responding costs one wait per batch instead of one per file.
+#[derive(Default)]
+pub struct ExistsCheck {
+    responding: HashMap<PathBuf, bool>,
+}
+
+impl ExistsCheck {
+    /// Like [`Path::try_exists`], but gives up with [`io::ErrorKind::TimedOut`] instead of
+    /// hanging when the path is on a share that stopped responding
+    pub fn try_exists(&mut self, path: &Path) -> io::Result<bool> {
+        let folder = path.parent().unwrap_or(path);
+        let responds = *self
+            .responding
+            .entry(folder.to_owned())
+            .or_insert_with(|| responds(folder));
+        if !responds {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no response from the file system",
+            ));
+        }
+        long_path(path).try_exists()
+    }
+}
+
+/// Whether the file system answers about `folder` within [`EXISTS_TIMEOUT`]. The check has a
+/// thread of its own, which is left behind if it hangs.
+fn responds(folder: &Path) -> bool {
+    let (sender, receiver) = mpsc::channel();
+    let folder = long_path(folder);
+    std::thread::spawn(move || {
+        let _ = sender.send(folder.try_exists());
+    });
+    receiver.recv_timeout(EXISTS_TIMEOUT).is_ok()
+}
+
+/// Whether both paths lead to the same file, like two spellings of its name on a file system
+/// that ignores case. The paths themselves are compared, links aren't followed.
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (
+            fs::symlink_metadata(long_path(a)),
+            fs::symlink_metadata(long_path(b)),
+        ) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        match (
+            fs::canonicalize(long_path(a)),
+            fs::canonicalize(long_path(b)),
+        ) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Errors worth retrying, as network shares often report them for a moment and then recover
+fn is_transient(error: &io::Error) -> bool {
+    match error.kind() {
+        io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => true,
+        // ERROR_SHARING_VIOLATION, ERROR_UNEXP_NET_ERR, ERROR_NETNAME_DELETED, ERROR_SEM_TIMEOUT
+        _ if cfg!(windows) => matches!(error.raw_os_error(), Some(32 | 59 | 64 | 121)),
+        _ => false,
+    }
+}
+
+/// Copies a file with long path support, retrying transient errors with increasing delays.
+/// Folders are copied with everything in them, returning the total size.
+///
+/// Files are cloned instead where the file system supports it (Btrfs, XFS, APFS), which takes
+/// no time or space however large they are.
+pub fn copy(from: &Path, to: &Path) -> io::Result<u64> {
+    copy_with_progress(from, to, &mut |_, _| {})
+}
+
+/// [`copy`], calling `progress` with the bytes copied so far and the size of the file being
+/// copied after every chunk
+pub fn copy_with_progress(
+    from: &Path,
+    to: &Path,
+    progress: &mut dyn FnMut(u64, u64),
+) -> io::Result<u64> {
+    if fs::metadata(long_path(from))?.is_dir() {
+        copy_folder(from, to, progress)
+    } else {
+        copy_file(from, to, progress)
+    }
+}
+
+fn copy_file(from: &Path, to: &Path, progress: &mut dyn FnMut(u64, u64)) -> io::Result<u64> {
+    let (from, to) = (long_path(from), long_path(to));
+    if let Ok(size) = reflink(&from, &to) {
+        progress(size, size);
+        return Ok(size);
+    }
+    let mut attempt = 1;
+    loop {
+        match copy_contents(&from, &to, progress) {
+            Err(error) if attempt < COPY_ATTEMPTS && is_transient(&error) => {
+                std::thread::sleep(RETRY_DELAY * 2u32.pow(attempt - 1));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Makes `to` share the data of `from` until either is changed, failing on file systems
+/// without copy-on-write so a normal copy can be made instead
+#[cfg(target_os = "linux")]
+fn reflink(from: &Path, to: &Path) -> io::Result<u64> {
+    use std::os::{raw::c_ulong, unix::io::AsRawFd};
+    extern "C" {
+        fn ioctl(fd: i32, request: c_ulong, ...) -> i32;
+    }
+    const FICLONE: c_ulong = 0x4004_9409;
+
+    let source = fs::File::open(from)?;
+    let metadata = source.metadata()?;
+    let target = fs::File::create(to)?;
+    // SAFETY: both descriptors stay open for the call, FICLONE only reads the second argument
+    if unsafe { ioctl(target.as_raw_fd(), FICLONE, source.as_raw_fd()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    target.set_permissions(metadata.permissions())?;
+    Ok(metadata.len())
+}
+
+#[cfg(target_os = "macos")]
+fn reflink(from: &Path, to: &Path) -> io::Result<u64> {
+    use std::{
+        ffi::CString,
+        os::{
+            raw::{c_char, c_int},
+            unix::ffi::OsStrExt,
+        },
+    };
+    extern "C" {
+        fn clonefile(src: *const c_char, dst: *const c_char, flags: u32) -> c_int;
+    }
+
+    let source = CString::new(from.as_os_str().as_bytes())?;
+    let target = CString::new(to.as_os_str().as_bytes())?;
+    // clonefile won't replace an existing file, those get a normal copy
+    // SAFETY: both are valid NUL-terminated strings for the duration of the call
+    if unsafe { clonefile(source.as_ptr(), target.as_ptr(), 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(fs::metadata(to)?.len())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink(_from: &Path, _to: &Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "cloning files is not supported here",
+    ))
+}
+
+/// Copies the data and permissions of a file in large chunks. Runs of zeros are skipped rather
+/// than written, so sparse files like disk images stay sparse.
+fn copy_contents(from: &Path, to: &Path, progress: &mut dyn FnMut(u64, u64)) -> io::Result<u64> {
+    let mut source = fs::File::open(from)?;
+    let metadata = source.metadata()?;
+    let size = metadata.len();
+    let mut target = fs::File::create(to)?;
+    let mut buffer = vec![0; COPY_BUFFER];
+    let mut copied = 0;
+    loop {
+        let read = match source.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        let chunk = &buffer[..read];
+        if chunk.iter().all(|byte| *byte == 0) {
+            target.seek(SeekFrom::Current(read as i64))?;
+        } else {
+            target.write_all(chunk)?;
+        }
+        copied += read as u64;
+        progress(copied, size);
+    }
+    // Gives the file its full length when it ends in a skipped run of zeros
+    target.set_len(copied)?;
+    target.set_permissions(metadata.permissions())?;
+    Ok(copied)
+}
+
+fn copy_folder(from: &Path, to: &Path, progress: &mut dyn FnMut(u64, u64)) -> io::Result<u64> {
+    fs::create_dir_all(long_path(to))?;
+    let mut size = 0;
+    for entry in fs::read_dir(long_path(from))? {
+        let entry = entry?;
+        let (from, to) = (from.join(entry.file_name()), to.join(entry.file_name()));
+        let file_type = entry.file_type()?;
+        // Links are recreated rather than followed, links to folders could lead back up the tree
+        size += if file_type.is_symlink() {
+            copy_link(&from, &to)?;
+            0
+        } else if file_type.is_dir() {
+            copy_folder(&from, &to, progress)?
+        } else if file_type.is_file() {
+            copy_file(&from, &to, progress)?
+        } else {
+            // Fifos, sockets and devices have nothing to copy, reading a fifo would block
+            0
+        };
+    }
+    Ok(size)
+}
+
+/// Makes `to` a link to wherever the link `from` points
+#[cfg(unix)]
+fn copy_link(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_link(from: &Path, to: &Path) -> io::Result<()> {
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+
+    let (from, to) = (long_path(from), long_path(to));
+    let target = fs::read_link(&from)?;
+    if fs::metadata(&from).map_or(false, |metadata| metadata.is_dir()) {
+        symlink_dir(target, to)
+    } else {
+        symlink_file(target, to)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn copy_link(_from: &Path, _to: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "links are not supported here",
+    ))
+}
+
+/// [`fs::rename`] with long path support
+pub fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    fs::rename(long_path(from), long_path(to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn root_level_files() {
+        let path = Path::new("/file.txt");
+        assert_eq!(file_name(path).as_deref(), Some("file.txt"));
+        assert_eq!(parent_folder(path), Some(Path::new("/")));
+        assert_eq!(dialog_folder(path), "/");
+    }
+
+    #[test]
+    fn roots_have_no_name_or_parent() {
+        let root = Path::new("/");
+        assert_eq!(file_name(root), None);
+        assert_eq!(parent_folder(root), None);
+        assert_eq!(dialog_folder(root), "/");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn drive_roots() {
+        for root in [r"C:\", "C:"] {
+            let root = Path::new(root);
+            assert_eq!(file_name(root), None);
+            assert_eq!(parent_folder(root), None);
+        }
+        assert_eq!(
+            parent_folder(Path::new(r"C:\file.txt")),
+            Some(Path::new(r"C:\"))
+        );
+        assert_eq!(dialog_folder(Path::new(r"C:\")), r"C:\");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_path_prefixes() {
+        assert_eq!(long_path(Path::new(r"C:\a")), Path::new(r"\\?\C:\a"));
+        assert_eq!(
+            long_path(Path::new(r"\\server\share\a")),
+            Path::new(r"\\?\UNC\server\share\a")
+        );
+        assert_eq!(long_path(Path::new(r"\\?\C:\a")), Path::new(r"\\?\C:\a"));
+        assert_eq!(long_path(Path::new("relative")), Path::new("relative"));
+    }
+
+    #[test]
+    fn trailing_separators() {
+        let path = Path::new("folder/file.txt/");
+        assert_eq!(file_name(path).as_deref(), Some("file.txt"));
+        assert_eq!(parent_folder(path), Some(Path::new("folder")));
+    }
+
+    #[test]
+    fn reserved_device_names() {
+        for name in [
+            "CON",
+            "con.mkv",
+            "Nul.tar.gz",
+            "COM1.txt",
+            "lpt9",
+            "AUX .srt",
+        ] {
+            assert!(is_reserved_name(name), "{name}");
+        }
+        for name in ["CONSOLE.mkv", "Con Air.mkv", "COM10", "my.con", ""] {
+            assert!(!is_reserved_name(name), "{name}");
+        }
+        assert_eq!(usable_name("CON.mkv"), "CON_.mkv");
+        assert_eq!(usable_name("nul"), "nul_");
+        assert_eq!(usable_name("Pilot.mkv"), "Pilot.mkv");
+    }
+
+    #[test]
+    fn bare_names_have_no_parent() {
+        let path = Path::new("file.txt");
+        assert_eq!(parent_folder(path), None);
+        assert_eq!(dialog_folder(path), "file.txt");
+    }
+
+    #[test]
+    fn existence_is_checked_per_file() {
+        let folder = std::env::temp_dir();
+        let file = folder.join(format!("fuzzy_rename_exists_{}", std::process::id()));
+        fs::write(&file, b"").unwrap();
+        let mut exists = ExistsCheck::default();
+        let found = exists.try_exists(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+
+        assert!(found);
+        assert!(!exists.try_exists(&file).unwrap());
+        assert_eq!(exists.responding.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_in_folders_are_recreated() {
+        use std::os::unix::fs::symlink;
+
+        let root = std::env::temp_dir().join(format!("fuzzy_rename_links_{}", std::process::id()));
+        let (from, to) = (root.join("from"), root.join("to"));
+        fs::create_dir_all(from.join("Season 1")).unwrap();
+        fs::write(from.join("Season 1/Pilot.mkv"), b"pilot").unwrap();
+        symlink("Season 1", from.join("Latest")).unwrap();
+        symlink("..", from.join("Season 1/Up")).unwrap();
+
+        let copied = copy(&from, &to);
+        let latest = fs::read_link(to.join("Latest"));
+        let up = fs::read_link(to.join("Season 1/Up"));
+        let pilot = fs::read(to.join("Latest/Pilot.mkv"));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(copied.unwrap(), 5);
+        assert_eq!(latest.unwrap(), Path::new("Season 1"));
+        assert_eq!(up.unwrap(), Path::new(".."));
+        assert_eq!(pilot.unwrap(), b"pilot");
+    }
+}
@@ -0,0 +1,332 @@
+//! Renames a batch of files in place so it either happens completely or is undone. Chains like
+//! `1 -> 2` with `2 -> 3` are renamed from the end, cycles like `A -> B` with `B -> A` go through
+//! a temporary name, and a failure halfway through puts everything back.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::paths;
+
+pub struct Outcome {
+    pub renamed: usize,
+    /// Files that were in the way and were replaced
+    pub replaced: usize,
+}
+
+pub struct Failure {
+    /// The file that couldn't be renamed, and why
+    pub path: PathBuf,
+    pub error: io::Error,
+    /// Files that couldn't be put back where they were, left under their temporary names
+    pub stranded: Vec<PathBuf>,
+}
+
+/// One move of a batch, by index into the renames
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Step {
+    Move(usize),
+    /// Out of the way of the rename that needs its name, to break a cycle
+    ToTemporary(usize),
+    FromTemporary(usize),
+}
+
+/// Orders the renames so none is renamed onto a file of the batch that hasn't moved yet
+fn plan(renames: &[(PathBuf, PathBuf)]) -> Vec<Step> {
+    let sources: HashMap<&Path, usize> = renames
+        .iter()
+        .enumerate()
+        .map(|(index, (from, _))| (from.as_path(), index))
+        .collect();
+    // Destinations are unique, so each rename waits on at most one other and the renames form
+    // separate chains and cycles
+    let mut waits_on = vec![None; renames.len()];
+    let mut waited_on_by = vec![None; renames.len()];
+    for (index, (_, to)) in renames.iter().enumerate() {
+        if let Some(&other) = sources.get(to.as_path()).filter(|&&other| other != index) {
+            waits_on[index] = Some(other);
+            waited_on_by[other] = Some(index);
+        }
+    }
+
+    let mut steps = Vec::with_capacity(renames.len());
+    let mut done = vec![false; renames.len()];
+    // Chains, starting from the rename whose destination is free
+    for end in (0..renames.len()).filter(|&index| waits_on[index].is_none()) {
+        let mut next = Some(end);
+        while let Some(index) = next {
+            steps.push(Step::Move(index));
+            done[index] = true;
+            next = waited_on_by[index];
+        }
+    }
+    // Whatever is left is in a cycle
+    for start in 0..renames.len() {
+        if done[start] {
+            continue;
+        }
+        steps.push(Step::ToTemporary(start));
+        done[start] = true;
+        let mut next = waited_on_by[start];
+        while let Some(index) = next.filter(|&index| index != start) {
+            steps.push(Step::Move(index));
+            done[index] = true;
+            next = waited_on_by[index];
+        }
+        steps.push(Step::FromTemporary(start));
+    }
+    steps
+}
+
+/// How the renames depend on each other, for confirming a batch: the renames that have to wait
+/// for another, and the number of cycles among them
+pub fn dependencies(renames: &[(PathBuf, PathBuf)]) -> (usize, usize) {
+    let sources: HashSet<&Path> = renames.iter().map(|(from, _)| from.as_path()).collect();
+    let waiting = renames
+        .iter()
+        .filter(|(from, to)| from != to && sources.contains(to.as_path()))
+        .count();
+    let cycles = plan(renames)
+        .iter()
+        .filter(|step| matches!(step, Step::ToTemporary(_)))
+        .count();
+    (waiting, cycles)
+}
+
+/// A hidden name beside `path` that this batch can use while it moves files around
+fn temporary_name(path: &Path, index: usize) -> PathBuf {
+    let name = paths::file_name(path).unwrap_or_default();
+    path.with_file_name(format!(".{name}.{}-{index}.renaming", std::process::id()))
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(paths::long_path(path))?.is_dir() {
+        fs::remove_dir_all(paths::long_path(path))
+    } else {
+        fs::remove_file(paths::long_path(path))
+    }
+}
+
+/// Moves done in this batch, undone in reverse order on failure
+#[derive(Default)]
+struct Journal(Vec<(PathBuf, PathBuf)>);
+
+impl Journal {
+    fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        paths::rename(from, to)?;
+        self.0.push((from.to_owned(), to.to_owned()));
+        Ok(())
+    }
+
+    /// Undoes every move, returning the files that couldn't be moved back
+    fn roll_back(self) -> Vec<PathBuf> {
+        let mut stranded = vec![];
+        for (from, to) in self.0.into_iter().rev() {
+            if let Err(error) = paths::rename(&to, &from) {
+                eprintln!("Could not undo rename: {error} ({to:?} -> {from:?})");
+                stranded.push(to);
+            }
+        }
+        stranded
+    }
+}
+
+/// Renames each file to its destination. Files already at a destination are replaced, but only
+/// removed once the whole batch has succeeded.
+pub fn rename_all(renames: &[(PathBuf, PathBuf)]) -> Result<Outcome, Failure> {
+    // Compared without case, as two destinations differing only in case are the same file on
+    // Windows and macOS, and the second rename would replace the first
+    let mut destinations = HashMap::new();
+    for (from, to) in renames {
+        if let Some(other) = destinations.insert(to.to_string_lossy().to_lowercase(), from) {
+            return Err(Failure {
+                path: from.clone(),
+                error: io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "{} and {} are both renamed to {}",
+                        other.display(),
+                        from.display(),
+                        to.display()
+                    ),
+                ),
+                stranded: vec![],
+            });
+        }
+    }
+
+    let mut journal = Journal::default();
+    let fail = |journal: Journal, path: &Path, error| Failure {
+        path: path.to_owned(),
+        error,
+        stranded: journal.roll_back(),
+    };
+
+    let mut replaced = vec![];
+    let mut exists = paths::ExistsCheck::default();
+    for step in plan(renames) {
+        let (index, from, to) = match step {
+            Step::ToTemporary(index) => {
+                let from = &renames[index].0;
+                if let Err(error) = journal.rename(from, &temporary_name(from, index)) {
+                    return Err(fail(journal, from, error));
+                }
+                continue;
+            }
+            Step::Move(index) => (index, renames[index].0.clone(), &renames[index].1),
+            Step::FromTemporary(index) => (
+                index,
+                temporary_name(&renames[index].0, index),
+                &renames[index].1,
+            ),
+        };
+        let source = &renames[index].0;
+        // Anything else in the way is kept until the whole batch succeeded. A change of case finds
+        // the file itself there on Windows and macOS, but another file where case matters.
+        if from != *to && !paths::same_file(&from, to) {
+            match exists.try_exists(to) {
+                Ok(true) => {
+                    let backup = temporary_name(to, renames.len() + index);
+                    if let Err(error) = journal.rename(to, &backup) {
+                        return Err(fail(journal, source, error));
+                    }
+                    replaced.push(backup);
+                }
+                Ok(false) => {}
+                Err(error) => return Err(fail(journal, source, error)),
+            }
+        }
+        if let Err(error) = journal.rename(&from, to) {
+            return Err(fail(journal, source, error));
+        }
+    }
+
+    for backup in &replaced {
+        if let Err(error) = remove(backup) {
+            eprintln!("Could not remove replaced file: {error} ({backup:?})");
+        }
+    }
+    Ok(Outcome {
+        renamed: renames.len(),
+        replaced: replaced.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renames(pairs: &[(&str, &str)]) -> Vec<(PathBuf, PathBuf)> {
+        pairs
+            .iter()
+            .map(|(from, to)| (PathBuf::from(from), PathBuf::from(to)))
+            .collect()
+    }
+
+    /// An empty folder for one test, removed when it ends
+    struct TestFolder(PathBuf);
+
+    impl TestFolder {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("fuzzy_rename_{name}_{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, name: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        }
+
+        fn read(&self, name: &str) -> String {
+            fs::read_to_string(self.0.join(name)).unwrap()
+        }
+    }
+
+    impl Drop for TestFolder {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn chains_start_at_the_free_name() {
+        let batch = renames(&[("1.mkv", "2.mkv"), ("2.mkv", "3.mkv"), ("a.mkv", "b.mkv")]);
+        assert_eq!(plan(&batch), [Step::Move(1), Step::Move(0), Step::Move(2)]);
+        assert_eq!(dependencies(&batch), (1, 0));
+    }
+
+    #[test]
+    fn cycles_go_through_a_temporary_name() {
+        let batch = renames(&[("a", "b"), ("b", "c"), ("c", "a"), ("same", "same")]);
+        assert_eq!(
+            plan(&batch),
+            [
+                Step::Move(3),
+                Step::ToTemporary(0),
+                Step::Move(2),
+                Step::Move(1),
+                Step::FromTemporary(0)
+            ]
+        );
+        assert_eq!(dependencies(&batch), (3, 1));
+    }
+
+    #[test]
+    fn failures_put_everything_back() {
+        let folder = TestFolder::new("roll_back");
+        let a = folder.write("a.txt", "a");
+        let b = folder.write("b.txt", "b");
+        let taken = folder.write("taken.txt", "taken");
+        let batch = vec![(a, taken), (b, folder.0.join("missing").join("b.txt"))];
+        let failure = rename_all(&batch).err().unwrap();
+        assert!(failure.stranded.is_empty());
+        assert_eq!(folder.read("a.txt"), "a");
+        assert_eq!(folder.read("b.txt"), "b");
+        assert_eq!(folder.read("taken.txt"), "taken");
+    }
+
+    #[test]
+    fn destinations_differing_in_case_are_rejected() {
+        let folder = TestFolder::new("case_collision");
+        let a = folder.write("a.txt", "a");
+        let b = folder.write("b.txt", "b");
+        let batch = vec![
+            (a, folder.0.join("Movie.txt")),
+            (b, folder.0.join("movie.txt")),
+        ];
+        let failure = rename_all(&batch).err().unwrap();
+        let message = failure.error.to_string();
+        assert!(message.contains("a.txt") && message.contains("b.txt"));
+        assert_eq!(folder.read("a.txt"), "a");
+        assert_eq!(folder.read("b.txt"), "b");
+    }
+
+    #[test]
+    fn case_changes_keep_other_files() {
+        let folder = TestFolder::new("case_change");
+        let lower = folder.write("a.txt", "lower");
+        let upper = folder.0.join("A.txt");
+        let case_sensitive = !upper.exists();
+        if case_sensitive {
+            folder.write("A.txt", "upper");
+        }
+        let b = folder.write("b.txt", "b");
+        let batch = vec![(lower, upper), (b, folder.0.join("missing").join("b.txt"))];
+        assert!(rename_all(&batch).is_err());
+        assert_eq!(folder.read("a.txt"), "lower");
+        if case_sensitive {
+            assert_eq!(folder.read("A.txt"), "upper");
+        }
+
+        // A change of case alone goes through either way
+        let outcome = rename_all(&batch[..1]).ok().unwrap();
+        assert_eq!(outcome.replaced, usize::from(case_sensitive));
+        assert_eq!(folder.read("A.txt"), "lower");
+    }
+}
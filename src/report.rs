@@ -0,0 +1,109 @@
+//! Standalone HTML report of the proposed renames, for review outside the app.
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Confidence {
+    Trusted,
+    NeedsReview,
+    Unmatched,
+    Manual,
+}
+
+pub struct Row<'a> {
+    pub source: &'a str,
+    pub score: Option<f32>,
+    pub confidence: Confidence,
+    pub choice: &'a str,
+    pub rename: String,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn render_html(title: &str, settings: &[(&str, String)], rows: &[Row<'_>]) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape(title)));
+    html.push_str(
+        "<style>\n\
+         body { font-family: sans-serif; margin: 2em; }\n\
+         table { border-collapse: collapse; }\n\
+         td, th { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }\n\
+         .trusted { background: #d4f4d4; }\n\
+         .review { background: #fbeec2; }\n\
+         .unmatched { background: #f6d0d0; }\n\
+         .manual { background: #d6e4f6; }\n\
+         </style>\n</head>\n<body>\n",
+    );
+    html.push_str(&format!("<h1>{}</h1>\n", escape(title)));
+
+    html.push_str("<h2>Settings</h2>\n<table>\n");
+    for (name, value) in settings {
+        html.push_str(&format!(
+            "<tr><th>{}</th><td>{}</td></tr>\n",
+            escape(name),
+            escape(value)
+        ));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str(&format!("<h2>Renames ({})</h2>\n<table>\n", rows.len()));
+    html.push_str(
+        "<tr><th>Source Name</th><th>Similarity</th><th>Closest Match</th><th>Renamed File</th></tr>\n",
+    );
+    for row in rows {
+        let (class, score) = match (&row.confidence, row.score) {
+            (Confidence::Manual, _) | (_, None) => ("manual", "Manual".to_owned()),
+            (Confidence::Trusted, Some(s)) => ("trusted", format!("{:.1}%", 100.0 * s)),
+            (Confidence::NeedsReview, Some(s)) => ("review", format!("{:.1}%", 100.0 * s)),
+            (Confidence::Unmatched, Some(s)) => ("unmatched", format!("{:.1}%", 100.0 * s)),
+        };
+        html.push_str(&format!(
+            "<tr class=\"{class}\"><td>{}</td><td>{score}</td><td>{}</td><td>{}</td></tr>\n",
+            escape(row.source),
+            escape(row.choice),
+            escape(&row.rename)
+        ));
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// The mapping as CSV, one row per source: `source,choice,score,rename`
+pub fn render_csv(rows: &[Row<'_>]) -> String {
+    let mut csv = String::from("source,choice,score,rename\n");
+    for row in rows {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            csv_field(row.source),
+            csv_field(row.choice),
+            row.score.map_or("".to_owned(), |s| format!("{s:.4}")),
+            csv_field(&row.rename)
+        ));
+    }
+    csv
+}
+
+/// Scores in long form, one row per pair: `source,choice,score`
+pub fn render_matrix_csv(cells: &[(&str, &str, f32)]) -> String {
+    let mut csv = String::from("source,choice,score\n");
+    for (source, choice, score) in cells {
+        csv.push_str(&format!(
+            "{},{},{score:.4}\n",
+            csv_field(source),
+            csv_field(choice)
+        ));
+    }
+    csv
+}
@@ -0,0 +1,89 @@
+//! Reads large folders on a background thread, so importing them doesn't freeze the window.
+
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::task::{Reporter, Task};
+
+/// Entries are handed over in batches of at most this many, or whatever was read in `BATCH_TIME`
+const BATCH_SIZE: usize = 256;
+const BATCH_TIME: Duration = Duration::from_millis(100);
+
+pub enum Progress<T> {
+    Batch(Vec<T>),
+    /// How many entries the folder listing failed on, sent once before `Done` when any did
+    Unlisted(usize),
+    /// The scan ended, `Ok(true)` when it was cancelled
+    Done(io::Result<bool>),
+}
+
+pub struct FolderScan<T> {
+    pub folder: PathBuf,
+    task: Task<Progress<T>>,
+}
+
+impl<T: Send + 'static> FolderScan<T> {
+    /// Starts listing the entries of `folder`, passing each to `read` on the scan thread and
+    /// waking the UI whenever a batch is ready
+    pub fn start(
+        folder: PathBuf,
+        ctx: egui::Context,
+        read: impl Fn(PathBuf) -> T + Send + 'static,
+    ) -> Self {
+        let thread_folder = folder.clone();
+        let task = Task::spawn(ctx, move |reporter| {
+            let result = scan(&thread_folder, &read, &reporter);
+            reporter.send(Progress::Done(result));
+        });
+        Self { folder, task }
+    }
+}
+
+impl<T> FolderScan<T> {
+    pub fn cancel(&self) {
+        self.task.cancel();
+    }
+
+    /// Everything the scan reported since the last call
+    pub fn poll(&self) -> Vec<Progress<T>> {
+        self.task.poll()
+    }
+}
+
+fn scan<T>(
+    folder: &std::path::Path,
+    read: &impl Fn(PathBuf) -> T,
+    reporter: &Reporter<Progress<T>>,
+) -> io::Result<bool> {
+    let mut batch = vec![];
+    let mut batch_start = Instant::now();
+    let mut unlisted = 0;
+    for item in fs::read_dir(folder)? {
+        if reporter.is_cancelled() {
+            return Ok(true);
+        }
+        let Ok(item) = item else {
+            unlisted += 1;
+            continue;
+        };
+        // Checking an entry against the import settings, reading its details and scoring it
+        // all happen here, so the UI only has to add it
+        batch.push(read(item.path()));
+        if batch.len() >= BATCH_SIZE || (!batch.is_empty() && batch_start.elapsed() > BATCH_TIME) {
+            if !reporter.send(Progress::Batch(std::mem::take(&mut batch))) {
+                return Ok(true);
+            }
+            batch_start = Instant::now();
+        }
+    }
+    if !batch.is_empty() {
+        reporter.send(Progress::Batch(batch));
+    }
+    if unlisted > 0 {
+        reporter.send(Progress::Unlisted(unlisted));
+    }
+    Ok(false)
+}
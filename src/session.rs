@@ -0,0 +1,69 @@
+//! Saved source and choice lists, so curated matches can be picked up again later.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct SessionSource {
+    pub path: PathBuf,
+    /// Index into [`Session::choices`], `Some(None)` when set to not use a match
+    #[serde(default)]
+    pub manual_choice: Option<Option<usize>>,
+    #[serde(default)]
+    pub destination: Option<PathBuf>,
+    #[serde(default)]
+    pub choice_group: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Edited search text, when it differs from the file name
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub locked: bool,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct SessionChoice {
+    pub name: String,
+    /// Empty for choices that only exist as a name in a list
+    #[serde(default)]
+    pub path: PathBuf,
+    #[serde(default)]
+    pub rename_to: Option<String>,
+    #[serde(default)]
+    pub group: String,
+    #[serde(default)]
+    pub excluded: bool,
+    /// Path of the only source allowed to match this choice
+    #[serde(default)]
+    pub owner: Option<PathBuf>,
+    /// Checksum, when known; restored only for choices without a file, e.g. from a DAT
+    #[serde(default)]
+    pub crc: Option<u32>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+pub struct Session {
+    pub sources: Vec<SessionSource>,
+    pub choices: Vec<SessionChoice>,
+}
+
+impl Session {
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        serde_json::from_str(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_json()?)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
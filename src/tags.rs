@@ -0,0 +1,275 @@
+//! Writes the title tag of audio files, so players show the same name as the file: ID3 tags of
+//! MP3s and Vorbis comments of FLACs. Other formats are left alone.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::paths;
+
+/// Written in Vorbis comment blocks this app adds
+const VENDOR: &str = "fuzzy_rename_rs";
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Sets the title of the audio file at `path`. Returns `Ok(false)` for files that aren't MP3 or
+/// FLAC, which are left unchanged.
+pub fn write_title(path: &Path, title: &str) -> io::Result<bool> {
+    let is_mp3 = path
+        .extension()
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("mp3"));
+    let is_flac = path
+        .extension()
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("flac"));
+    if !is_mp3 && !is_flac {
+        return Ok(false);
+    }
+
+    let bytes = fs::read(paths::long_path(path))?;
+    let permissions = fs::metadata(paths::long_path(path))?.permissions();
+    let tagged = if bytes.starts_with(b"fLaC") {
+        flac_with_title(&bytes, title)?
+    } else if is_mp3 {
+        mp3_with_title(&bytes, title)?
+    } else {
+        return Err(invalid("Not a FLAC file"));
+    };
+
+    // Written beside the file first, so a failure can't leave it half written
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tagging");
+    let temporary = PathBuf::from(temporary);
+    let written = fs::write(paths::long_path(&temporary), tagged)
+        .and_then(|_| fs::set_permissions(paths::long_path(&temporary), permissions));
+    if let Err(error) = written.and_then(|_| paths::rename(&temporary, path)) {
+        let _ = fs::remove_file(paths::long_path(&temporary));
+        return Err(error);
+    }
+    Ok(true)
+}
+
+/// A size stored in 7 bits per byte, as ID3v2 headers use
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |size, byte| (size << 7) | (*byte & 0x7F) as usize)
+}
+
+fn to_syncsafe(size: usize) -> [u8; 4] {
+    [21, 14, 7, 0].map(|shift| ((size >> shift) & 0x7F) as u8)
+}
+
+/// The ID3v2 frames of a tag, with the title frame replaced
+fn id3_frames(tag: &[u8], version: u8, title: &str) -> io::Result<Vec<u8>> {
+    let mut frames = vec![];
+    let mut at = 0;
+    while at + 10 <= tag.len() && tag[at] != 0 {
+        let size_bytes = &tag[at + 4..at + 8];
+        let size = if version == 4 {
+            syncsafe(size_bytes)
+        } else {
+            u32::from_be_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]])
+                as usize
+        };
+        let end = at + 10 + size;
+        if end > tag.len() {
+            return Err(invalid("Damaged ID3 tag"));
+        }
+        if &tag[at..at + 4] != b"TIT2" {
+            frames.extend_from_slice(&tag[at..end]);
+        }
+        at = end;
+    }
+
+    // UTF-8 only exists from ID3v2.4, older versions get UTF-16 with a byte order mark
+    let text: Vec<u8> = if version == 4 {
+        [&[3], title.as_bytes()].concat()
+    } else {
+        let mut text = vec![1, 0xFF, 0xFE];
+        text.extend(title.encode_utf16().flat_map(u16::to_le_bytes));
+        text
+    };
+    frames.extend_from_slice(b"TIT2");
+    if version == 4 {
+        frames.extend_from_slice(&to_syncsafe(text.len()));
+    } else {
+        frames.extend_from_slice(&(text.len() as u32).to_be_bytes());
+    }
+    frames.extend_from_slice(&[0, 0]);
+    frames.extend_from_slice(&text);
+    Ok(frames)
+}
+
+/// An MP3 with `title` in its ID3v2 tag (added as ID3v2.3 if there is none), and in its
+/// ID3v1 tag if it has one
+fn mp3_with_title(bytes: &[u8], title: &str) -> io::Result<Vec<u8>> {
+    let (version, frames, audio) = if bytes.starts_with(b"ID3") && bytes.len() >= 10 {
+        let (version, flags) = (bytes[3], bytes[5]);
+        // Unsynchronised tags and extended headers are rare, and not worth rewriting
+        if !(3..=4).contains(&version) || flags & 0xC0 != 0 {
+            return Err(invalid("Unsupported ID3 tag"));
+        }
+        let footer = if flags & 0x10 != 0 { 10 } else { 0 };
+        let end = 10 + syncsafe(&bytes[6..10]);
+        if end + footer > bytes.len() {
+            return Err(invalid("Damaged ID3 tag"));
+        }
+        let frames = id3_frames(&bytes[10..end], version, title)?;
+        (version, frames, &bytes[end + footer..])
+    } else {
+        (3, id3_frames(&[], 3, title)?, bytes)
+    };
+
+    let mut tagged = Vec::with_capacity(10 + frames.len() + audio.len());
+    tagged.extend_from_slice(&[b'I', b'D', b'3', version, 0, 0]);
+    tagged.extend_from_slice(&to_syncsafe(frames.len()));
+    tagged.extend_from_slice(&frames);
+    tagged.extend_from_slice(audio);
+
+    // ID3v1 keeps 30 Latin-1 characters at a fixed place in the last 128 bytes
+    let length = tagged.len();
+    if length >= 128 && tagged[length - 128..].starts_with(b"TAG") {
+        let field = &mut tagged[length - 125..length - 95];
+        field.fill(0);
+        for (byte, c) in field.iter_mut().zip(title.chars()) {
+            *byte = u8::try_from(c).unwrap_or(b'?');
+        }
+    }
+    Ok(tagged)
+}
+
+fn u32_le(bytes: &[u8], at: usize) -> io::Result<usize> {
+    bytes
+        .get(at..at + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or_else(|| invalid("Damaged Vorbis comment"))
+}
+
+/// A Vorbis comment block with every `TITLE=` comment replaced by `title`
+fn vorbis_comments(block: Option<&[u8]>, title: &str) -> io::Result<Vec<u8>> {
+    let mut vendor = VENDOR.as_bytes();
+    let mut comments: Vec<&[u8]> = vec![];
+    if let Some(block) = block {
+        let vendor_length = u32_le(block, 0)?;
+        vendor = block
+            .get(4..4 + vendor_length)
+            .ok_or_else(|| invalid("Damaged Vorbis comment"))?;
+        let mut at = 4 + vendor_length;
+        let count = u32_le(block, at)?;
+        at += 4;
+        for _ in 0..count {
+            let length = u32_le(block, at)?;
+            let comment = block
+                .get(at + 4..at + 4 + length)
+                .ok_or_else(|| invalid("Damaged Vorbis comment"))?;
+            let is_title = comment.len() >= 6 && comment[..6].eq_ignore_ascii_case(b"TITLE=");
+            if !is_title {
+                comments.push(comment);
+            }
+            at += 4 + length;
+        }
+    }
+    let title = format!("TITLE={title}");
+    comments.push(title.as_bytes());
+
+    let mut block = vec![];
+    block.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    block.extend_from_slice(vendor);
+    block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        block.extend_from_slice(comment);
+    }
+    Ok(block)
+}
+
+/// A FLAC with `title` in its Vorbis comment block, added after the stream info if there is none
+fn flac_with_title(bytes: &[u8], title: &str) -> io::Result<Vec<u8>> {
+    // Each metadata block has a type, with the high bit set on the last one, and a 24-bit size
+    let mut blocks: Vec<(u8, Vec<u8>)> = vec![];
+    let mut at = 4;
+    loop {
+        let header = bytes
+            .get(at..at + 4)
+            .ok_or_else(|| invalid("Damaged FLAC metadata"))?;
+        let kind = header[0] & 0x7F;
+        let size = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let data = bytes
+            .get(at + 4..at + 4 + size)
+            .ok_or_else(|| invalid("Damaged FLAC metadata"))?;
+        blocks.push((kind, data.to_vec()));
+        at += 4 + size;
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+
+    const VORBIS_COMMENT: u8 = 4;
+    match blocks.iter().position(|(kind, _)| *kind == VORBIS_COMMENT) {
+        Some(index) => blocks[index].1 = vorbis_comments(Some(&blocks[index].1), title)?,
+        None => blocks.insert(1, (VORBIS_COMMENT, vorbis_comments(None, title)?)),
+    }
+
+    let mut tagged = b"fLaC".to_vec();
+    let last = blocks.len() - 1;
+    for (index, (kind, data)) in blocks.iter().enumerate() {
+        let size = (data.len() as u32).to_be_bytes();
+        if size[0] != 0 {
+            return Err(invalid("Metadata too large"));
+        }
+        let kind = if index == last { kind | 0x80 } else { *kind };
+        tagged.extend_from_slice(&[kind, size[1], size[2], size[3]]);
+        tagged.extend_from_slice(data);
+    }
+    tagged.extend_from_slice(&bytes[at..]);
+    Ok(tagged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mp3_titles() {
+        let audio = [0xFF, 0xFB, 0x90, 0x00];
+        let tagged = mp3_with_title(&audio, "Ké").unwrap();
+        assert_eq!(&tagged[..6], b"ID3\x03\x00\x00");
+        assert_eq!(&tagged[10..14], b"TIT2");
+        assert_eq!(&tagged[tagged.len() - 4..], audio);
+
+        // Replaced, not added twice
+        let retagged = mp3_with_title(&tagged, "Ké").unwrap();
+        assert_eq!(retagged, tagged);
+    }
+
+    #[test]
+    fn flac_titles() {
+        let mut flac = b"fLaC".to_vec();
+        flac.extend_from_slice(&[0x80, 0, 0, 2, 1, 2]);
+        flac.extend_from_slice(b"frames");
+        let tagged = flac_with_title(&flac, "Song").unwrap();
+        assert_eq!(&tagged[4..10], &[0x00, 0, 0, 2, 1, 2]);
+        assert_eq!(tagged[10], 0x84);
+        assert!(tagged.ends_with(b"TITLE=Songframes"));
+        assert_eq!(flac_with_title(&tagged, "Song").unwrap(), tagged);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tagged_files_keep_their_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path =
+            std::env::temp_dir().join(format!("fuzzy_rename_tag_{}.mp3", std::process::id()));
+        fs::write(&path, [0xFF, 0xFB, 0x90, 0x00]).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        let tagged = write_title(&path, "Song");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        fs::remove_file(&path).unwrap();
+        assert!(tagged.unwrap());
+        assert_eq!(mode & 0o777, 0o640);
+    }
+}
@@ -0,0 +1,85 @@
+//! Long work run on a background thread: it reports progress the UI picks up each frame, and
+//! checks whether it was cancelled between steps.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc, Arc,
+};
+
+pub struct Task<P> {
+    receiver: mpsc::Receiver<P>,
+    cancel: Arc<AtomicBool>,
+}
+
+/// The side of a [`Task`] given to the work itself
+pub struct Reporter<P> {
+    sender: mpsc::Sender<P>,
+    cancel: Arc<AtomicBool>,
+    ctx: egui::Context,
+}
+
+impl<P: Send + 'static> Task<P> {
+    /// Starts `work` on its own thread. The web has no threads, there it runs before this returns.
+    pub fn spawn(ctx: egui::Context, work: impl FnOnce(Reporter<P>) + Send + 'static) -> Self {
+        let (task, reporter) = Self::new(ctx);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::spawn(move || work(reporter));
+        #[cfg(target_arch = "wasm32")]
+        work(reporter);
+
+        task
+    }
+
+    /// Starts `work` on the browser's event loop, for work that waits on the browser
+    #[cfg(target_arch = "wasm32")]
+    pub fn spawn_local<F>(ctx: egui::Context, work: impl FnOnce(Reporter<P>) -> F) -> Self
+    where
+        F: std::future::Future<Output = ()> + 'static,
+    {
+        let (task, reporter) = Self::new(ctx);
+        wasm_bindgen_futures::spawn_local(work(reporter));
+        task
+    }
+
+    fn new(ctx: egui::Context) -> (Self, Reporter<P>) {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let reporter = Reporter {
+            sender,
+            cancel: cancel.clone(),
+            ctx,
+        };
+        (Self { receiver, cancel }, reporter)
+    }
+}
+
+impl<P> Task<P> {
+    /// Asks the work to stop at its next check
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Everything reported since the last call
+    pub fn poll(&self) -> Vec<P> {
+        self.receiver.try_iter().collect()
+    }
+
+    /// Whether the work is still going; it ended, or panicked, once its reporter was dropped
+    pub fn is_running(&self) -> bool {
+        Arc::strong_count(&self.cancel) > 1
+    }
+}
+
+impl<P> Reporter<P> {
+    /// Hands `progress` to the UI and wakes it; false once the task was dropped
+    pub fn send(&self, progress: P) -> bool {
+        let sent = self.sender.send(progress).is_ok();
+        self.ctx.request_repaint();
+        sent
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,52 @@
+//! Output name templates, e.g. `{n:02} - {name}`.
+
+/// Widths are cut to this, no file name is longer
+const MAX_WIDTH: usize = 255;
+
+pub enum Value<'a> {
+    Text(&'a str),
+    Number(usize),
+}
+
+/// Formats a value with an optional width spec (`3` pads with spaces, `03` with zeros).
+fn format_value(value: &Value<'_>, spec: &str) -> String {
+    match value {
+        Value::Text(text) => (*text).to_owned(),
+        Value::Number(number) => {
+            let width = spec.parse::<usize>().unwrap_or(0).min(MAX_WIDTH);
+            if spec.starts_with('0') {
+                format!("{number:0width$}")
+            } else {
+                format!("{number:width$}")
+            }
+        }
+    }
+}
+
+/// Replaces `{token}` and `{token:spec}` with their values; unknown tokens are kept as written.
+pub fn render(template: &str, values: &[(&str, Value<'_>)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let token = &after[..end];
+        let (key, spec) = token.split_once(':').unwrap_or((token, ""));
+        match values.iter().find(|(k, _)| *k == key) {
+            Some((_, value)) => output.push_str(&format_value(value, spec)),
+            None => {
+                output.push('{');
+                output.push_str(token);
+                output.push('}');
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    output.push_str(rest);
+    output
+}